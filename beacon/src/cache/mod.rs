@@ -0,0 +1,242 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+pub(crate) mod tree;
+
+pub use self::tree::TreeHashCache;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::primitives::{H256, Epoch, Uint, ValidatorId, ValidatorIndex};
+use crate::types::Validator;
+use crate::{Config, Error, utils};
+
+/// Maximum number of shufflings kept in the committee cache.
+const MAX_SHUFFLINGS: usize = 16;
+
+/// Validator public key to index cache.
+///
+/// Validators are only ever appended to the registry, and deposits are
+/// processed in eth1 order, so registries of all states on the same chain
+/// share their common prefix. Lookups must be bounded by the registry length
+/// of the state in use.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct PubkeyCache {
+	indices: BTreeMap<ValidatorId, ValidatorIndex>,
+	pubkeys: Vec<ValidatorId>,
+}
+
+impl PubkeyCache {
+	/// Create a new empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of validators in the cache.
+	pub fn len(&self) -> usize {
+		self.pubkeys.len()
+	}
+
+	/// Whether the cache is empty.
+	pub fn is_empty(&self) -> bool {
+		self.pubkeys.is_empty()
+	}
+
+	/// Get validator index from public key.
+	pub fn get(&self, pubkey: &ValidatorId) -> Option<ValidatorIndex> {
+		self.indices.get(pubkey).cloned()
+	}
+
+	/// Append a new validator public key.
+	pub fn push(&mut self, pubkey: ValidatorId) -> ValidatorIndex {
+		let index = self.pubkeys.len() as ValidatorIndex;
+		self.indices.entry(pubkey.clone()).or_insert(index);
+		self.pubkeys.push(pubkey);
+		index
+	}
+
+	/// Sync the cache with the given registry. The cache may run ahead of
+	/// the registry, but is rebuilt if the two disagree on their common
+	/// prefix.
+	pub fn update(&mut self, validators: &[Validator]) {
+		let common = core::cmp::min(self.pubkeys.len(), validators.len());
+		if common > 0 && self.pubkeys[common - 1] != validators[common - 1].pubkey {
+			*self = Self::new();
+		}
+
+		if validators.len() > self.pubkeys.len() {
+			for validator in &validators[self.pubkeys.len()..] {
+				self.push(validator.pubkey.clone());
+			}
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Shuffling {
+	epoch: Epoch,
+	active: Vec<ValidatorIndex>,
+	shuffled: Vec<ValidatorIndex>,
+}
+
+/// Committee cache, holding whole-epoch shufflings keyed by seed and active
+/// validator indices.
+///
+/// The seed already commits to the epoch, so a shuffling can be reused by all
+/// states on the same chain. Shufflings are computed on first use, which
+/// allows lookups through a shared reference.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct CommitteeCache {
+	shufflings: RefCell<BTreeMap<H256, Shuffling>>,
+}
+
+impl CommitteeCache {
+	/// Create a new empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of shufflings in the cache.
+	pub fn len(&self) -> usize {
+		self.shufflings.borrow().len()
+	}
+
+	/// Whether the cache is empty.
+	pub fn is_empty(&self) -> bool {
+		self.shufflings.borrow().is_empty()
+	}
+
+	/// Get committee `index` out of `count` committees of the epoch, computing
+	/// the epoch shuffling if it is not yet cached.
+	pub fn committee<C: Config>(
+		&self,
+		epoch: Epoch,
+		seed: H256,
+		indices: &[ValidatorIndex],
+		index: Uint,
+		count: Uint,
+	) -> Result<Vec<ValidatorIndex>, Error> {
+		let start = ((indices.len() as u64 * index) / count) as usize;
+		let end = ((indices.len() as u64 * (index + 1)) / count) as usize;
+		if end > indices.len() {
+			return Err(Error::IndexOutOfRange)
+		}
+
+		let mut shufflings = self.shufflings.borrow_mut();
+		let cached = shufflings.get(&seed)
+			.map(|shuffling| &shuffling.active[..] == indices)
+			.unwrap_or(false);
+
+		if !cached {
+			let shuffled = utils::shuffle_list::<C>(indices.to_vec(), seed)?;
+
+			shufflings.remove(&seed);
+			while shufflings.len() >= MAX_SHUFFLINGS {
+				let oldest = shufflings.iter()
+					.min_by_key(|(_, shuffling)| shuffling.epoch)
+					.map(|(seed, _)| *seed)
+					.expect("Cache is not empty; qed");
+				shufflings.remove(&oldest);
+			}
+
+			shufflings.insert(seed, Shuffling {
+				epoch,
+				active: indices.to_vec(),
+				shuffled,
+			});
+		}
+
+		let shuffling = shufflings.get(&seed).expect("Shuffling is inserted above; qed");
+		Ok(shuffling.shuffled[start..end].to_vec())
+	}
+}
+
+/// Caches reused across beacon executives on the same chain.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct BeaconCache {
+	/// Validator public key to index cache.
+	pub pubkeys: PubkeyCache,
+	/// Epoch committee cache.
+	pub committees: CommitteeCache,
+	/// State tree hash cache.
+	pub tree: TreeHashCache,
+}
+
+impl BeaconCache {
+	/// Create a new empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn validator(byte: u8) -> Validator {
+		Validator {
+			pubkey: ValidatorId::repeat_byte(byte),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn pubkey_cache_follows_registry() {
+		let mut cache = PubkeyCache::new();
+		let registry = [validator(1), validator(2), validator(3)];
+
+		cache.update(&registry[..2]);
+		assert_eq!(cache.len(), 2);
+		assert_eq!(cache.get(&ValidatorId::repeat_byte(3)), None);
+
+		cache.update(&registry[..]);
+		assert_eq!(cache.get(&ValidatorId::repeat_byte(3)), Some(2));
+
+		cache.update(&registry[..1]);
+		assert_eq!(cache.len(), 3);
+
+		let fork = [validator(1), validator(4)];
+		cache.update(&fork[..]);
+		assert_eq!(cache.len(), 2);
+		assert_eq!(cache.get(&ValidatorId::repeat_byte(2)), None);
+		assert_eq!(cache.get(&ValidatorId::repeat_byte(4)), Some(1));
+	}
+
+	#[test]
+	fn committee_cache_matches_computed_committees() {
+		type C = crate::MinimalConfig;
+
+		let cache = CommitteeCache::new();
+		let indices = (0..300).map(|i| i * 2).collect::<Vec<ValidatorIndex>>();
+		let seed = H256::repeat_byte(42);
+
+		for index in 0..8 {
+			assert_eq!(
+				cache.committee::<C>(1, seed, &indices, index, 8).unwrap(),
+				utils::compute_committee::<C>(&indices, seed, index, 8).unwrap(),
+			);
+		}
+		assert_eq!(cache.len(), 1);
+
+		let changed = &indices[1..];
+		assert_eq!(
+			cache.committee::<C>(1, seed, changed, 0, 8).unwrap(),
+			utils::compute_committee::<C>(changed, seed, 0, 8).unwrap(),
+		);
+		assert_eq!(cache.len(), 1);
+	}
+}
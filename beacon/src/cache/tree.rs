@@ -0,0 +1,268 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::vec::Vec;
+use bm_le::{tree_root, CompactRef};
+use crate::primitives::{H256, Uint};
+use crate::types::Validator;
+use crate::{Config, BeaconState};
+
+/// Merkle tree over a list of chunks, keeping all internal nodes so that only
+/// paths to changed chunks are rehashed.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub(crate) struct ChunkTree {
+	depth: usize,
+	levels: Vec<Vec<H256>>,
+	zero_hashes: Vec<H256>,
+}
+
+impl ChunkTree {
+	pub(crate) fn new<C: Config>(depth: usize) -> Self {
+		let mut zero_hashes = Vec::with_capacity(depth + 1);
+		zero_hashes.push(H256::default());
+		for i in 0..depth {
+			let zero = zero_hashes[i];
+			zero_hashes.push(hash_pair::<C>(&zero, &zero));
+		}
+
+		Self { depth, levels: (0..(depth + 1)).map(|_| Vec::new()).collect(), zero_hashes }
+	}
+
+	/// Update the tree to the given chunks, rehashing only changed paths.
+	pub(crate) fn update<C: Config>(&mut self, depth: usize, chunks: &[H256]) {
+		self.reset_if_needed::<C>(depth, chunks.len());
+
+		let changed = chunks.iter().enumerate()
+			.filter(|&(i, chunk)| self.levels[0].get(i) != Some(chunk))
+			.map(|(i, chunk)| (i, *chunk))
+			.collect::<Vec<_>>();
+		self.set_leaves::<C>(chunks.len(), changed);
+	}
+
+	/// Reset the tree if it cannot be updated in place to the given depth and
+	/// length. Returns whether it was reset.
+	fn reset_if_needed<C: Config>(&mut self, depth: usize, len: usize) -> bool {
+		if self.levels.len() != depth + 1 || len < self.levels[0].len() {
+			*self = Self::new::<C>(depth);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Resize the leaves to the given length, set the changed leaves and
+	/// rehash their paths.
+	fn set_leaves<C: Config>(&mut self, len: usize, changed: Vec<(usize, H256)>) {
+		self.levels[0].resize(len, H256::default());
+		let mut dirty = Vec::with_capacity(changed.len());
+		for (i, leaf) in changed {
+			self.levels[0][i] = leaf;
+			dirty.push(i);
+		}
+
+		for level in 1..(self.depth + 1) {
+			let len = (self.levels[level - 1].len() + 1) / 2;
+			self.levels[level].resize(len, H256::default());
+
+			dirty = dirty.into_iter().map(|i| i / 2).collect();
+			dirty.dedup();
+
+			for i in &dirty {
+				let below = &self.levels[level - 1];
+				let left = below[i * 2];
+				let right = below.get(i * 2 + 1).cloned()
+					.unwrap_or(self.zero_hashes[level - 1]);
+				self.levels[level][*i] = hash_pair::<C>(&left, &right);
+			}
+		}
+	}
+
+	/// Root of the tree.
+	pub(crate) fn root(&self) -> H256 {
+		self.levels[self.depth].get(0).cloned().unwrap_or(self.zero_hashes[self.depth])
+	}
+
+	/// Sibling nodes of the chunk at `index`, from the chunk up to the root.
+	pub(crate) fn branch(&self, index: usize) -> Vec<H256> {
+		(0..self.depth).map(|level| {
+			self.levels[level].get((index >> level) ^ 1).cloned()
+				.unwrap_or(self.zero_hashes[level])
+		}).collect()
+	}
+}
+
+/// Tree hash cache for `BeaconState`.
+///
+/// Fixed-size history vectors and the validator registry are kept as cached
+/// merkle trees. Changed chunks are found by comparing against the cached
+/// leaves, and only their paths are rehashed. Validators are compared in
+/// place against a cached copy, and only changed entries are copied and
+/// rehashed. The remaining fields are small
+/// and hashed directly.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct TreeHashCache {
+	block_roots: ChunkTree,
+	state_roots: ChunkTree,
+	historical_roots: ChunkTree,
+	validators: Vec<Validator>,
+	validator_roots: ChunkTree,
+	balances: ChunkTree,
+	randao_mixes: ChunkTree,
+	slashings: ChunkTree,
+}
+
+impl TreeHashCache {
+	/// Create a new empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Compute the state root, updating the cache.
+	pub fn state_root<C: Config>(&mut self, state: &BeaconState<C>) -> H256 {
+		let validator_depth = depth(C::validator_registry_limit());
+		if self.validator_roots.reset_if_needed::<C>(validator_depth, state.validators.len()) {
+			self.validators.clear();
+		}
+
+		let mut changed = Vec::new();
+		for (i, validator) in state.validators.iter().enumerate() {
+			match self.validators.get_mut(i) {
+				Some(cached) if cached == validator => (),
+				Some(cached) => {
+					*cached = validator.clone();
+					changed.push((i, tree_root::<C::Digest, _>(validator)));
+				},
+				None => {
+					self.validators.push(validator.clone());
+					changed.push((i, tree_root::<C::Digest, _>(validator)));
+				},
+			}
+		}
+		self.validator_roots.set_leaves::<C>(state.validators.len(), changed);
+
+		self.block_roots.update::<C>(
+			depth(C::slots_per_historical_root()), &state.block_roots[..]
+		);
+		self.state_roots.update::<C>(
+			depth(C::slots_per_historical_root()), &state.state_roots[..]
+		);
+		self.historical_roots.update::<C>(
+			depth(C::historical_roots_limit()), &state.historical_roots[..]
+		);
+		self.balances.update::<C>(
+			depth(packed_chunks(C::validator_registry_limit())), &pack(&state.balances[..])
+		);
+		self.randao_mixes.update::<C>(
+			depth(C::epochs_per_historical_vector()), &state.randao_mixes[..]
+		);
+		self.slashings.update::<C>(
+			depth(packed_chunks(C::epochs_per_slashings_vector())), &pack(&state.slashings[..])
+		);
+
+		let fields = [
+			tree_root::<C::Digest, _>(&state.genesis_time),
+			tree_root::<C::Digest, _>(&state.slot),
+			tree_root::<C::Digest, _>(&state.fork),
+			tree_root::<C::Digest, _>(&state.latest_block_header),
+			self.block_roots.root(),
+			self.state_roots.root(),
+			mix_in_length::<C>(&self.historical_roots.root(), state.historical_roots.len()),
+			tree_root::<C::Digest, _>(&state.eth1_data),
+			tree_root::<C::Digest, _>(&state.eth1_data_votes),
+			tree_root::<C::Digest, _>(&state.eth1_deposit_index),
+			mix_in_length::<C>(&self.validator_roots.root(), state.validators.len()),
+			mix_in_length::<C>(&self.balances.root(), state.balances.len()),
+			self.randao_mixes.root(),
+			self.slashings.root(),
+			tree_root::<C::Digest, _>(&state.previous_epoch_attestations),
+			tree_root::<C::Digest, _>(&state.current_epoch_attestations),
+			tree_root::<C::Digest, _>(&CompactRef(&state.justification_bits)),
+			tree_root::<C::Digest, _>(&state.previous_justified_checkpoint),
+			tree_root::<C::Digest, _>(&state.current_justified_checkpoint),
+			tree_root::<C::Digest, _>(&state.finalized_checkpoint),
+		];
+
+		let container_depth = depth(fields.len() as u64);
+		let mut container = ChunkTree::new::<C>(container_depth);
+		container.update::<C>(container_depth, &fields);
+		container.root()
+	}
+}
+
+pub(crate) fn hash_pair<C: Config>(left: &H256, right: &H256) -> H256 {
+	C::hash(&[&left[..], &right[..]])
+}
+
+pub(crate) fn mix_in_length<C: Config>(root: &H256, len: usize) -> H256 {
+	let mut length = H256::default();
+	length[..8].copy_from_slice(&(len as u64).to_le_bytes());
+	hash_pair::<C>(root, &length)
+}
+
+pub(crate) fn depth(chunks: Uint) -> usize {
+	let mut depth = 0;
+	while (1u64 << depth) < chunks {
+		depth += 1;
+	}
+	depth
+}
+
+pub(crate) fn packed_chunks(count: Uint) -> Uint {
+	(count * 8 + 31) / 32
+}
+
+pub(crate) fn pack(values: &[Uint]) -> Vec<H256> {
+	values.chunks(4).map(|values| {
+		let mut chunk = H256::default();
+		for (i, value) in values.iter().enumerate() {
+			chunk[(i * 8)..((i + 1) * 8)].copy_from_slice(&value.to_le_bytes());
+		}
+		chunk
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimalConfig;
+
+	#[test]
+	fn cached_root_matches_tree_root() {
+		let mut cache = TreeHashCache::new();
+		let mut state = BeaconState::<MinimalConfig>::default();
+		assert_eq!(cache.state_root(&state), tree_root::<<MinimalConfig as Config>::Digest, _>(&state));
+
+		for i in 0..9u8 {
+			state.validators.push(Validator {
+				pubkey: crate::primitives::ValidatorId::repeat_byte(i),
+				..Default::default()
+			});
+			state.balances.push(i as u64 * 1000);
+		}
+		state.block_roots[3] = H256::repeat_byte(1);
+		state.slashings[5] = 42;
+		assert_eq!(cache.state_root(&state), tree_root::<<MinimalConfig as Config>::Digest, _>(&state));
+
+		state.validators[4].slashed = true;
+		state.balances[8] = 1;
+		state.randao_mixes[0] = H256::repeat_byte(2);
+		assert_eq!(cache.state_root(&state), tree_root::<<MinimalConfig as Config>::Digest, _>(&state));
+
+		state.validators.pop();
+		state.balances.pop();
+		assert_eq!(cache.state_root(&state), tree_root::<<MinimalConfig as Config>::Digest, _>(&state));
+	}
+}
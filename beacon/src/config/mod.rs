@@ -14,6 +14,12 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+#[cfg(feature = "std")]
+mod runtime;
+
+#[cfg(feature = "std")]
+pub use self::runtime::{RuntimeConfig, RuntimeParameters, RuntimeConfigError};
+
 use digest::Digest;
 use typenum::Unsigned;
 use serde::{Serialize, Deserialize};
@@ -123,6 +129,10 @@ pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + S
 	fn bls_withdrawal_prefix_byte() -> u8;
 
 	// == Time parameters ==
+	/// Seconds per slot.
+	fn seconds_per_slot() -> Uint;
+	/// Distance in eth1 blocks to follow behind the eth1 head.
+	fn eth1_follow_distance() -> Uint;
 	/// Minimum attestation inclusion delay.
 	fn min_attestation_inclusion_delay() -> Uint;
 	/// Slots per epoch.
@@ -173,6 +183,8 @@ pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + S
 	fn max_attester_slashings() -> Uint { Self::MaxAttesterSlashings::to_u64() }
 	/// Maximum attestations per block.
 	fn max_attestations() -> Uint { Self::MaxAttestations::to_u64() }
+	/// Maximum attestations per epoch.
+	fn max_attestations_per_epoch() -> Uint { Self::MaxAttestationsPerEpoch::to_u64() }
 	/// Maximum deposits per block.
 	fn max_deposits() -> Uint { Self::MaxDeposits::to_u64() }
 	/// Maximum voluntary exits per block.
@@ -249,6 +261,8 @@ impl Config for MinimalConfig {
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 6 }
+	fn eth1_follow_distance() -> Uint { 16 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
@@ -311,6 +325,8 @@ impl Config for MainnetConfig {
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 12 }
+	fn eth1_follow_distance() -> Uint { 1024 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
@@ -373,6 +389,8 @@ impl Config for SapphireConfig {
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 6 }
+	fn eth1_follow_distance() -> Uint { 16 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
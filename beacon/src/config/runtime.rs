@@ -0,0 +1,544 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use core::fmt;
+use core::marker::PhantomData;
+use once_cell::sync::OnceCell;
+use typenum::Unsigned;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize, Deserializer};
+use crate::primitives::Uint;
+use super::Config;
+
+static PARAMETERS: OnceCell<RuntimeParameters> = OnceCell::new();
+
+/// Config values that can be loaded from a spec config file. Values not
+/// present fall back to the base config.
+///
+/// List lengths are part of the types and can not be changed at runtime.
+/// They are only accepted if they match the base config.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, rename_all = "SCREAMING_SNAKE_CASE"))]
+pub struct RuntimeParameters {
+	// === Misc ===
+	pub max_committees_per_slot: Option<Uint>,
+	pub target_committee_size: Option<Uint>,
+	pub max_validators_per_committee: Option<Uint>,
+	pub min_per_epoch_churn_limit: Option<Uint>,
+	pub churn_limit_quotient: Option<Uint>,
+	pub shuffle_round_count: Option<Uint>,
+	pub min_genesis_active_validator_count: Option<Uint>,
+	pub min_genesis_time: Option<Uint>,
+	pub shard_count: Option<Uint>,
+
+	// == Gwei values ==
+	pub min_deposit_amount: Option<Uint>,
+	pub max_effective_balance: Option<Uint>,
+	pub ejection_balance: Option<Uint>,
+	pub effective_balance_increment: Option<Uint>,
+
+	// == Initial values ==
+	pub genesis_slot: Option<Uint>,
+	pub genesis_epoch: Option<Uint>,
+	#[cfg_attr(feature = "serde", serde(
+		rename = "BLS_WITHDRAWAL_PREFIX",
+		deserialize_with = "deserialize_prefix",
+	))]
+	pub bls_withdrawal_prefix_byte: Option<u8>,
+
+	// == Time parameters ==
+	pub seconds_per_slot: Option<Uint>,
+	pub eth1_follow_distance: Option<Uint>,
+	pub min_attestation_inclusion_delay: Option<Uint>,
+	pub slots_per_epoch: Option<Uint>,
+	pub min_seed_lookahead: Option<Uint>,
+	pub max_seed_lookahead: Option<Uint>,
+	pub slots_per_eth1_voting_period: Option<Uint>,
+	pub slots_per_historical_root: Option<Uint>,
+	pub min_validator_withdrawability_delay: Option<Uint>,
+	pub persistent_committee_period: Option<Uint>,
+	pub max_epochs_per_crosslink: Option<Uint>,
+	pub min_epochs_to_inactivity_penalty: Option<Uint>,
+
+	// == State list lengths ==
+	pub epochs_per_historical_vector: Option<Uint>,
+	pub epochs_per_slashings_vector: Option<Uint>,
+	pub historical_roots_limit: Option<Uint>,
+	pub validator_registry_limit: Option<Uint>,
+
+	// == Reward and penalty quotients ==
+	pub base_reward_factor: Option<Uint>,
+	pub whistleblower_reward_quotient: Option<Uint>,
+	pub proposer_reward_quotient: Option<Uint>,
+	pub inactivity_penalty_quotient: Option<Uint>,
+	pub min_slashing_penalty_quotient: Option<Uint>,
+
+	// == Max operations per block ==
+	pub max_proposer_slashings: Option<Uint>,
+	pub max_attester_slashings: Option<Uint>,
+	pub max_attestations: Option<Uint>,
+	pub max_deposits: Option<Uint>,
+	pub max_voluntary_exits: Option<Uint>,
+
+	// == Signature domains ==
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_domain"))]
+	pub domain_beacon_proposer: Option<u32>,
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_domain"))]
+	pub domain_beacon_attester: Option<u32>,
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_domain"))]
+	pub domain_randao: Option<u32>,
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_domain"))]
+	pub domain_deposit: Option<u32>,
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_domain"))]
+	pub domain_voluntary_exit: Option<u32>,
+}
+
+impl RuntimeParameters {
+	/// Check that all list lengths present match the base config, and that
+	/// values used as divisors are not zero.
+	pub fn check<C: Config>(&self) -> Result<(), RuntimeConfigError> {
+		let divisors = [
+			("TARGET_COMMITTEE_SIZE", self.target_committee_size),
+			("CHURN_LIMIT_QUOTIENT", self.churn_limit_quotient),
+			("EFFECTIVE_BALANCE_INCREMENT", self.effective_balance_increment),
+			("SECONDS_PER_SLOT", self.seconds_per_slot),
+			("SLOTS_PER_EPOCH", self.slots_per_epoch),
+			("SLOTS_PER_ETH1_VOTING_PERIOD", self.slots_per_eth1_voting_period),
+			("SLOTS_PER_HISTORICAL_ROOT", self.slots_per_historical_root),
+			("EPOCHS_PER_HISTORICAL_VECTOR", self.epochs_per_historical_vector),
+			("EPOCHS_PER_SLASHINGS_VECTOR", self.epochs_per_slashings_vector),
+			("WHISTLEBLOWER_REWARD_QUOTIENT", self.whistleblower_reward_quotient),
+			("PROPOSER_REWARD_QUOTIENT", self.proposer_reward_quotient),
+			("INACTIVITY_PENALTY_QUOTIENT", self.inactivity_penalty_quotient),
+			("MIN_SLASHING_PENALTY_QUOTIENT", self.min_slashing_penalty_quotient),
+		];
+
+		for (name, value) in divisors.iter() {
+			if *value == Some(0) {
+				return Err(RuntimeConfigError::ZeroDivisor { name: *name })
+			}
+		}
+
+		let lengths = [
+			("MAX_VALIDATORS_PER_COMMITTEE", self.max_validators_per_committee,
+			 C::MaxValidatorsPerCommittee::to_u64()),
+			("SHARD_COUNT", self.shard_count, C::ShardCount::to_u64()),
+			("SLOTS_PER_EPOCH", self.slots_per_epoch, C::SlotsPerEpoch::to_u64()),
+			("SLOTS_PER_ETH1_VOTING_PERIOD", self.slots_per_eth1_voting_period,
+			 C::SlotsPerEth1VotingPeriod::to_u64()),
+			("SLOTS_PER_HISTORICAL_ROOT", self.slots_per_historical_root,
+			 C::SlotsPerHistoricalRoot::to_u64()),
+			("EPOCHS_PER_HISTORICAL_VECTOR", self.epochs_per_historical_vector,
+			 C::EpochsPerHistoricalVector::to_u64()),
+			("EPOCHS_PER_SLASHINGS_VECTOR", self.epochs_per_slashings_vector,
+			 C::EpochsPerSlashingsVector::to_u64()),
+			("HISTORICAL_ROOTS_LIMIT", self.historical_roots_limit,
+			 C::HistoricalRootsLimit::to_u64()),
+			("VALIDATOR_REGISTRY_LIMIT", self.validator_registry_limit,
+			 C::ValidatorRegistryLimit::to_u64()),
+			("MAX_PROPOSER_SLASHINGS", self.max_proposer_slashings,
+			 C::MaxProposerSlashings::to_u64()),
+			("MAX_ATTESTER_SLASHINGS", self.max_attester_slashings,
+			 C::MaxAttesterSlashings::to_u64()),
+			("MAX_ATTESTATIONS", self.max_attestations, C::MaxAttestations::to_u64()),
+			("MAX_DEPOSITS", self.max_deposits, C::MaxDeposits::to_u64()),
+			("MAX_VOLUNTARY_EXITS", self.max_voluntary_exits, C::MaxVoluntaryExits::to_u64()),
+		];
+
+		for (name, value, expected) in lengths.iter() {
+			match value {
+				Some(value) if value != expected => {
+					return Err(RuntimeConfigError::LengthMismatch {
+						name: *name, expected: *expected, got: *value,
+					})
+				},
+				_ => (),
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Error when installing runtime parameters.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RuntimeConfigError {
+	/// A list length differs from the one of the base config.
+	LengthMismatch {
+		/// Name of the config value.
+		name: &'static str,
+		/// Value of the base config.
+		expected: Uint,
+		/// Value loaded.
+		got: Uint,
+	},
+	/// A value used as a divisor is zero.
+	ZeroDivisor {
+		/// Name of the config value.
+		name: &'static str,
+	},
+	/// Different parameters were already installed.
+	AlreadyInstalled,
+}
+
+impl fmt::Display for RuntimeConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			RuntimeConfigError::LengthMismatch { name, expected, got } => write!(
+				f, "{} is {} in the base config and can not be changed to {}",
+				name, expected, got,
+			),
+			RuntimeConfigError::ZeroDivisor { name } =>
+				write!(f, "{} is used as a divisor and can not be zero", name),
+			RuntimeConfigError::AlreadyInstalled =>
+				write!(f, "Different runtime parameters were already installed"),
+		}
+	}
+}
+
+impl std::error::Error for RuntimeConfigError { }
+
+/// Config whose values are read from the installed runtime parameters,
+/// falling back to `Base`. List lengths are always those of `Base`.
+///
+/// Parameters are process-wide and can only be installed once.
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+pub struct RuntimeConfig<Base: Config>(PhantomData<Base>);
+
+impl<Base: Config> RuntimeConfig<Base> {
+	/// Install runtime parameters, after checking them against `Base`.
+	/// Installing the same parameters again is a no-op.
+	pub fn install(parameters: RuntimeParameters) -> Result<(), RuntimeConfigError> {
+		parameters.check::<Base>()?;
+
+		let installed = PARAMETERS.get_or_init(|| parameters.clone());
+		if installed != &parameters {
+			return Err(RuntimeConfigError::AlreadyInstalled)
+		}
+
+		Ok(())
+	}
+
+	/// Currently installed runtime parameters.
+	pub fn parameters() -> Option<&'static RuntimeParameters> {
+		PARAMETERS.get()
+	}
+}
+
+macro_rules! runtime_values {
+	( $( $name:ident: $t:ty ),* $(,)? ) => {
+		$(
+			fn $name() -> $t {
+				PARAMETERS.get()
+					.and_then(|parameters| parameters.$name)
+					.unwrap_or_else(Base::$name)
+			}
+		)*
+	}
+}
+
+impl<Base: Config> Config for RuntimeConfig<Base> {
+	type Digest = Base::Digest;
+	type MaxValidatorsPerCommittee = Base::MaxValidatorsPerCommittee;
+	type SlotsPerHistoricalRoot = Base::SlotsPerHistoricalRoot;
+	type MaxProposerSlashings = Base::MaxProposerSlashings;
+	type MaxAttesterSlashings = Base::MaxAttesterSlashings;
+	type MaxAttestations = Base::MaxAttestations;
+	type MaxDeposits = Base::MaxDeposits;
+	type MaxVoluntaryExits = Base::MaxVoluntaryExits;
+	type HistoricalRootsLimit = Base::HistoricalRootsLimit;
+	type ShardCount = Base::ShardCount;
+	type SlotsPerEpoch = Base::SlotsPerEpoch;
+	type SlotsPerEth1VotingPeriod = Base::SlotsPerEth1VotingPeriod;
+	type ValidatorRegistryLimit = Base::ValidatorRegistryLimit;
+	type EpochsPerHistoricalVector = Base::EpochsPerHistoricalVector;
+	type EpochsPerSlashingsVector = Base::EpochsPerSlashingsVector;
+	type MaxAttestationsPerEpoch = Base::MaxAttestationsPerEpoch;
+
+	runtime_values! {
+		// === Misc ===
+		max_committees_per_slot: Uint,
+		target_committee_size: Uint,
+		min_per_epoch_churn_limit: Uint,
+		churn_limit_quotient: Uint,
+		shuffle_round_count: Uint,
+		min_genesis_active_validator_count: Uint,
+		min_genesis_time: Uint,
+
+		// == Gwei values ==
+		min_deposit_amount: Uint,
+		max_effective_balance: Uint,
+		ejection_balance: Uint,
+		effective_balance_increment: Uint,
+
+		// == Initial values ==
+		genesis_slot: Uint,
+		genesis_epoch: Uint,
+		bls_withdrawal_prefix_byte: u8,
+
+		// == Time parameters ==
+		seconds_per_slot: Uint,
+		eth1_follow_distance: Uint,
+		min_attestation_inclusion_delay: Uint,
+		min_seed_lookahead: Uint,
+		max_seed_lookahead: Uint,
+		min_validator_withdrawability_delay: Uint,
+		persistent_committee_period: Uint,
+		max_epochs_per_crosslink: Uint,
+		min_epochs_to_inactivity_penalty: Uint,
+
+		// == Reward and penalty quotients ==
+		base_reward_factor: Uint,
+		whistleblower_reward_quotient: Uint,
+		proposer_reward_quotient: Uint,
+		inactivity_penalty_quotient: Uint,
+		min_slashing_penalty_quotient: Uint,
+
+		// == Signature domains ==
+		domain_beacon_proposer: u32,
+		domain_beacon_attester: u32,
+		domain_randao: u32,
+		domain_deposit: u32,
+		domain_voluntary_exit: u32,
+	}
+}
+
+/// Spec config files write byte values as hex literals, which YAML parsers
+/// may read either as integers or as strings.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HexValue {
+	Number(u64),
+	Text(String),
+}
+
+#[cfg(feature = "serde")]
+fn hex_bytes<'de, D: Deserializer<'de>>(deserializer: D, len: usize) -> Result<Vec<u8>, D::Error> {
+	use serde::de::Error;
+
+	match HexValue::deserialize(deserializer)? {
+		HexValue::Number(value) => {
+			let bytes = value.to_be_bytes();
+			if bytes[..(8 - len)].iter().any(|b| *b != 0) {
+				return Err(D::Error::custom("hex value too long"))
+			}
+			Ok(bytes[(8 - len)..].to_vec())
+		},
+		HexValue::Text(text) => {
+			let text = text.trim_start_matches("0x");
+			if text.len() != len * 2 {
+				return Err(D::Error::custom("hex value has invalid length"))
+			}
+			(0..len).map(|i| {
+				u8::from_str_radix(&text[(i * 2)..(i * 2 + 2)], 16)
+					.map_err(|_| D::Error::custom("invalid hex value"))
+			}).collect()
+		},
+	}
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_domain<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+	let bytes = hex_bytes(deserializer, 4)?;
+	let mut domain = [0u8; 4];
+	domain.copy_from_slice(&bytes);
+	Ok(Some(u32::from_le_bytes(domain)))
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_prefix<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+	Ok(Some(hex_bytes(deserializer, 1)?[0]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimalConfig;
+
+	#[test]
+	fn check_rejects_changed_lengths() {
+		let mut parameters = RuntimeParameters::default();
+		parameters.churn_limit_quotient = Some(1024);
+		parameters.slots_per_epoch = Some(8);
+		assert_eq!(parameters.check::<MinimalConfig>(), Ok(()));
+
+		parameters.slots_per_epoch = Some(32);
+		assert_eq!(parameters.check::<MinimalConfig>(), Err(RuntimeConfigError::LengthMismatch {
+			name: "SLOTS_PER_EPOCH", expected: 8, got: 32,
+		}));
+	}
+
+	#[test]
+	fn check_rejects_zero_divisors() {
+		let mut parameters = RuntimeParameters::default();
+		parameters.seconds_per_slot = Some(0);
+		assert_eq!(parameters.check::<MinimalConfig>(), Err(RuntimeConfigError::ZeroDivisor {
+			name: "SECONDS_PER_SLOT",
+		}));
+
+		parameters.seconds_per_slot = Some(6);
+		parameters.slots_per_epoch = Some(0);
+		assert_eq!(parameters.check::<MinimalConfig>(), Err(RuntimeConfigError::ZeroDivisor {
+			name: "SLOTS_PER_EPOCH",
+		}));
+	}
+
+	#[test]
+	#[cfg(feature = "serde")]
+	fn deserializes_spec_config() {
+		let parameters: RuntimeParameters = serde_yaml::from_str(MINIMAL_CONFIG).unwrap();
+		assert_eq!(parameters.check::<MinimalConfig>(), Ok(()));
+
+		assert_eq!(parameters.max_validators_per_committee, Some(2048));
+		assert_eq!(parameters.min_genesis_time, Some(1578009600));
+		assert_eq!(parameters.seconds_per_slot, Some(6));
+		assert_eq!(parameters.validator_registry_limit, Some(1099511627776));
+		assert_eq!(parameters.bls_withdrawal_prefix_byte, Some(0x00));
+		assert_eq!(parameters.domain_beacon_proposer, Some(0));
+		assert_eq!(parameters.domain_beacon_attester, Some(1));
+		assert_eq!(parameters.domain_randao, Some(2));
+		assert_eq!(parameters.domain_deposit, Some(3));
+		assert_eq!(parameters.domain_voluntary_exit, Some(4));
+	}
+
+	#[cfg(feature = "serde")]
+	const MINIMAL_CONFIG: &str = r#"# Minimal preset
+
+# Misc
+# ---------------------------------------------------------------
+# [customized] Just 4 committees for slot for testing purposes
+MAX_COMMITTEES_PER_SLOT: 4
+# [customized] unsecure, but fast
+TARGET_COMMITTEE_SIZE: 4
+# 2**11 (= 2,048)
+MAX_VALIDATORS_PER_COMMITTEE: 2048
+# 2**2 (= 4)
+MIN_PER_EPOCH_CHURN_LIMIT: 4
+# 2**16 (= 65,536)
+CHURN_LIMIT_QUOTIENT: 65536
+# [customized] Faster, but unsecure.
+SHUFFLE_ROUND_COUNT: 10
+# [customized]
+MIN_GENESIS_ACTIVE_VALIDATOR_COUNT: 64
+# Jan 3, 2020
+MIN_GENESIS_TIME: 1578009600
+
+
+# Deposit contract
+# ---------------------------------------------------------------
+# **TBD**
+DEPOSIT_CONTRACT_ADDRESS: 0x1234567890123456789012345678901234567890
+
+
+# Gwei values
+# ---------------------------------------------------------------
+# 2**0 * 10**9 (= 1,000,000,000) Gwei
+MIN_DEPOSIT_AMOUNT: 1000000000
+# 2**5 * 10**9 (= 32,000,000,000) Gwei
+MAX_EFFECTIVE_BALANCE: 32000000000
+# 2**4 * 10**9 (= 16,000,000,000) Gwei
+EJECTION_BALANCE: 16000000000
+# 2**0 * 10**9 (= 1,000,000,000) Gwei
+EFFECTIVE_BALANCE_INCREMENT: 1000000000
+
+
+# Initial values
+# ---------------------------------------------------------------
+GENESIS_SLOT: 0
+GENESIS_EPOCH: 0
+BLS_WITHDRAWAL_PREFIX: 0x00
+
+
+# Time parameters
+# ---------------------------------------------------------------
+# [customized] Faster for testing purposes
+SECONDS_PER_SLOT: 6
+# 2**0 (= 1) slots 6 seconds
+MIN_ATTESTATION_INCLUSION_DELAY: 1
+# [customized] fast epochs
+SLOTS_PER_EPOCH: 8
+# 2**0 (= 1) epochs
+MIN_SEED_LOOKAHEAD: 1
+# 2**2 (= 4) epochs
+MAX_SEED_LOOKAHEAD: 4
+# [customized] higher frequency new deposits from eth1 for testing
+SLOTS_PER_ETH1_VOTING_PERIOD: 16
+# [customized] smaller state
+SLOTS_PER_HISTORICAL_ROOT: 64
+# 2**8 (= 256) epochs
+MIN_VALIDATOR_WITHDRAWABILITY_DELAY: 256
+# 2**11 (= 2,048) epochs
+PERSISTENT_COMMITTEE_PERIOD: 2048
+# [customized] 2**2 (= 4)
+MIN_EPOCHS_TO_INACTIVITY_PENALTY: 4
+# [customized] 2**4 (= 16)
+ETH1_FOLLOW_DISTANCE: 16
+
+
+# State vector lengths
+# ---------------------------------------------------------------
+# [customized] smaller state
+EPOCHS_PER_HISTORICAL_VECTOR: 64
+# [customized] smaller state
+EPOCHS_PER_SLASHINGS_VECTOR: 64
+# 2**24 (= 16,777,216) historical roots
+HISTORICAL_ROOTS_LIMIT: 16777216
+# 2**40 (= 1,099,511,627,776) validator spots
+VALIDATOR_REGISTRY_LIMIT: 1099511627776
+
+
+# Reward and penalty quotients
+# ---------------------------------------------------------------
+# 2**6 (= 64)
+BASE_REWARD_FACTOR: 64
+# 2**9 (= 512)
+WHISTLEBLOWER_REWARD_QUOTIENT: 512
+# 2**3 (= 8)
+PROPOSER_REWARD_QUOTIENT: 8
+# 2**25 (= 33,554,432)
+INACTIVITY_PENALTY_QUOTIENT: 33554432
+# 2**5 (= 32)
+MIN_SLASHING_PENALTY_QUOTIENT: 32
+
+
+# Max operations per block
+# ---------------------------------------------------------------
+# 2**4 (= 16)
+MAX_PROPOSER_SLASHINGS: 16
+# 2**0 (= 1)
+MAX_ATTESTER_SLASHINGS: 1
+# 2**7 (= 128)
+MAX_ATTESTATIONS: 128
+# 2**4 (= 16)
+MAX_DEPOSITS: 16
+# 2**4 (= 16)
+MAX_VOLUNTARY_EXITS: 16
+
+
+# Signature domains
+# ---------------------------------------------------------------
+DOMAIN_BEACON_PROPOSER: 0x00000000
+DOMAIN_BEACON_ATTESTER: 0x01000000
+DOMAIN_RANDAO: 0x02000000
+DOMAIN_DEPOSIT: 0x03000000
+DOMAIN_VOLUNTARY_EXIT: 0x04000000
+"#;
+}
@@ -0,0 +1,202 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::vec::Vec;
+use core::cmp::max;
+use core::fmt;
+use bm_le::{tree_root, CompactRef};
+use crate::primitives::H256;
+use crate::{Config, BeaconState};
+
+/// Maximum number of changed indices shown per field when displayed.
+const DISPLAY_INDICES: usize = 16;
+
+/// Difference of a single state field.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldDiff {
+	/// Name of the field.
+	pub field: &'static str,
+	/// Field subtree root in the left state.
+	pub left_root: H256,
+	/// Field subtree root in the right state.
+	pub right_root: H256,
+	/// Lengths of list and vector fields, in the left and right state.
+	pub lengths: Option<(usize, usize)>,
+	/// Changed item indices of list and vector fields.
+	pub indices: Vec<usize>,
+}
+
+/// Field by field difference of two beacon states.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StateDiff {
+	/// Root of the left state.
+	pub left_root: H256,
+	/// Root of the right state.
+	pub right_root: H256,
+	/// Differing fields, in state field order.
+	pub fields: Vec<FieldDiff>,
+}
+
+impl StateDiff {
+	/// Whether the two states are equal.
+	pub fn is_empty(&self) -> bool {
+		self.fields.is_empty()
+	}
+
+	/// Get the difference of a field by name.
+	pub fn field(&self, name: &str) -> Option<&FieldDiff> {
+		self.fields.iter().find(|diff| diff.field == name)
+	}
+}
+
+impl fmt::Display for StateDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.is_empty() {
+			return write!(f, "states are equal")
+		}
+
+		writeln!(f, "state root {:?} != {:?}", self.left_root, self.right_root)?;
+		for diff in &self.fields {
+			write!(f, "  {}: root {:?} != {:?}", diff.field, diff.left_root, diff.right_root)?;
+			if let Some((left, right)) = diff.lengths {
+				if left != right {
+					write!(f, ", length {} != {}", left, right)?;
+				}
+			}
+			if !diff.indices.is_empty() {
+				write!(f, ", changed indices")?;
+				for index in diff.indices.iter().take(DISPLAY_INDICES) {
+					write!(f, " {}", index)?;
+				}
+				if diff.indices.len() > DISPLAY_INDICES {
+					write!(f, " ({} more)", diff.indices.len() - DISPLAY_INDICES)?;
+				}
+			}
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
+
+fn changed_indices<T: PartialEq>(left: &[T], right: &[T]) -> Vec<usize> {
+	(0..max(left.len(), right.len()))
+		.filter(|i| left.get(*i) != right.get(*i))
+		.collect()
+}
+
+/// Compare two beacon states field by field.
+pub fn diff_states<C: Config>(left: &BeaconState<C>, right: &BeaconState<C>) -> StateDiff {
+	let mut fields = Vec::new();
+
+	macro_rules! diff_field {
+		( $field:ident ) => {
+			diff_field!($field, |value| tree_root::<C::Digest, _>(value))
+		};
+		( $field:ident, $root:expr ) => {
+			if left.$field != right.$field {
+				let root = $root;
+				fields.push(FieldDiff {
+					field: stringify!($field),
+					left_root: root(&left.$field),
+					right_root: root(&right.$field),
+					lengths: None,
+					indices: Vec::new(),
+				});
+			}
+		};
+	}
+
+	macro_rules! diff_items {
+		( $field:ident ) => {
+			diff_items!($field, |value| tree_root::<C::Digest, _>(value))
+		};
+		( $field:ident, $root:expr ) => {
+			if left.$field != right.$field {
+				let root = $root;
+				fields.push(FieldDiff {
+					field: stringify!($field),
+					left_root: root(&left.$field),
+					right_root: root(&right.$field),
+					lengths: Some((left.$field.len(), right.$field.len())),
+					indices: changed_indices(&left.$field[..], &right.$field[..]),
+				});
+			}
+		};
+	}
+
+	diff_field!(genesis_time);
+	diff_field!(slot);
+	diff_field!(fork);
+	diff_field!(latest_block_header);
+	diff_items!(block_roots);
+	diff_items!(state_roots);
+	diff_items!(historical_roots);
+	diff_field!(eth1_data);
+	diff_items!(eth1_data_votes);
+	diff_field!(eth1_deposit_index);
+	diff_items!(validators);
+	diff_items!(balances, |value| tree_root::<C::Digest, _>(&CompactRef(value)));
+	diff_items!(randao_mixes);
+	diff_items!(slashings, |value| tree_root::<C::Digest, _>(&CompactRef(value)));
+	diff_items!(previous_epoch_attestations);
+	diff_items!(current_epoch_attestations);
+	diff_field!(justification_bits, |value| tree_root::<C::Digest, _>(&CompactRef(value)));
+	diff_field!(previous_justified_checkpoint);
+	diff_field!(current_justified_checkpoint);
+	diff_field!(finalized_checkpoint);
+
+	StateDiff {
+		left_root: tree_root::<C::Digest, _>(left),
+		right_root: tree_root::<C::Digest, _>(right),
+		fields,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimalConfig;
+	use crate::types::Validator;
+
+	#[test]
+	fn reports_changed_fields_and_indices() {
+		let mut left = BeaconState::<MinimalConfig>::default();
+		for _ in 0..4 {
+			left.validators.push(Validator::default());
+			left.balances.push(32);
+		}
+		let mut right = left.clone();
+		assert!(diff_states(&left, &right).is_empty());
+
+		right.slot = 1;
+		right.validators[2].slashed = true;
+		right.balances[1] = 31;
+		right.balances.push(1);
+		right.randao_mixes[5] = H256::repeat_byte(1);
+
+		let diff = diff_states(&left, &right);
+		assert_eq!(
+			diff.fields.iter().map(|diff| diff.field).collect::<Vec<_>>(),
+			["slot", "validators", "balances", "randao_mixes"],
+		);
+		assert_eq!(diff.field("validators").unwrap().indices, [2]);
+		assert_eq!(diff.field("balances").unwrap().indices, [1, 4]);
+		assert_eq!(diff.field("balances").unwrap().lengths, Some((4, 5)));
+		assert_eq!(diff.field("randao_mixes").unwrap().indices, [5]);
+		assert_ne!(diff.left_root, diff.right_root);
+	}
+}
@@ -68,8 +68,11 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 	/// Get validator index from public key.
 	pub fn validator_index(&self, pubkey: &ValidatorId) -> Option<u64> {
-		let validator_pubkeys = self.validators.iter()
-			.map(|v| v.pubkey.clone()).collect::<Vec<_>>();
-		validator_pubkeys.iter().position(|v| v == pubkey).map(|v| v as u64)
+		match self.cache {
+			Some(ref cache) => cache.pubkeys.get(pubkey)
+				.filter(|index| (*index as usize) < self.validators.len()),
+			None => self.validators.iter()
+				.position(|v| &v.pubkey == pubkey).map(|v| v as u64),
+		}
 	}
 }
@@ -121,15 +121,27 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		let index = (slot % C::slots_per_epoch()) * committees_per_slot + index;
 		let count = committees_per_slot * C::slots_per_epoch();
 
-		utils::compute_committee::<C>(&indices, seed, index, count)
+		match self.cache {
+			Some(ref cache) => cache.committees.committee::<C>(epoch, seed, &indices, index, count),
+			None => utils::compute_committee::<C>(&indices, seed, index, count),
+		}
 	}
 
 	/// Get the current beacon proposer index.
 	pub fn beacon_proposer_index(&self) -> Result<ValidatorIndex, Error> {
+		self.beacon_proposer_index_at_slot(self.slot)
+	}
+
+	/// Get the beacon proposer index at a slot of the current epoch.
+	pub fn beacon_proposer_index_at_slot(&self, slot: Slot) -> Result<ValidatorIndex, Error> {
 		let epoch = self.current_epoch();
+		if utils::epoch_of_slot::<C>(slot) != epoch {
+			return Err(Error::EpochOutOfRange)
+		}
+
 		let seed = C::hash(&[
 			&self.seed(epoch, C::domain_beacon_proposer())[..],
-			&self.slot.to_le_bytes()[..8]
+			&slot.to_le_bytes()[..8]
 		]);
 		let indices = self.active_validator_indices(epoch);
 
@@ -27,7 +27,7 @@ use serde::{Serialize, Deserialize};
 use ssz::{Codec, Encode, Decode};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use vecarray::VecArray;
-use crate::{Config, Error};
+use crate::{Config, Error, BeaconCache};
 use crate::primitives::{H256, Uint, ValidatorIndex, Gwei};
 use crate::types::{
 	BeaconBlockHeader, Validator, Eth1Data, PendingAttestation, Checkpoint, Fork,
@@ -42,6 +42,7 @@ pub struct BeaconExecutive<'a, C: Config> {
 
 	active_validator_indices: Option<Vec<ValidatorIndex>>,
 	total_active_balance: Option<Gwei>,
+	cache: Option<&'a mut BeaconCache>,
 }
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
@@ -52,6 +53,27 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 			active_validator_indices: None,
 			total_active_balance: None,
+			cache: None,
+		}
+	}
+
+	/// Create an executive from a mutable state reference, using the given
+	/// cache shared across state transitions.
+	pub fn with_cache(
+		state: &'a mut BeaconState<C>,
+		cache: Option<&'a mut BeaconCache>,
+	) -> Self {
+		let cache = cache.map(|cache| {
+			cache.pubkeys.update(&state.validators);
+			cache
+		});
+
+		Self {
+			state,
+
+			active_validator_indices: None,
+			total_active_balance: None,
+			cache,
 		}
 	}
 }
@@ -31,7 +31,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		self.process_slots(block.slot())?;
 		self.process_block::<_, BLS>(block)?;
 
-		if !(block.state_root() == &tree_root::<C::Digest, _>(self.state)) {
+		if !(block.state_root() == &self.state_root()) {
 			return Err(Error::BlockStateRootInvalid)
 		}
 
@@ -55,9 +55,17 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		Ok(())
 	}
 
+	/// Compute the state root, through the tree hash cache if available.
+	pub fn state_root(&mut self) -> H256 {
+		match self.cache {
+			Some(ref mut cache) => cache.tree.state_root::<C>(self.state),
+			None => tree_root::<C::Digest, _>(self.state),
+		}
+	}
+
 	/// Advance slot
 	pub fn process_slot(&mut self) {
-		let previous_state_root = tree_root::<C::Digest, _>(self.state);
+		let previous_state_root = self.state_root();
 		self.state.state_roots[
 			(self.state.slot % C::slots_per_historical_root()) as usize
 		] = previous_state_root;
@@ -30,13 +30,12 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			return Err(Error::AttestationIncorrectJustifiedEpochOrBlockRoot)
 		}
 
-		if !(data.slot + C::min_attestation_inclusion_delay() <=
-			 self.slot &&
-			 self.slot <=
-			 data.slot + C::slots_per_epoch())
-		{
+		if !(data.slot + C::min_attestation_inclusion_delay() <= self.slot) {
 			return Err(Error::AttestationSubmittedTooQuickly)
 		}
+		if !(self.slot <= data.slot + C::slots_per_epoch()) {
+			return Err(Error::AttestationTooFarInHistory)
+		}
 
 		let pending_attestation = PendingAttestation {
 			data: data.clone(),
@@ -36,10 +36,10 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 		let pubkey = deposit.data.pubkey.clone();
 		let amount = deposit.data.amount.clone();
-		let validator_pubkeys = self.validators.iter()
-			.map(|v| v.pubkey.clone()).collect::<Vec<_>>();
 
-		if !validator_pubkeys.contains(&pubkey) {
+		if let Some(index) = self.validator_index(&pubkey) {
+			self.increase_balance(index, amount);
+		} else {
 			// Verify the deposit signature (proof of possession). Invalid
 			// signatures are allowed by the deposit contract, and hence
 			// included on-chain, but must not be processed.
@@ -53,7 +53,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			}
 
 			let validator = Validator {
-				pubkey,
+				pubkey: pubkey.clone(),
 				withdrawal_credentials: deposit.data.withdrawal_credentials,
 				activation_eligibility_epoch: consts::FAR_FUTURE_EPOCH,
 				activation_epoch: consts::FAR_FUTURE_EPOCH,
@@ -67,10 +67,13 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			};
 			self.state.validators.push(validator);
 			self.state.balances.push(amount);
-		} else {
-			let index = validator_pubkeys.iter().position(|v| v == &pubkey)
-				.expect("Registry contains the public key");
-			self.increase_balance(index as u64, amount);
+
+			let count = self.validators.len();
+			if let Some(ref mut cache) = self.cache {
+				if cache.pubkeys.len() < count {
+					cache.pubkeys.push(pubkey);
+				}
+			}
 		}
 
 		Ok(())
@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::primitives::Uint;
+use crate::primitives::{H256, Uint};
 use crate::types::{Deposit, BeaconBlockHeader, BeaconBlock, Eth1Data, BeaconBlockBody};
-use crate::{Config, BeaconState, BeaconExecutive, BLSConfig, Error};
+use crate::utils::DepositTree;
+use crate::{Config, BeaconState, BeaconExecutive, BLSConfig, Error, consts};
 use bm_le::tree_root;
 
 /// Generate genesis state and genesis block from given deposits, timestamp and eth1 data.
@@ -64,12 +65,101 @@ pub fn genesis_beacon_state<C: Config, BLS: BLSConfig>(
 		}
 	}
 
-	for validator in state.validators.iter_mut() {
+	activate_genesis_validators(&mut state);
+
+	Ok(state)
+}
+
+/// Initialize beacon state from an eth1 block, as in the spec's
+/// `initialize_beacon_state_from_eth1`. Deposits are processed against the
+/// deposit root of all deposits up to and including each of them.
+pub fn initialize_beacon_state_from_eth1<C: Config, BLS: BLSConfig>(
+	eth1_block_hash: H256,
+	eth1_timestamp: Uint,
+	deposits: &[Deposit],
+) -> Result<BeaconState<C>, Error> {
+	let genesis_time = eth1_timestamp - eth1_timestamp % consts::SECONDS_PER_DAY +
+		2 * consts::SECONDS_PER_DAY;
+	let mut state = genesis_beacon_state::<C, BLS>(&[], genesis_time, Eth1Data {
+		block_hash: eth1_block_hash,
+		deposit_count: deposits.len() as u64,
+		..Default::default()
+	})?;
+
+	let mut tree = DepositTree::<C>::new();
+	for deposit in deposits {
+		tree.push(tree_root::<C::Digest, _>(&deposit.data));
+		state.eth1_data.deposit_root = tree.root();
+		BeaconExecutive::new(&mut state).process_deposit::<BLS>(deposit.clone())?;
+	}
+
+	activate_genesis_validators(&mut state);
+
+	Ok(state)
+}
+
+/// Check whether the state is a valid genesis state.
+pub fn is_valid_genesis_state<C: Config>(state: &BeaconState<C>) -> bool {
+	if state.genesis_time < C::min_genesis_time() {
+		return false
+	}
+
+	let active_validator_count = state.validators.iter()
+		.filter(|validator| validator.is_active(C::genesis_epoch()))
+		.count() as u64;
+	active_validator_count >= C::min_genesis_active_validator_count()
+}
+
+fn activate_genesis_validators<C: Config>(state: &mut BeaconState<C>) {
+	for (validator, balance) in state.validators.iter_mut().zip(state.balances.iter()) {
+		// Effective balance only follows the first deposit of a validator,
+		// so recompute it to account for top-ups.
+		validator.effective_balance = core::cmp::min(
+			balance - balance % C::effective_balance_increment(),
+			C::max_effective_balance(),
+		);
 		if validator.effective_balance >= C::max_effective_balance() {
 			validator.activation_eligibility_epoch = C::genesis_epoch();
 			validator.activation_epoch = C::genesis_epoch();
 		}
 	}
+}
 
-	Ok(state)
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MinimalConfig, BLSNoVerification};
+	use crate::primitives::H384;
+	use crate::types::DepositData;
+	use core::convert::TryInto;
+
+	#[test]
+	fn activates_validators_funded_by_top_ups() {
+		let datas = [16_000_000_000, 16_000_000_000, 31_000_000_000].iter()
+			.enumerate()
+			.map(|(i, amount)| DepositData {
+				pubkey: H384::repeat_byte(if i < 2 { 0 } else { 1 }),
+				amount: *amount,
+				..Default::default()
+			})
+			.collect::<Vec<_>>();
+
+		let mut tree = DepositTree::<MinimalConfig>::new();
+		let deposits = datas.iter().enumerate().map(|(i, data)| {
+			tree.push(tree_root::<<MinimalConfig as Config>::Digest, _>(data));
+			Deposit {
+				proof: tree.proof(i as u64, i as u64 + 1).unwrap().try_into().unwrap(),
+				data: data.clone(),
+			}
+		}).collect::<Vec<_>>();
+
+		let state = initialize_beacon_state_from_eth1::<MinimalConfig, BLSNoVerification>(
+			Default::default(), 0, &deposits,
+		).unwrap();
+		assert_eq!(state.validators.len(), 2);
+		assert_eq!(state.validators[0].effective_balance, 32_000_000_000);
+		assert_eq!(state.validators[0].activation_epoch, MinimalConfig::genesis_epoch());
+		assert_eq!(state.validators[1].effective_balance, 31_000_000_000);
+		assert_eq!(state.validators[1].activation_epoch, consts::FAR_FUTURE_EPOCH);
+	}
 }
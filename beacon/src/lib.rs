@@ -30,6 +30,14 @@ pub mod consts;
 pub mod utils;
 /// Components for reuse.
 pub mod components;
+/// Caches shared across state transitions.
+pub mod cache;
+/// Structural state differences.
+pub mod diff;
+/// Merkle proofs for beacon chain containers.
+pub mod proof;
+/// Light client finality updates.
+pub mod light_client;
 
 mod error;
 mod config;
@@ -41,8 +49,13 @@ pub use self::config::{
 	BLSConfig, BLSNoVerification,
 	Config, MinimalConfig, MainnetConfig, SapphireConfig,
 };
+#[cfg(feature = "std")]
+pub use self::config::{RuntimeConfig, RuntimeParameters, RuntimeConfigError};
 pub use self::executive::{BeaconState, BeaconExecutive};
-pub use self::genesis::{genesis, genesis_beacon_state};
+pub use self::cache::BeaconCache;
+pub use self::genesis::{
+	genesis, genesis_beacon_state, initialize_beacon_state_from_eth1, is_valid_genesis_state,
+};
 
 use self::primitives::{H256, H768};
 use self::types::{
@@ -55,9 +68,10 @@ use bm_le::tree_root;
 /// Given a block, execute based on a parent state.
 pub fn execute_block<C: Config, BLS: BLSConfig>(
 	block: &BeaconBlock<C>,
-	state: &mut BeaconState<C>
+	state: &mut BeaconState<C>,
+	cache: Option<&mut BeaconCache>,
 ) -> Result<(), Error> {
-	let mut executive = BeaconExecutive::new(state);
+	let mut executive = BeaconExecutive::with_cache(state, cache);
 	executive.state_transition::<_, BLS>(block)
 }
 
@@ -91,9 +105,10 @@ pub enum Transaction<C: Config> {
 /// Initialize a block, and apply inherents.
 pub fn initialize_block<C: Config>(
 	state: &mut BeaconState<C>,
-	target_slot: u64
+	target_slot: u64,
+	cache: Option<&mut BeaconCache>,
 ) -> Result<(), Error> {
-	let mut executive = BeaconExecutive::new(state);
+	let mut executive = BeaconExecutive::with_cache(state, cache);
 	executive.process_slots(target_slot)
 }
 
@@ -101,7 +116,8 @@ pub fn initialize_block<C: Config>(
 pub fn apply_inherent<C: Config, BLS: BLSConfig>(
 	parent_block: &BeaconBlock<C>,
 	state: &mut BeaconState<C>,
-	inherent: Inherent
+	inherent: Inherent,
+	cache: Option<&mut BeaconCache>,
 ) -> Result<UnsealedBeaconBlock<C>, Error> {
 	let body = BeaconBlockBody {
 		randao_reveal: inherent.randao_reveal,
@@ -120,7 +136,7 @@ pub fn apply_inherent<C: Config, BLS: BLSConfig>(
 		&SigningBeaconBlockHeader::from(state.latest_block_header.clone())
 	);
 
-	let mut executive = BeaconExecutive::new(state);
+	let mut executive = BeaconExecutive::with_cache(state, cache);
 	executive.process_randao::<BLS>(block.body())?;
 	executive.process_eth1_data(block.body());
 
@@ -132,8 +148,9 @@ pub fn apply_transaction<C: Config, BLS: BLSConfig>(
 	block: &mut UnsealedBeaconBlock<C>,
 	state: &mut BeaconState<C>,
 	extrinsic: Transaction<C>,
+	cache: Option<&mut BeaconCache>,
 ) -> Result<(), Error> {
-	let mut executive = BeaconExecutive::new(state);
+	let mut executive = BeaconExecutive::with_cache(state, cache);
 	match extrinsic {
 		Transaction::ProposerSlashing(slashing) => {
 			if block.body.proposer_slashings.len() >= C::max_proposer_slashings() as usize {
@@ -177,7 +194,8 @@ pub fn apply_transaction<C: Config, BLS: BLSConfig>(
 /// Finalize an unsealed block.
 pub fn finalize_block<C: Config, BLS: BLSConfig>(
 	block: &mut UnsealedBeaconBlock<C>,
-	state: &mut BeaconState<C>
+	state: &mut BeaconState<C>,
+	cache: Option<&mut BeaconCache>,
 ) -> Result<(), Error> {
 	if state.eth1_data.deposit_count < state.eth1_deposit_index {
 		return Err(Error::InvalidEth1Data)
@@ -190,10 +208,10 @@ pub fn finalize_block<C: Config, BLS: BLSConfig>(
 		return Err(Error::TooManyDeposits)
 	}
 
-	let mut executive = BeaconExecutive::new(state);
+	let mut executive = BeaconExecutive::with_cache(state, cache);
 	executive.process_block_header::<_, BLS>(block)?;
 
-	block.state_root = tree_root::<C::Digest, _>(state);
+	block.state_root = executive.state_root();
 
 	Ok(())
 }
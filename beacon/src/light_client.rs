@@ -0,0 +1,398 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use alloc::vec::Vec;
+use alloc::collections::BTreeSet;
+use core::convert::TryFrom;
+use core::fmt;
+use ssz::{Codec, Encode, Decode};
+use bm_le::{tree_root, MaxVec};
+use vecarray::VecArray;
+use typenum::U5;
+use crate::primitives::{H256, Uint};
+use crate::types::{
+	BeaconBlockHeader, SigningBeaconBlockHeader, Checkpoint, Fork, Validator,
+	IndexedAttestation, AttestationDataAndCustodyBit, Block,
+};
+use crate::utils::{self, is_valid_merkle_branch};
+use crate::proof::prove;
+use crate::{Config, BLSConfig, BeaconState};
+
+/// Index of `finalized_checkpoint` among the fields of `BeaconState`.
+pub const FINALIZED_CHECKPOINT_INDEX: u64 = 19;
+/// Depth of `finalized_checkpoint` in the `BeaconState` tree.
+pub const FINALIZED_CHECKPOINT_DEPTH: u64 = 5;
+
+/// Error for building or verifying light client updates.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LightClientError {
+	/// Attested header does not commit to the given state.
+	StateRootMismatch,
+	/// Finalized header does not match the finalized checkpoint.
+	InvalidFinalizedHeader,
+	/// Finality branch does not verify against the attested state root.
+	InvalidFinalityBranch,
+	/// Update does not finalize a newer epoch than the trusted checkpoint.
+	NotNewer,
+	/// Too many attestations for a single update.
+	TooManyAttestations,
+	/// Attestation does not target the attested header.
+	InvalidAttestation,
+	/// Attestation references a validator outside of the known set.
+	UnknownValidator,
+	/// Attestation signature is invalid.
+	InvalidSignature,
+	/// Attesting balance is below two thirds of the active balance.
+	InsufficientParticipation,
+}
+
+impl fmt::Display for LightClientError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LightClientError::StateRootMismatch => write!(f, "State root mismatch"),
+			LightClientError::InvalidFinalizedHeader => write!(f, "Invalid finalized header"),
+			LightClientError::InvalidFinalityBranch => write!(f, "Invalid finality branch"),
+			LightClientError::NotNewer => write!(f, "Update is not newer than trusted checkpoint"),
+			LightClientError::TooManyAttestations => write!(f, "Too many attestations"),
+			LightClientError::InvalidAttestation => write!(f, "Invalid attestation"),
+			LightClientError::UnknownValidator => write!(f, "Unknown validator"),
+			LightClientError::InvalidSignature => write!(f, "Invalid signature"),
+			LightClientError::InsufficientParticipation => write!(f, "Insufficient participation"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LightClientError { }
+
+#[derive(Codec, Encode, Decode, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(bound = "C: Config"))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Finality update for light clients.
+pub struct LightClientUpdate<C: Config> {
+	/// Header of the epoch boundary block whose state contains the finalized
+	/// checkpoint.
+	pub attested_header: BeaconBlockHeader,
+	/// Header of the finalized block.
+	pub finalized_header: BeaconBlockHeader,
+	/// Finalized checkpoint of the attested state.
+	pub finalized_checkpoint: Checkpoint,
+	/// Merkle branch of the finalized checkpoint in the attested state.
+	pub finality_branch: VecArray<H256, U5>,
+	/// Attestations of a single epoch whose target is the attested header,
+	/// bounded so that every committee of the epoch can be included.
+	pub attestations: MaxVec<IndexedAttestation<C>, C::MaxAttestationsPerEpoch>,
+}
+
+/// Header of a block, including its signature.
+pub fn block_header<B: Block>(block: &B) -> BeaconBlockHeader {
+	BeaconBlockHeader {
+		slot: block.slot(),
+		parent_root: *block.parent_root(),
+		state_root: *block.state_root(),
+		body_root: tree_root::<<B::Config as Config>::Digest, _>(block.body()),
+		signature: block.signature().cloned().unwrap_or_default(),
+	}
+}
+
+/// Signing root of a block header, which is the block root.
+pub fn header_root<C: Config>(header: &BeaconBlockHeader) -> H256 {
+	tree_root::<C::Digest, _>(&SigningBeaconBlockHeader::from(header.clone()))
+}
+
+/// Build a finality update from the state of the attested header.
+pub fn build_update<C: Config>(
+	state: &BeaconState<C>,
+	attested_header: BeaconBlockHeader,
+	finalized_header: BeaconBlockHeader,
+	attestations: Vec<IndexedAttestation<C>>,
+) -> Result<LightClientUpdate<C>, LightClientError> {
+	if tree_root::<C::Digest, _>(state) != attested_header.state_root {
+		return Err(LightClientError::StateRootMismatch)
+	}
+
+	if header_root::<C>(&finalized_header) != state.finalized_checkpoint.root {
+		return Err(LightClientError::InvalidFinalizedHeader)
+	}
+
+	if attestations.len() as u64 > C::max_attestations_per_epoch() {
+		return Err(LightClientError::TooManyAttestations)
+	}
+
+	let proof = prove::<C, _>(state, "finalized_checkpoint")
+		.expect("finalized_checkpoint is a field of BeaconState; qed");
+	let finality_branch = VecArray::try_from(proof.branch)
+		.expect("finalized_checkpoint is at depth 5 of BeaconState; qed");
+
+	Ok(LightClientUpdate {
+		attested_header,
+		finalized_header,
+		finalized_checkpoint: state.finalized_checkpoint.clone(),
+		finality_branch,
+		attestations: attestations.into(),
+	})
+}
+
+/// Light client following finality from a trusted checkpoint.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LightClient<C: Config> {
+	finalized_checkpoint: Checkpoint,
+	finalized_header: Option<BeaconBlockHeader>,
+	validators: Vec<Validator>,
+	fork: Fork,
+	_marker: core::marker::PhantomData<C>,
+}
+
+impl<C: Config> LightClient<C> {
+	/// Create a new light client from a trusted checkpoint and validator set.
+	pub fn new(finalized_checkpoint: Checkpoint, validators: Vec<Validator>, fork: Fork) -> Self {
+		Self {
+			finalized_checkpoint,
+			finalized_header: None,
+			validators,
+			fork,
+			_marker: core::marker::PhantomData,
+		}
+	}
+
+	/// Latest trusted finalized checkpoint.
+	pub fn finalized_checkpoint(&self) -> &Checkpoint {
+		&self.finalized_checkpoint
+	}
+
+	/// Header of the latest trusted finalized block, if known.
+	pub fn finalized_header(&self) -> Option<&BeaconBlockHeader> {
+		self.finalized_header.as_ref()
+	}
+
+	/// Validator set used to verify attestations.
+	pub fn validators(&self) -> &[Validator] {
+		&self.validators
+	}
+
+	/// Replace the validator set and fork used to verify attestations.
+	pub fn set_validators(&mut self, validators: Vec<Validator>, fork: Fork) {
+		self.validators = validators;
+		self.fork = fork;
+	}
+
+	/// Verify an update without applying it.
+	pub fn verify<BLS: BLSConfig>(
+		&self,
+		update: &LightClientUpdate<C>,
+	) -> Result<(), LightClientError> {
+		if update.finalized_checkpoint.epoch <= self.finalized_checkpoint.epoch {
+			return Err(LightClientError::NotNewer)
+		}
+
+		if header_root::<C>(&update.finalized_header) != update.finalized_checkpoint.root ||
+			update.finalized_header.slot >
+			utils::start_slot_of_epoch::<C>(update.finalized_checkpoint.epoch)
+		{
+			return Err(LightClientError::InvalidFinalizedHeader)
+		}
+
+		if !is_valid_merkle_branch::<C>(
+			tree_root::<C::Digest, _>(&update.finalized_checkpoint),
+			&update.finality_branch[..],
+			FINALIZED_CHECKPOINT_DEPTH,
+			FINALIZED_CHECKPOINT_INDEX,
+			update.attested_header.state_root,
+		) {
+			return Err(LightClientError::InvalidFinalityBranch)
+		}
+
+		let attested_root = header_root::<C>(&update.attested_header);
+		let epoch = match update.attestations.first() {
+			Some(attestation) => attestation.data.target.epoch,
+			None => return Err(LightClientError::InsufficientParticipation),
+		};
+		if epoch < utils::epoch_of_slot::<C>(update.attested_header.slot) {
+			return Err(LightClientError::InvalidAttestation)
+		}
+		let mut attesters = BTreeSet::new();
+
+		for attestation in update.attestations.iter() {
+			if attestation.data.target.root != attested_root ||
+				attestation.data.target.epoch != epoch
+			{
+				return Err(LightClientError::InvalidAttestation)
+			}
+
+			self.verify_attestation::<BLS>(attestation)?;
+			attesters.extend(attestation.custody_bit_0_indices.iter().cloned());
+			attesters.extend(attestation.custody_bit_1_indices.iter().cloned());
+		}
+
+		let total_balance = self.validators.iter()
+			.filter(|v| v.is_active(epoch))
+			.fold(0, |acc: Uint, v| acc.saturating_add(v.effective_balance));
+		let attesting_balance = attesters.into_iter()
+			.map(|index| &self.validators[index as usize])
+			.filter(|v| v.is_active(epoch) && !v.slashed)
+			.fold(0, |acc: Uint, v| acc.saturating_add(v.effective_balance));
+
+		if total_balance == 0 ||
+			attesting_balance.saturating_mul(3) < total_balance.saturating_mul(2)
+		{
+			return Err(LightClientError::InsufficientParticipation)
+		}
+
+		Ok(())
+	}
+
+	/// Verify an update and, if valid, move the trusted checkpoint forward.
+	pub fn process_update<BLS: BLSConfig>(
+		&mut self,
+		update: &LightClientUpdate<C>,
+	) -> Result<(), LightClientError> {
+		self.verify::<BLS>(update)?;
+		self.finalized_checkpoint = update.finalized_checkpoint.clone();
+		self.finalized_header = Some(update.finalized_header.clone());
+		Ok(())
+	}
+
+	fn verify_attestation<BLS: BLSConfig>(
+		&self,
+		attestation: &IndexedAttestation<C>,
+	) -> Result<(), LightClientError> {
+		let bit_0_indices = &attestation.custody_bit_0_indices;
+		let bit_1_indices = &attestation.custody_bit_1_indices;
+
+		if bit_1_indices.len() > 0 ||
+			!bit_0_indices.windows(2).all(|w| w[0] < w[1])
+		{
+			return Err(LightClientError::InvalidAttestation)
+		}
+
+		let pubkeys = |indices: &[Uint]| -> Result<Vec<_>, LightClientError> {
+			indices.iter()
+				.map(|i| self.validators.get(*i as usize)
+					.map(|v| v.pubkey)
+					.ok_or(LightClientError::UnknownValidator))
+				.collect()
+		};
+
+		let fork_version = if attestation.data.target.epoch < self.fork.epoch {
+			self.fork.previous_version
+		} else {
+			self.fork.current_version
+		};
+
+		if !BLS::verify_multiple(
+			&[
+				BLS::aggregate_pubkeys(&pubkeys(&bit_0_indices[..])?),
+				BLS::aggregate_pubkeys(&pubkeys(&bit_1_indices[..])?),
+			],
+			&[
+				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+					data: attestation.data.clone(),
+					custody_bit: false,
+				}),
+				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+					data: attestation.data.clone(),
+					custody_bit: true,
+				}),
+			],
+			&attestation.signature,
+			utils::bls_domain(C::domain_beacon_attester(), fork_version),
+		) {
+			return Err(LightClientError::InvalidSignature)
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{MinimalConfig, BLSNoVerification};
+	use crate::types::AttestationData;
+
+	type C = MinimalConfig;
+
+	fn validators() -> Vec<Validator> {
+		(0..4).map(|_| Validator {
+			effective_balance: 32_000_000_000,
+			exit_epoch: u64::max_value(),
+			withdrawable_epoch: u64::max_value(),
+			..Default::default()
+		}).collect()
+	}
+
+	fn update(signers: &[Uint]) -> LightClientUpdate<C> {
+		let finalized_header = BeaconBlockHeader {
+			slot: 8,
+			..Default::default()
+		};
+
+		let mut state = BeaconState::<C>::default();
+		state.slot = 25;
+		state.validators = validators().into();
+		state.finalized_checkpoint = Checkpoint {
+			epoch: 1,
+			root: header_root::<C>(&finalized_header),
+		};
+
+		let attested_header = BeaconBlockHeader {
+			slot: 25,
+			state_root: tree_root::<<C as Config>::Digest, _>(&state),
+			..Default::default()
+		};
+		let attestation = IndexedAttestation {
+			custody_bit_0_indices: signers.to_vec().into(),
+			data: AttestationData {
+				slot: 27,
+				target: Checkpoint { epoch: 3, root: header_root::<C>(&attested_header) },
+				..Default::default()
+			},
+			..Default::default()
+		};
+
+		build_update(&state, attested_header, finalized_header, vec![attestation]).unwrap()
+	}
+
+	#[test]
+	fn follows_finality() {
+		let mut client = LightClient::<C>::new(
+			Checkpoint::default(), validators(), Fork::default()
+		);
+
+		assert_eq!(
+			client.process_update::<BLSNoVerification>(&update(&[0, 1])),
+			Err(LightClientError::InsufficientParticipation)
+		);
+
+		let mut invalid = update(&[0, 1, 2]);
+		invalid.finality_branch[2] = H256::repeat_byte(0x42);
+		assert_eq!(
+			client.process_update::<BLSNoVerification>(&invalid),
+			Err(LightClientError::InvalidFinalityBranch)
+		);
+
+		let update = update(&[0, 1, 2]);
+		assert_eq!(client.process_update::<BLSNoVerification>(&update), Ok(()));
+		assert_eq!(client.finalized_checkpoint().epoch, 1);
+		assert_eq!(
+			client.process_update::<BLSNoVerification>(&update),
+			Err(LightClientError::NotNewer)
+		);
+	}
+}
@@ -0,0 +1,776 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+use bm_le::{tree_root, CompactRef, MaxVec};
+use vecarray::VecArray;
+use typenum::Unsigned;
+use crate::primitives::{H32, H256, H384, H768, Uint};
+use crate::types::*;
+use crate::cache::tree::{ChunkTree, hash_pair, mix_in_length, depth, packed_chunks, pack};
+use crate::{Config, BeaconState};
+
+/// Maximum depth of a generalized index that fits in `u64`.
+const MAX_DEPTH: usize = 63;
+
+/// Error when generating a proof.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProofError {
+	/// Path could not be parsed.
+	InvalidPath,
+	/// Container does not have the field.
+	UnknownField,
+	/// Field access on a value that is not a container.
+	NotAContainer,
+	/// Index access on a value that is not a list or vector.
+	NotAList,
+	/// List or vector index is out of bounds.
+	IndexOutOfBounds,
+	/// Generalized index does not fit in `u64`.
+	TooDeep,
+}
+
+impl fmt::Display for ProofError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ProofError::InvalidPath => write!(f, "Invalid path"),
+			ProofError::UnknownField => write!(f, "Unknown field"),
+			ProofError::NotAContainer => write!(f, "Value is not a container"),
+			ProofError::NotAList => write!(f, "Value is not a list or vector"),
+			ProofError::IndexOutOfBounds => write!(f, "Index out of bounds"),
+			ProofError::TooDeep => write!(f, "Generalized index too deep"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError { }
+
+/// Element of a field path.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathElement<'a> {
+	/// Container field.
+	Field(&'a str),
+	/// List or vector index.
+	Index(usize),
+}
+
+/// Parse a field path such as `validators[42].effective_balance`.
+pub fn parse_path(path: &str) -> Result<Vec<PathElement>, ProofError> {
+	let mut elements = Vec::new();
+	if path.is_empty() {
+		return Ok(elements)
+	}
+
+	for segment in path.split('.') {
+		let (name, mut rest) = match segment.find('[') {
+			Some(i) => (&segment[..i], &segment[i..]),
+			None => (segment, ""),
+		};
+		if !name.is_empty() {
+			elements.push(PathElement::Field(name));
+		} else if rest.is_empty() {
+			return Err(ProofError::InvalidPath)
+		}
+
+		while !rest.is_empty() {
+			let end = rest.find(']').ok_or(ProofError::InvalidPath)?;
+			if !rest.starts_with('[') {
+				return Err(ProofError::InvalidPath)
+			}
+			let index = rest[1..end].parse().map_err(|_| ProofError::InvalidPath)?;
+			elements.push(PathElement::Index(index));
+			rest = &rest[(end + 1)..];
+		}
+	}
+
+	Ok(elements)
+}
+
+/// Roots of the children of a container, list or vector.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Layer {
+	/// Roots of the children, or packed chunks of basic values.
+	pub chunks: Vec<H256>,
+	/// Depth of the merkle tree over the chunks.
+	pub depth: usize,
+	/// Length mixed into the root, for lists.
+	pub length: Option<usize>,
+}
+
+impl Layer {
+	fn tree<C: Config>(&self) -> ChunkTree {
+		let mut tree = ChunkTree::new::<C>(self.depth);
+		tree.update::<C>(self.depth, &self.chunks);
+		tree
+	}
+
+	/// Depth of the children below the value, including the length mix-in.
+	fn child_depth(&self) -> usize {
+		self.depth + if self.length.is_some() { 1 } else { 0 }
+	}
+
+	/// Root of the value.
+	pub fn root<C: Config>(&self) -> H256 {
+		let root = self.tree::<C>().root();
+		match self.length {
+			Some(length) => mix_in_length::<C>(&root, length),
+			None => root,
+		}
+	}
+}
+
+/// Value whose merkle tree can be navigated by field path.
+pub trait Provable<C: Config> {
+	/// Hash tree root of the value.
+	fn proof_root(&self) -> H256 {
+		self.layer().map(|layer| layer.root::<C>()).unwrap_or_default()
+	}
+
+	/// Roots of the children. `None` for basic values.
+	fn layer(&self) -> Option<Layer> {
+		None
+	}
+
+	/// Index of the chunk for the given path element in the value's layer,
+	/// and the child value.
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		_layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		Err(not_composite(element))
+	}
+}
+
+fn not_composite(element: PathElement) -> ProofError {
+	match element {
+		PathElement::Field(_) => ProofError::NotAContainer,
+		PathElement::Index(_) => ProofError::NotAList,
+	}
+}
+
+/// Merkle proof of a single node.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Proof {
+	/// Generalized index of the node.
+	pub gindex: u64,
+	/// The node.
+	pub leaf: H256,
+	/// Sibling nodes from the leaf up to the root.
+	pub branch: Vec<H256>,
+}
+
+impl Proof {
+	/// Calculate the root of the proof. Returns `None` if the branch length
+	/// does not match the generalized index.
+	pub fn root<C: Config>(&self) -> Option<H256> {
+		if self.gindex == 0 || gindex_depth(self.gindex) != self.branch.len() {
+			return None
+		}
+
+		let mut node = self.leaf;
+		for (i, sibling) in self.branch.iter().enumerate() {
+			node = if (self.gindex >> i) & 1 == 1 {
+				hash_pair::<C>(sibling, &node)
+			} else {
+				hash_pair::<C>(&node, sibling)
+			};
+		}
+		Some(node)
+	}
+}
+
+/// Merkle proof of multiple nodes, sharing helper nodes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Multiproof {
+	/// Generalized indices of the nodes.
+	pub gindices: Vec<u64>,
+	/// The nodes.
+	pub leaves: Vec<H256>,
+	/// Helper nodes, in the order of `helper_indices`.
+	pub helpers: Vec<H256>,
+}
+
+impl Multiproof {
+	/// Calculate the root of the multiproof. Returns `None` if the proof is
+	/// malformed, or if a supplied node does not match the node computed
+	/// from its children.
+	pub fn root<C: Config>(&self) -> Option<H256> {
+		let indices = helper_indices(&self.gindices);
+		if self.leaves.len() != self.gindices.len() || self.helpers.len() != indices.len() {
+			return None
+		}
+
+		let mut objects = BTreeMap::new();
+		for (gindex, node) in self.gindices.iter().zip(self.leaves.iter())
+			.chain(indices.iter().zip(self.helpers.iter()))
+		{
+			if objects.insert(*gindex, *node).map_or(false, |existing| existing != *node) {
+				return None
+			}
+		}
+
+		// Deeper nodes have larger generalized indices, so visiting keys in
+		// descending order computes all children before their parent. A
+		// left node whose sibling exists was handled with that sibling.
+		let mut pending = objects.keys().cloned().collect::<BTreeSet<_>>();
+		while let Some(key) = pending.iter().next_back().cloned() {
+			pending.remove(&key);
+			if key <= 1 || (key & 1 == 0 && objects.contains_key(&(key | 1))) {
+				continue
+			}
+			let parent = match (objects.get(&(key & !1)), objects.get(&(key | 1))) {
+				(Some(left), Some(right)) => hash_pair::<C>(left, right),
+				_ => continue,
+			};
+
+			match objects.get(&(key / 2)) {
+				Some(existing) if *existing != parent => return None,
+				Some(_) => (),
+				None => {
+					objects.insert(key / 2, parent);
+					pending.insert(key / 2);
+				},
+			}
+		}
+
+		objects.get(&1).cloned()
+	}
+}
+
+/// Nodes of the value's merkle tree collected while proving paths.
+struct Collected {
+	/// Sibling nodes by generalized index.
+	nodes: BTreeMap<u64, H256>,
+	/// Generalized index and node of each path.
+	leaves: Vec<(u64, H256)>,
+}
+
+/// Walk the given paths of a value, visiting each shared prefix only once.
+/// Paths are given with their position in `collected.leaves`.
+fn collect<C: Config>(
+	value: &dyn Provable<C>,
+	gindex: u64,
+	depth: usize,
+	paths: &[(usize, &[PathElement])],
+	collected: &mut Collected,
+) -> Result<(), ProofError> {
+	let first = match paths.first() {
+		Some((_, elements)) => elements[0],
+		None => return Ok(()),
+	};
+	let layer = value.layer().ok_or_else(|| not_composite(first))?;
+	let tree = layer.tree::<C>();
+	let child_depth = depth + layer.child_depth();
+	if child_depth > MAX_DEPTH {
+		return Err(ProofError::TooDeep)
+	}
+
+	let mut elements = Vec::<PathElement>::new();
+	for (_, path) in paths {
+		if !elements.contains(&path[0]) {
+			elements.push(path[0]);
+		}
+	}
+
+	for element in elements {
+		let (index, child) = value.child(element, &layer)?;
+		let child_gindex = (gindex << layer.child_depth()) | index as u64;
+
+		let mut branch = tree.branch(index);
+		if let Some(length) = layer.length {
+			branch.push(length_chunk(length));
+		}
+		for (i, sibling) in branch.into_iter().enumerate() {
+			collected.nodes.insert((child_gindex >> i) ^ 1, sibling);
+		}
+
+		let mut rest = Vec::new();
+		for (pos, path) in paths.iter().filter(|(_, path)| path[0] == element) {
+			if path.len() == 1 {
+				collected.leaves[*pos] = (child_gindex, layer.chunks[index]);
+			} else {
+				rest.push((*pos, &path[1..]));
+			}
+		}
+		collect::<C>(&*child, child_gindex, child_depth, &rest, collected)?;
+	}
+
+	Ok(())
+}
+
+fn collect_paths<C: Config>(
+	value: &dyn Provable<C>,
+	paths: &[&str],
+) -> Result<Collected, ProofError> {
+	let parsed = paths.iter().map(|path| parse_path(path)).collect::<Result<Vec<_>, _>>()?;
+	let mut collected = Collected {
+		nodes: BTreeMap::new(),
+		leaves: alloc::vec![(1, H256::default()); paths.len()],
+	};
+
+	let mut nested = Vec::new();
+	let mut value_root = None;
+	for (pos, elements) in parsed.iter().enumerate() {
+		if elements.is_empty() {
+			let root = *value_root.get_or_insert_with(|| value.proof_root());
+			collected.leaves[pos] = (1, root);
+		} else {
+			nested.push((pos, &elements[..]));
+		}
+	}
+	collect::<C>(value, 1, 0, &nested, &mut collected)?;
+
+	Ok(collected)
+}
+
+/// Generate a proof for the given field path of a value.
+pub fn prove<C: Config, T: Provable<C>>(value: &T, path: &str) -> Result<Proof, ProofError> {
+	let collected = collect_paths::<C>(value, &[path])?;
+	let (gindex, leaf) = collected.leaves[0];
+	let branch = (0..gindex_depth(gindex))
+		.map(|i| collected.nodes[&((gindex >> i) ^ 1)])
+		.collect();
+
+	Ok(Proof { gindex, leaf, branch })
+}
+
+/// Generate a multiproof for the given field paths of a value.
+pub fn prove_multi<C: Config, T: Provable<C>>(
+	value: &T,
+	paths: &[&str],
+) -> Result<Multiproof, ProofError> {
+	let collected = collect_paths::<C>(value, paths)?;
+	let (gindices, leaves): (Vec<_>, Vec<_>) = collected.leaves.into_iter().unzip();
+	let helpers = helper_indices(&gindices).into_iter()
+		.map(|gindex| collected.nodes[&gindex])
+		.collect();
+
+	Ok(Multiproof { gindices, leaves, helpers })
+}
+
+/// Generalized indices of helper nodes needed to prove the given nodes, in
+/// descending order.
+pub fn helper_indices(gindices: &[u64]) -> Vec<u64> {
+	let mut helpers = BTreeSet::new();
+	let mut paths = BTreeSet::new();
+
+	for gindex in gindices {
+		let mut gindex = *gindex;
+		while gindex > 1 {
+			helpers.insert(gindex ^ 1);
+			paths.insert(gindex);
+			gindex /= 2;
+		}
+	}
+
+	helpers.difference(&paths).rev().cloned().collect()
+}
+
+/// Verify a proof against the given root.
+pub fn verify_proof<C: Config>(root: H256, proof: &Proof) -> bool {
+	proof.root::<C>() == Some(root)
+}
+
+/// Verify a multiproof against the given root.
+pub fn verify_multiproof<C: Config>(root: H256, proof: &Multiproof) -> bool {
+	proof.root::<C>() == Some(root)
+}
+
+fn gindex_depth(gindex: u64) -> usize {
+	63 - gindex.leading_zeros() as usize
+}
+
+fn length_chunk(len: usize) -> H256 {
+	let mut chunk = H256::default();
+	chunk[..8].copy_from_slice(&(len as u64).to_le_bytes());
+	chunk
+}
+
+fn boxed<'a, C: Config, T: Provable<C> + 'a>(value: T) -> Box<dyn Provable<C> + 'a> {
+	Box::new(value)
+}
+
+fn item_index(element: PathElement, len: usize) -> Result<usize, ProofError> {
+	match element {
+		PathElement::Index(index) if index < len => Ok(index),
+		PathElement::Index(_) => Err(ProofError::IndexOutOfBounds),
+		PathElement::Field(_) => Err(ProofError::NotAContainer),
+	}
+}
+
+impl<'b, C: Config, T: Provable<C>> Provable<C> for &'b T {
+	fn proof_root(&self) -> H256 {
+		(**self).proof_root()
+	}
+
+	fn layer(&self) -> Option<Layer> {
+		(**self).layer()
+	}
+
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		(**self).child(element, layer)
+	}
+}
+
+macro_rules! impl_leaf {
+	( $( $t:ty ),* ) => { $(
+		impl<C: Config> Provable<C> for $t {
+			fn proof_root(&self) -> H256 {
+				tree_root::<C::Digest, _>(self)
+			}
+		}
+	)* }
+}
+
+impl_leaf!(Uint, bool, H32, H256, H384, H768);
+
+impl<C: Config, T: Provable<C>, L: Unsigned> Provable<C> for MaxVec<T, L> {
+	fn layer(&self) -> Option<Layer> {
+		Some(Layer {
+			chunks: self.iter().map(|item| item.proof_root()).collect(),
+			depth: depth(L::to_u64()),
+			length: Some(self.len()),
+		})
+	}
+
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		_layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		let index = item_index(element, self.len())?;
+		Ok((index, boxed(&self[index])))
+	}
+}
+
+impl<C: Config, T: Provable<C>, L: Unsigned> Provable<C> for VecArray<T, L> {
+	fn layer(&self) -> Option<Layer> {
+		Some(Layer {
+			chunks: self.iter().map(|item| item.proof_root()).collect(),
+			depth: depth(L::to_u64()),
+			length: None,
+		})
+	}
+
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		_layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		let index = item_index(element, self.len())?;
+		Ok((index, boxed(&self[index])))
+	}
+}
+
+/// Packed list or vector of basic values. Indexing into it proves the chunk
+/// containing the value.
+struct Packed<'a, T>(&'a T);
+
+impl<'b, C: Config, L: Unsigned> Provable<C> for Packed<'b, MaxVec<Uint, L>> {
+	fn proof_root(&self) -> H256 {
+		tree_root::<C::Digest, _>(&CompactRef(self.0))
+	}
+
+	fn layer(&self) -> Option<Layer> {
+		Some(Layer {
+			chunks: pack(&self.0[..]),
+			depth: depth(packed_chunks(L::to_u64())),
+			length: Some(self.0.len()),
+		})
+	}
+
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		let index = item_index(element, self.0.len())? / 4;
+		Ok((index, boxed(layer.chunks[index])))
+	}
+}
+
+impl<'b, C: Config, L: Unsigned> Provable<C> for Packed<'b, VecArray<Uint, L>> {
+	fn proof_root(&self) -> H256 {
+		tree_root::<C::Digest, _>(&CompactRef(self.0))
+	}
+
+	fn layer(&self) -> Option<Layer> {
+		Some(Layer {
+			chunks: pack(&self.0[..]),
+			depth: depth(packed_chunks(L::to_u64())),
+			length: None,
+		})
+	}
+
+	fn child<'a>(
+		&'a self,
+		element: PathElement,
+		layer: &Layer,
+	) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+		let index = item_index(element, self.0.len())? / 4;
+		Ok((index, boxed(layer.chunks[index])))
+	}
+}
+
+impl<'b, C: Config, L: Unsigned> Provable<C> for Packed<'b, MaxVec<bool, L>> {
+	fn proof_root(&self) -> H256 {
+		tree_root::<C::Digest, _>(&CompactRef(self.0))
+	}
+}
+
+impl<'b, C: Config, L: Unsigned> Provable<C> for Packed<'b, VecArray<bool, L>> {
+	fn proof_root(&self) -> H256 {
+		tree_root::<C::Digest, _>(&CompactRef(self.0))
+	}
+}
+
+macro_rules! wrap_field {
+	( $wrap:ident $value:expr ) => { $wrap($value) };
+	( $value:expr ) => { $value };
+}
+
+macro_rules! impl_container {
+	( $t:ty { $( $field:ident $( ($wrap:ident) )? ),* $(,)? } ) => {
+		impl<C: Config> Provable<C> for $t {
+			fn layer(&self) -> Option<Layer> {
+				let chunks = alloc::vec![
+					$( Provable::<C>::proof_root(&wrap_field!($( $wrap )? &self.$field)) ),*
+				];
+				let depth = depth(chunks.len() as u64);
+				Some(Layer { chunks, depth, length: None })
+			}
+
+			fn child<'a>(
+				&'a self,
+				element: PathElement,
+				_layer: &Layer,
+			) -> Result<(usize, Box<dyn Provable<C> + 'a>), ProofError> {
+				let name = match element {
+					PathElement::Field(name) => name,
+					PathElement::Index(_) => return Err(ProofError::NotAList),
+				};
+				let names = [ $( stringify!($field) ),* ];
+				let index = names.iter().position(|n| *n == name)
+					.ok_or(ProofError::UnknownField)?;
+
+				let mut children: Vec<Box<dyn Provable<C> + 'a>> = alloc::vec![
+					$( boxed(wrap_field!($( $wrap )? &self.$field)) ),*
+				];
+				Ok((index, children.swap_remove(index)))
+			}
+		}
+	}
+}
+
+impl_container!(Fork { previous_version, current_version, epoch });
+impl_container!(Checkpoint { epoch, root });
+impl_container!(Validator {
+	pubkey, withdrawal_credentials, effective_balance, slashed,
+	activation_eligibility_epoch, activation_epoch, exit_epoch, withdrawable_epoch,
+});
+impl_container!(AttestationData { slot, index, beacon_block_root, source, target });
+impl_container!(IndexedAttestation<C> {
+	custody_bit_0_indices (Packed), custody_bit_1_indices (Packed), data, signature,
+});
+impl_container!(PendingAttestation<C> {
+	aggregation_bits (Packed), data, inclusion_delay, proposer_index,
+});
+impl_container!(Eth1Data { deposit_root, deposit_count, block_hash });
+impl_container!(DepositData { pubkey, withdrawal_credentials, amount, signature });
+impl_container!(BeaconBlockHeader { slot, parent_root, state_root, body_root, signature });
+impl_container!(ProposerSlashing { proposer_index, header_1, header_2 });
+impl_container!(AttesterSlashing<C> { attestation_1, attestation_2 });
+impl_container!(Attestation<C> {
+	aggregation_bits (Packed), data, custody_bits (Packed), signature,
+});
+impl_container!(Deposit { proof, data });
+impl_container!(VoluntaryExit { epoch, validator_index, signature });
+impl_container!(BeaconBlockBody<C> {
+	randao_reveal, eth1_data, graffiti, proposer_slashings, attester_slashings,
+	attestations, deposits, voluntary_exits,
+});
+impl_container!(BeaconBlock<C> { slot, parent_root, state_root, body, signature });
+impl_container!(BeaconState<C> {
+	genesis_time, slot, fork, latest_block_header, block_roots, state_roots,
+	historical_roots, eth1_data, eth1_data_votes, eth1_deposit_index, validators,
+	balances (Packed), randao_mixes, slashings (Packed), previous_epoch_attestations,
+	current_epoch_attestations, justification_bits (Packed),
+	previous_justified_checkpoint, current_justified_checkpoint, finalized_checkpoint,
+});
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimalConfig;
+
+	type C = MinimalConfig;
+
+	fn state() -> BeaconState<C> {
+		let mut state = BeaconState::<C>::default();
+		state.slot = 42;
+		state.finalized_checkpoint.root = H256::repeat_byte(0x11);
+		for i in 0..7 {
+			state.validators.push(Validator {
+				effective_balance: 32_000_000_000 + i,
+				..Default::default()
+			});
+			state.balances.push(i * 3);
+		}
+		state
+	}
+
+	#[test]
+	fn proofs_verify_against_state_root() {
+		let state = state();
+		let root = tree_root::<<C as Config>::Digest, _>(&state);
+		assert_eq!(Provable::<C>::proof_root(&state), root);
+
+		let slot = prove::<C, _>(&state, "slot").unwrap();
+		assert_eq!(slot.gindex, 33);
+		assert!(verify_proof::<C>(root, &slot));
+
+		let finalized = prove::<C, _>(&state, "finalized_checkpoint.root").unwrap();
+		assert_eq!(finalized.gindex, 103);
+		assert_eq!(finalized.leaf, H256::repeat_byte(0x11));
+		assert!(verify_proof::<C>(root, &finalized));
+
+		for path in &["validators[5].effective_balance", "balances[6]", "block_roots[3]", "fork"] {
+			let proof = prove::<C, _>(&state, path).unwrap();
+			assert!(verify_proof::<C>(root, &proof), "{}", path);
+		}
+
+		let mut invalid = finalized.clone();
+		invalid.leaf = H256::repeat_byte(0x22);
+		assert!(!verify_proof::<C>(root, &invalid));
+
+		assert_eq!(prove::<C, _>(&state, "validators[7]"), Err(ProofError::IndexOutOfBounds));
+		assert_eq!(prove::<C, _>(&state, "slot.epoch"), Err(ProofError::NotAContainer));
+		assert_eq!(prove::<C, _>(&state, "unknown"), Err(ProofError::UnknownField));
+	}
+
+	fn check_root<T: Provable<C> + bm_le::IntoTree>(value: &T) {
+		assert_eq!(value.proof_root(), tree_root::<<C as Config>::Digest, _>(value));
+	}
+
+	#[test]
+	fn proof_roots_match_tree_roots() {
+		let data = AttestationData {
+			slot: 3,
+			index: 1,
+			beacon_block_root: H256::repeat_byte(1),
+			source: Checkpoint { epoch: 1, root: H256::repeat_byte(2) },
+			target: Checkpoint { epoch: 2, root: H256::repeat_byte(3) },
+		};
+		let attestation = Attestation::<C> {
+			aggregation_bits: alloc::vec![true, false, true].into(),
+			custody_bits: alloc::vec![false, false, true].into(),
+			data: data.clone(),
+			signature: Default::default(),
+		};
+		let indexed = IndexedAttestation::<C> {
+			custody_bit_0_indices: alloc::vec![1, 2, 5].into(),
+			custody_bit_1_indices: alloc::vec![7].into(),
+			data: data.clone(),
+			signature: Default::default(),
+		};
+		let header = BeaconBlockHeader { slot: 9, ..Default::default() };
+		let deposit = Deposit {
+			data: DepositData { amount: 32, ..Default::default() },
+			..Default::default()
+		};
+		let exit = VoluntaryExit { epoch: 4, validator_index: 2, ..Default::default() };
+
+		let mut block = BeaconBlock::<C>::default();
+		block.slot = 9;
+		block.body.attestations.push(attestation.clone());
+		block.body.deposits.push(deposit.clone());
+		block.body.voluntary_exits.push(exit.clone());
+		block.body.proposer_slashings.push(ProposerSlashing {
+			proposer_index: 1, header_1: header.clone(), header_2: Default::default(),
+		});
+		block.body.attester_slashings.push(AttesterSlashing {
+			attestation_1: indexed.clone(), attestation_2: Default::default(),
+		});
+
+		let mut state = state();
+		state.current_epoch_attestations.push(PendingAttestation {
+			aggregation_bits: attestation.aggregation_bits.clone(),
+			data: data.clone(),
+			inclusion_delay: 1,
+			proposer_index: 3,
+		});
+		state.justification_bits[1] = true;
+		state.slashings[2] = 5;
+
+		check_root(&Fork::default());
+		check_root(&data.source);
+		check_root(&state.validators[3]);
+		check_root(&data);
+		check_root(&indexed);
+		check_root(&state.current_epoch_attestations[0]);
+		check_root(&Eth1Data { deposit_count: 5, ..Default::default() });
+		check_root(&deposit.data);
+		check_root(&header);
+		check_root(&block.body.proposer_slashings[0]);
+		check_root(&block.body.attester_slashings[0]);
+		check_root(&attestation);
+		check_root(&deposit);
+		check_root(&exit);
+		check_root(&block.body);
+		check_root(&block);
+		check_root(&state);
+		check_root(&BeaconState::<C>::default());
+	}
+
+	#[test]
+	fn multiproofs_verify_against_state_root() {
+		let state = state();
+		let root = tree_root::<<C as Config>::Digest, _>(&state);
+
+		let mut proof = prove_multi::<C, _>(&state, &[
+			"slot", "finalized_checkpoint.root", "validators[2].effective_balance",
+			"validators[3].slashed",
+		]).unwrap();
+		assert!(verify_multiproof::<C>(root, &proof));
+
+		proof.leaves[2] = H256::default();
+		assert!(!verify_multiproof::<C>(root, &proof));
+	}
+
+	#[test]
+	fn multiproofs_check_descendants_of_supplied_nodes() {
+		let state = state();
+		let root = tree_root::<<C as Config>::Digest, _>(&state);
+
+		let mut proof = prove_multi::<C, _>(&state, &[
+			"finalized_checkpoint", "finalized_checkpoint.root",
+		]).unwrap();
+		assert_eq!(proof.gindices, alloc::vec![51, 103]);
+		assert!(verify_multiproof::<C>(root, &proof));
+
+		proof.leaves[1] = H256::repeat_byte(0x22);
+		assert!(!verify_multiproof::<C>(root, &proof));
+	}
+}
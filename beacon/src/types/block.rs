@@ -18,7 +18,7 @@
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
-use ssz::{Codec, Encode, Decode};
+use ssz::{Codec, Encode, Decode, View};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use crate::Config;
 use crate::primitives::{H256, H768, Uint, Signature};
@@ -66,7 +66,7 @@ pub trait Block {
 	fn signature(&self) -> Option<&Signature>;
 }
 
-#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[derive(Codec, Encode, Decode, View, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
 #[cfg_attr(feature = "serde", serde(bound = "C: Config"))]
 #[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
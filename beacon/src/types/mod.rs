@@ -29,5 +29,5 @@ pub use self::operation::{
 	Deposit, VoluntaryExit, SigningVoluntaryExit
 };
 pub use self::block::{
-	BeaconBlockBody, BeaconBlock, UnsealedBeaconBlock, Block,
+	BeaconBlockBody, BeaconBlock, BeaconBlockView, UnsealedBeaconBlock, Block,
 };
@@ -0,0 +1,228 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::primitives::H256;
+use crate::{Config, Error, consts};
+use super::to_bytes;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+const DEPTH: usize = consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize;
+
+/// Incremental deposit merkle tree, following the eth1 deposit contract.
+///
+/// Only nodes of complete subtrees are stored, so that pushing a deposit is
+/// `O(depth)`, and roots and proofs can be built for any past deposit count.
+#[derive(Clone, Debug)]
+pub struct DepositTree<C: Config> {
+	nodes: Vec<Vec<H256>>,
+	zero_hashes: Vec<H256>,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config> Default for DepositTree<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<C: Config> DepositTree<C> {
+	/// Create a new empty deposit tree.
+	pub fn new() -> Self {
+		let mut zero_hashes = Vec::with_capacity(DEPTH + 1);
+		zero_hashes.push(H256::default());
+		for height in 1..(DEPTH + 1) {
+			zero_hashes.push(C::hash(&[
+				zero_hashes[height - 1].as_ref(),
+				zero_hashes[height - 1].as_ref(),
+			]));
+		}
+
+		Self {
+			nodes: (0..(DEPTH + 1)).map(|_| Vec::new()).collect(),
+			zero_hashes,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Create a new deposit tree from the given leaves.
+	pub fn from_leaves<I: IntoIterator<Item=H256>>(leaves: I) -> Self {
+		let mut tree = Self::new();
+		for leaf in leaves {
+			tree.push(leaf);
+		}
+		tree
+	}
+
+	/// Number of deposits in the tree.
+	pub fn len(&self) -> u64 {
+		self.nodes[0].len() as u64
+	}
+
+	/// Whether the tree is empty.
+	pub fn is_empty(&self) -> bool {
+		self.nodes[0].is_empty()
+	}
+
+	/// Leaf at the given deposit index.
+	pub fn leaf(&self, index: u64) -> Option<H256> {
+		self.nodes[0].get(index as usize).cloned()
+	}
+
+	/// Push a new deposit leaf into the tree.
+	pub fn push(&mut self, leaf: H256) {
+		self.nodes[0].push(leaf);
+
+		let mut index = self.nodes[0].len() - 1;
+		for height in 0..DEPTH {
+			if index % 2 == 0 {
+				break
+			}
+
+			let parent = C::hash(&[
+				self.nodes[height][index - 1].as_ref(),
+				self.nodes[height][index].as_ref(),
+			]);
+			self.nodes[height + 1].push(parent);
+			index /= 2;
+		}
+	}
+
+	/// Deposit root of the current tree, with length mixed in.
+	pub fn root(&self) -> H256 {
+		self.root_at(self.len()).expect("Current length is always in range; qed")
+	}
+
+	/// Deposit root when the tree contained the given number of deposits.
+	pub fn root_at(&self, count: u64) -> Result<H256, Error> {
+		if count > self.len() {
+			return Err(Error::IndexOutOfRange)
+		}
+
+		Ok(C::hash(&[
+			self.node(DEPTH, 0, count as usize).as_ref(),
+			to_bytes(count).as_ref(),
+		]))
+	}
+
+	/// Proof of the deposit at index, against the root when the tree
+	/// contained the given number of deposits. The proof contains the length
+	/// mix-in, and is suitable for `Deposit::proof`.
+	pub fn proof(&self, index: u64, count: u64) -> Result<Vec<H256>, Error> {
+		if index >= count || count > self.len() {
+			return Err(Error::IndexOutOfRange)
+		}
+
+		let mut proof = Vec::with_capacity(DEPTH + 1);
+		for height in 0..DEPTH {
+			let sibling = ((index as usize) >> height) ^ 1;
+			proof.push(self.node(height, sibling, count as usize));
+		}
+		proof.push(to_bytes(count));
+
+		Ok(proof)
+	}
+
+	/// Node at height and index, considering only the first `count` leaves.
+	fn node(&self, height: usize, index: usize, count: usize) -> H256 {
+		let start = index << height;
+		let end = (index + 1) << height;
+
+		if end <= count {
+			self.nodes[height][index]
+		} else if start >= count {
+			self.zero_hashes[height]
+		} else {
+			C::hash(&[
+				self.node(height - 1, index * 2, count).as_ref(),
+				self.node(height - 1, index * 2 + 1, count).as_ref(),
+			])
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MinimalConfig;
+	use crate::utils::is_valid_merkle_branch;
+
+	#[test]
+	fn historical_proofs_are_valid() {
+		type C = MinimalConfig;
+		let leaves = (0..13u64).map(|i| C::hash(&[&i.to_le_bytes()[..]])).collect::<Vec<_>>();
+		let tree = DepositTree::<C>::from_leaves(leaves.clone());
+
+		for count in 1..=leaves.len() as u64 {
+			let root = tree.root_at(count).unwrap();
+			assert_eq!(root, DepositTree::<C>::from_leaves(
+				leaves[..count as usize].iter().cloned()
+			).root());
+
+			for index in 0..count {
+				let proof = tree.proof(index, count).unwrap();
+				assert!(is_valid_merkle_branch::<C>(
+					leaves[index as usize],
+					&proof,
+					consts::DEPOSIT_CONTRACT_TREE_DEPTH + 1,
+					index,
+					root,
+				));
+			}
+		}
+
+		assert_eq!(tree.proof(13, 13), Err(Error::IndexOutOfRange));
+		assert_eq!(tree.root_at(14), Err(Error::IndexOutOfRange));
+	}
+
+	fn h256(value: &str) -> H256 {
+		let bytes = (0..32)
+			.map(|i| u8::from_str_radix(&value[(i * 2)..(i * 2 + 2)], 16).unwrap())
+			.collect::<Vec<_>>();
+		H256::from_slice(&bytes)
+	}
+
+	#[test]
+	fn empty_root_matches_deposit_contract() {
+		assert_eq!(
+			DepositTree::<MinimalConfig>::new().root(),
+			h256("d70a234731285c6804c2a4f56711ddb8c82c99740f207854891028af34e27e5e"),
+		);
+	}
+
+	#[test]
+	fn root_and_proof_match_spec() {
+		type C = MinimalConfig;
+		let leaves = (1..4u8).map(H256::repeat_byte).collect::<Vec<_>>();
+		let tree = DepositTree::<C>::from_leaves(leaves.clone());
+		let root = h256("75d097f35e94b5937339aca95d84f34faf62eb9bc4e76ecba0c7b35d23a71831");
+		assert_eq!(tree.root(), root);
+
+		let proof = tree.proof(1, 3).unwrap();
+		assert_eq!(proof.len(), DEPTH + 1);
+		assert_eq!(proof[0], leaves[0]);
+		assert_eq!(proof[1], h256("1cd4dbfe68da3cda08126527949d2c9fa7ccc5f3f31a36a7b880d6c83c5abe78"));
+		assert_eq!(proof[2], h256("db56114e00fdd4c1f85c892bf35ac9a89289aaecb1ebd0a96cde606a748b5d71"));
+		assert_eq!(proof[DEPTH], to_bytes(3));
+		assert!(is_valid_merkle_branch::<C>(
+			leaves[1],
+			&proof,
+			consts::DEPOSIT_CONTRACT_TREE_DEPTH + 1,
+			1,
+			root,
+		));
+	}
+}
@@ -20,6 +20,10 @@ mod serde;
 #[cfg(feature = "serde")]
 pub use self::serde::*;
 
+mod deposit_tree;
+
+pub use self::deposit_tree::DepositTree;
+
 use crate::{Config, Error};
 use crate::primitives::{H256, Uint, ValidatorIndex, Version};
 use core::cmp::max;
@@ -116,6 +120,73 @@ pub fn compute_committee<C: Config>(
 	}).collect::<Result<Vec<_>, Error>>()
 }
 
+/// Shuffle a whole list, so that the item at position `i` of the result is
+/// `input[shuffled_index(i)]`. This runs the swap-or-not rounds in reverse
+/// over all positions at once, hashing each source chunk only once per round.
+pub fn shuffle_list<C: Config>(
+	mut input: Vec<ValidatorIndex>,
+	seed: H256,
+) -> Result<Vec<ValidatorIndex>, Error> {
+	let count = input.len();
+	if count as u64 > 2u64.pow(40) {
+		return Err(Error::IndexOutOfRange)
+	}
+	if count <= 1 {
+		return Ok(input)
+	}
+
+	let source = |round: &[u8], position: usize| C::hash(&[
+		&seed[..],
+		round,
+		&((position >> 8) as u64).to_le_bytes()[..4],
+	]);
+
+	for round in (0..C::shuffle_round_count()).rev() {
+		let round = round.to_le_bytes();
+		let round = &round[..1];
+		let pivot = (to_uint(
+			&C::hash(&[&seed[..], round])[..8]
+		) % count as u64) as usize;
+
+		// Swap pairs mirrored around the pivot, in the lower part of the list.
+		let mirror = (pivot + 1) >> 1;
+		let mut hash = source(round, pivot);
+		let mut byte = hash[(pivot & 0xff) >> 3];
+		for i in 0..mirror {
+			let j = pivot - i;
+			if j & 0xff == 0xff {
+				hash = source(round, j);
+			}
+			if j & 0x07 == 0x07 {
+				byte = hash[(j & 0xff) >> 3];
+			}
+			if (byte >> (j & 0x07)) & 0x01 == 1 {
+				input.swap(i, j);
+			}
+		}
+
+		// Swap pairs mirrored around the pivot, in the upper part of the list.
+		let mirror = (pivot + count + 1) >> 1;
+		let end = count - 1;
+		let mut hash = source(round, end);
+		let mut byte = hash[(end & 0xff) >> 3];
+		for (offset, i) in ((pivot + 1)..mirror).enumerate() {
+			let j = end - offset;
+			if j & 0xff == 0xff {
+				hash = source(round, j);
+			}
+			if j & 0x07 == 0x07 {
+				byte = hash[(j & 0xff) >> 3];
+			}
+			if (byte >> (j & 0x07)) & 0x01 == 1 {
+				input.swap(i, j);
+			}
+		}
+	}
+
+	Ok(input)
+}
+
 /// Get epoch of slot.
 pub fn epoch_of_slot<C: Config>(slot: Uint) -> Uint {
 	slot / C::slots_per_epoch()
@@ -1,26 +1,243 @@
 use wasm_bindgen::prelude::*;
-use beacon::{BLSNoVerification, BLSConfig, Config, MinimalConfig, MainnetConfig, BeaconState};
-use beacon::types::BeaconBlock;
+use bm_le::tree_root;
+use beacon::{Config, MinimalConfig, MainnetConfig, BeaconState, BeaconExecutive, Error};
+use beacon::types::{
+	BeaconBlock, UnsealedBeaconBlock, BeaconBlockHeader, SigningBeaconBlockHeader,
+	Attestation, SigningAttestation, IndexedAttestation, SigningIndexedAttestation,
+	AttestationData, AttestationDataAndCustodyBit, Deposit, DepositData, SigningDepositData,
+	VoluntaryExit, SigningVoluntaryExit, ProposerSlashing, AttesterSlashing,
+};
 
+#[cfg(not(feature = "bls-verification"))]
+type BLS = beacon::BLSNoVerification;
+#[cfg(feature = "bls-verification")]
+type BLS = shasper_crypto::bls::BLSVerification;
+
+/// Whether real BLS verification is compiled in.
 #[wasm_bindgen]
-pub fn execute_minimal(block: &JsValue, state: &JsValue) -> Result<JsValue, JsValue> {
-	execute::<MinimalConfig, BLSNoVerification>(block, state)
+pub fn bls_verification() -> bool {
+	cfg!(feature = "bls-verification")
 }
 
-#[wasm_bindgen]
-pub fn execute_mainnet(block: &JsValue, state: &JsValue) -> Result<JsValue, JsValue> {
-	execute::<MainnetConfig, BLSNoVerification>(block, state)
+macro_rules! config_bindings {
+	( $config:ty, { $( $name:ident => $inner:ident ( $( $arg:ident: $argty:ty ),* ) -> $ret:ty; )* } ) => {
+		$(
+			#[wasm_bindgen]
+			pub fn $name( $( $arg: $argty ),* ) -> Result<$ret, JsValue> {
+				$inner::<$config>( $( $arg ),* )
+			}
+		)*
+	}
 }
 
-fn execute<C: Config, BLS: BLSConfig>(block: &JsValue, state: &JsValue) -> Result<JsValue, JsValue> where
+config_bindings!(MinimalConfig, {
+	execute_minimal => execute(block: &JsValue, state: &JsValue) -> JsValue;
+	encode_minimal => encode(typ: &str, value: &JsValue) -> Vec<u8>;
+	decode_minimal => decode(typ: &str, value: &[u8]) -> JsValue;
+	tree_root_minimal => root(typ: &str, value: &JsValue) -> Vec<u8>;
+	signing_root_minimal => signing_root(typ: &str, value: &JsValue) -> Vec<u8>;
+	committees_minimal => committees(state: &JsValue, slot: u64) -> JsValue;
+	proposer_index_minimal => proposer_index(state: &JsValue, slot: u64) -> u64;
+	process_attestation_minimal => process_attestation(state: &JsValue, attestation: &JsValue) -> JsValue;
+	process_deposit_minimal => process_deposit(state: &JsValue, deposit: &JsValue) -> JsValue;
+	process_voluntary_exit_minimal => process_voluntary_exit(state: &JsValue, exit: &JsValue) -> JsValue;
+});
+
+config_bindings!(MainnetConfig, {
+	execute_mainnet => execute(block: &JsValue, state: &JsValue) -> JsValue;
+	encode_mainnet => encode(typ: &str, value: &JsValue) -> Vec<u8>;
+	decode_mainnet => decode(typ: &str, value: &[u8]) -> JsValue;
+	tree_root_mainnet => root(typ: &str, value: &JsValue) -> Vec<u8>;
+	signing_root_mainnet => signing_root(typ: &str, value: &JsValue) -> Vec<u8>;
+	committees_mainnet => committees(state: &JsValue, slot: u64) -> JsValue;
+	proposer_index_mainnet => proposer_index(state: &JsValue, slot: u64) -> u64;
+	process_attestation_mainnet => process_attestation(state: &JsValue, attestation: &JsValue) -> JsValue;
+	process_deposit_mainnet => process_deposit(state: &JsValue, deposit: &JsValue) -> JsValue;
+	process_voluntary_exit_mainnet => process_voluntary_exit(state: &JsValue, exit: &JsValue) -> JsValue;
+});
+
+/// Dispatch a generic function on the container named by `typ`.
+macro_rules! with_type {
+	( $typ:expr, $config:ty, $f:ident ( $( $arg:expr ),* ) ) => {
+		match $typ {
+			"block" => $f::<$config, BeaconBlock<$config>>( $( $arg ),* ),
+			"block_header" => $f::<$config, BeaconBlockHeader>( $( $arg ),* ),
+			"state" => $f::<$config, BeaconState<$config>>( $( $arg ),* ),
+			"attestation" => $f::<$config, Attestation<$config>>( $( $arg ),* ),
+			"indexed_attestation" => $f::<$config, IndexedAttestation<$config>>( $( $arg ),* ),
+			"attestation_data" => $f::<$config, AttestationData>( $( $arg ),* ),
+			"deposit" => $f::<$config, Deposit>( $( $arg ),* ),
+			"deposit_data" => $f::<$config, DepositData>( $( $arg ),* ),
+			"voluntary_exit" => $f::<$config, VoluntaryExit>( $( $arg ),* ),
+			"proposer_slashing" => $f::<$config, ProposerSlashing>( $( $arg ),* ),
+			"attester_slashing" => $f::<$config, AttesterSlashing<$config>>( $( $arg ),* ),
+			typ => Err(JsValue::from(format!("Unknown type {}", typ))),
+		}
+	}
+}
+
+fn js_error<E: core::fmt::Debug>(e: E) -> JsValue {
+	JsValue::from(format!("{:?}", e))
+}
+
+fn from_js<T: serde::de::DeserializeOwned>(value: &JsValue) -> Result<T, JsValue> {
+	value.into_serde().map_err(js_error)
+}
+
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+	JsValue::from_serde(value).map_err(js_error)
+}
+
+fn execute<C: Config>(block: &JsValue, state: &JsValue) -> Result<JsValue, JsValue> where
 	C: serde::Serialize + serde::de::DeserializeOwned,
 {
-	let block: BeaconBlock<C> = block.into_serde().map_err(|e| JsValue::from(format!("{:?}", e)))?;
-	let mut state: BeaconState<C> = state.into_serde().map_err(|e| JsValue::from(format!("{:?}", e)))?;
+	let block: BeaconBlock<C> = from_js(block)?;
+	let mut state: BeaconState<C> = from_js(state)?;
 
 	beacon::execute_block::<C, BLS>(
-		&block, &mut state
-	).map_err(|e| JsValue::from(format!("{:?}", e)))?;
+		&block, &mut state, None
+	).map_err(js_error)?;
+
+	to_js(&state)
+}
+
+fn encode<C: Config>(typ: &str, value: &JsValue) -> Result<Vec<u8>, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn typed<C: Config, T: ssz::Encode + serde::de::DeserializeOwned>(
+		value: &JsValue
+	) -> Result<Vec<u8>, JsValue> {
+		Ok(ssz::Encode::encode(&from_js::<T>(value)?))
+	}
 
-	Ok(JsValue::from_serde(&state).map_err(|e| JsValue::from(format!("{:?}", e)))?)
+	with_type!(typ, C, typed(value))
+}
+
+fn decode<C: Config>(typ: &str, value: &[u8]) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn typed<C: Config, T: ssz::Decode + serde::Serialize>(
+		value: &[u8]
+	) -> Result<JsValue, JsValue> {
+		to_js(&<T as ssz::Decode>::decode(value).map_err(js_error)?)
+	}
+
+	with_type!(typ, C, typed(value))
+}
+
+fn root<C: Config>(typ: &str, value: &JsValue) -> Result<Vec<u8>, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	fn typed<C: Config, T: bm_le::IntoTree + serde::de::DeserializeOwned>(
+		value: &JsValue
+	) -> Result<Vec<u8>, JsValue> {
+		Ok(tree_root::<C::Digest, _>(&from_js::<T>(value)?)[..].to_vec())
+	}
+
+	with_type!(typ, C, typed(value))
+}
+
+fn signing_root<C: Config>(typ: &str, value: &JsValue) -> Result<Vec<u8>, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	let root = match typ {
+		"block" => tree_root::<C::Digest, _>(
+			&UnsealedBeaconBlock::from(&from_js::<BeaconBlock<C>>(value)?)
+		),
+		"block_header" => tree_root::<C::Digest, _>(
+			&SigningBeaconBlockHeader::from(from_js::<BeaconBlockHeader>(value)?)
+		),
+		"attestation" => tree_root::<C::Digest, _>(
+			&SigningAttestation::from(from_js::<Attestation<C>>(value)?)
+		),
+		"indexed_attestation" => tree_root::<C::Digest, _>(
+			&SigningIndexedAttestation::from(from_js::<IndexedAttestation<C>>(value)?)
+		),
+		"attestation_data_and_custody_bit" => tree_root::<C::Digest, _>(
+			&from_js::<AttestationDataAndCustodyBit>(value)?
+		),
+		"deposit_data" => tree_root::<C::Digest, _>(
+			&SigningDepositData::from(from_js::<DepositData>(value)?)
+		),
+		"voluntary_exit" => tree_root::<C::Digest, _>(
+			&SigningVoluntaryExit::from(from_js::<VoluntaryExit>(value)?)
+		),
+		typ => return Err(JsValue::from(format!("Unknown signing type {}", typ))),
+	};
+
+	Ok(root[..].to_vec())
+}
+
+/// Advance a state so that `slot` is in its current epoch.
+fn state_at_slot<C: Config>(state: &JsValue, slot: u64) -> Result<BeaconState<C>, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	let mut state: BeaconState<C> = from_js(state)?;
+	if slot > state.slot {
+		BeaconExecutive::new(&mut state).process_slots(slot).map_err(js_error)?;
+	}
+	Ok(state)
+}
+
+fn committees<C: Config>(state: &JsValue, slot: u64) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	let mut state = state_at_slot::<C>(state, slot)?;
+	let executive = BeaconExecutive::new(&mut state);
+
+	let committees = (0..executive.committee_count_at_slot(slot))
+		.map(|index| executive.beacon_committee(slot, index))
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(js_error)?;
+
+	to_js(&committees)
+}
+
+fn proposer_index<C: Config>(state: &JsValue, slot: u64) -> Result<u64, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	let mut state = state_at_slot::<C>(state, slot)?;
+	let executive = BeaconExecutive::new(&mut state);
+
+	executive.beacon_proposer_index_at_slot(slot).map_err(js_error)
+}
+
+fn process_operation<C: Config, O: serde::de::DeserializeOwned, F>(
+	state: &JsValue,
+	operation: &JsValue,
+	f: F,
+) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+	F: FnOnce(&mut BeaconExecutive<C>, O) -> Result<(), Error>,
+{
+	let mut state: BeaconState<C> = from_js(state)?;
+	let operation: O = from_js(operation)?;
+
+	f(&mut BeaconExecutive::new(&mut state), operation).map_err(js_error)?;
+
+	to_js(&state)
+}
+
+fn process_attestation<C: Config>(state: &JsValue, attestation: &JsValue) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	process_operation::<C, Attestation<C>, _>(
+		state, attestation, |executive, attestation| executive.process_attestation::<BLS>(attestation)
+	)
+}
+
+fn process_deposit<C: Config>(state: &JsValue, deposit: &JsValue) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	process_operation::<C, Deposit, _>(
+		state, deposit, |executive, deposit| executive.process_deposit::<BLS>(deposit)
+	)
+}
+
+fn process_voluntary_exit<C: Config>(state: &JsValue, exit: &JsValue) -> Result<JsValue, JsValue> where
+	C: serde::Serialize + serde::de::DeserializeOwned,
+{
+	process_operation::<C, VoluntaryExit, _>(
+		state, exit, |executive, exit| executive.process_voluntary_exit::<BLS>(exit)
+	)
 }
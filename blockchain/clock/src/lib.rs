@@ -0,0 +1,278 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Wall-clock slot timer driven by the beacon chain genesis time.
+
+use beacon::Config;
+use beacon::primitives::{Slot, Epoch};
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::marker::PhantomData;
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+	/// Current time, as duration since unix epoch.
+	fn now(&self) -> Duration;
+	/// Block the current thread until the clock reaches the given time.
+	fn sleep_until(&self, at: Duration);
+}
+
+/// Clock backed by the system time.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Duration {
+		SystemTime::now().duration_since(UNIX_EPOCH)
+			.expect("System time is always after unix epoch; qed")
+	}
+
+	fn sleep_until(&self, at: Duration) {
+		let now = self.now();
+		if at > now {
+			std::thread::sleep(at - now);
+		}
+	}
+}
+
+/// Clock that only moves when told to. Used for tests.
+#[derive(Clone, Default, Debug)]
+pub struct ManualClock {
+	inner: Arc<(Mutex<Duration>, Condvar)>,
+}
+
+impl ManualClock {
+	/// Create a new manual clock at the given time.
+	pub fn new(now: Duration) -> Self {
+		Self { inner: Arc::new((Mutex::new(now), Condvar::new())) }
+	}
+
+	/// Set the current time.
+	pub fn set(&self, now: Duration) {
+		let (ref time, ref cvar) = *self.inner;
+		*time.lock().expect("Lock is never poisoned; qed") = now;
+		cvar.notify_all();
+	}
+
+	/// Advance the current time.
+	pub fn advance(&self, by: Duration) {
+		let now = self.now();
+		self.set(now + by);
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Duration {
+		*self.inner.0.lock().expect("Lock is never poisoned; qed")
+	}
+
+	fn sleep_until(&self, at: Duration) {
+		let (ref time, ref cvar) = *self.inner;
+		let mut now = time.lock().expect("Lock is never poisoned; qed");
+		while *now < at {
+			now = cvar.wait(now).expect("Lock is never poisoned; qed");
+		}
+	}
+}
+
+/// Event emitted by the slot timer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tick {
+	/// Start of a new epoch. Emitted right before the slot tick of its first slot.
+	Epoch(Epoch),
+	/// Start of a slot. Blocks are proposed and the head is re-evaluated.
+	Slot(Slot),
+	/// One third into a slot. Attestations for the slot are produced.
+	Attestation(Slot),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+	Start,
+	Attestation,
+}
+
+/// Slot timer emitting slot, attestation and epoch ticks from genesis time.
+pub struct SlotTimer<C: Config, Cl: Clock> {
+	clock: Cl,
+	genesis_time: Duration,
+	next: Option<(Slot, Phase)>,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config, Cl: Clock> SlotTimer<C, Cl> {
+	/// Create a new slot timer.
+	pub fn new(clock: Cl, genesis_time: u64) -> Self {
+		Self {
+			clock,
+			genesis_time: Duration::from_secs(genesis_time),
+			next: None,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Underlying clock.
+	pub fn clock(&self) -> &Cl {
+		&self.clock
+	}
+
+	/// Duration of a single slot.
+	pub fn slot_duration(&self) -> Duration {
+		Duration::from_secs(C::seconds_per_slot())
+	}
+
+	/// Current slot, or `None` if genesis has not yet happened.
+	pub fn current_slot(&self) -> Option<Slot> {
+		let now = self.clock.now();
+		if now < self.genesis_time {
+			return None
+		}
+
+		let since_genesis = now - self.genesis_time;
+		Some(since_genesis.as_secs() / C::seconds_per_slot())
+	}
+
+	/// Current epoch, or `None` if genesis has not yet happened.
+	pub fn current_epoch(&self) -> Option<Epoch> {
+		self.current_slot().map(|slot| beacon::utils::epoch_of_slot::<C>(slot))
+	}
+
+	/// Start time of the given slot, or `None` if it does not fit in a `Duration`.
+	pub fn slot_start(&self, slot: Slot) -> Option<Duration> {
+		let since_genesis = slot.checked_mul(C::seconds_per_slot())?;
+		self.genesis_time.checked_add(Duration::from_secs(since_genesis))
+	}
+
+	fn time_of(&self, (slot, phase): (Slot, Phase)) -> Option<Duration> {
+		match phase {
+			Phase::Start => self.slot_start(slot),
+			Phase::Attestation => self.slot_start(slot)?.checked_add(self.slot_duration() / 3),
+		}
+	}
+
+	fn next_event(&self) -> (Slot, Phase) {
+		match self.next {
+			Some(next) => {
+				// Skip events of slots that have already passed, when we have been
+				// suspended for longer than a slot.
+				match self.current_slot() {
+					Some(current) if current > next.0 => (current, Phase::Start),
+					_ => next,
+				}
+			},
+			None => (self.current_slot().unwrap_or(0), Phase::Start),
+		}
+	}
+
+	/// Time of the next tick, or `None` if there is none representable.
+	pub fn next_tick_time(&self) -> Option<Duration> {
+		self.time_of(self.next_event())
+	}
+
+	/// Collect all ticks that are due, without blocking.
+	pub fn poll(&mut self) -> Vec<Tick> {
+		let now = self.clock.now();
+		let mut ticks = Vec::new();
+
+		loop {
+			let next = self.next_event();
+			match self.time_of(next) {
+				Some(time) if time <= now => (),
+				_ => {
+					self.next = Some(next);
+					break
+				},
+			}
+
+			let (slot, phase) = next;
+			self.next = Some(match phase {
+				Phase::Start => {
+					if slot % C::slots_per_epoch() == 0 {
+						ticks.push(Tick::Epoch(beacon::utils::epoch_of_slot::<C>(slot)));
+					}
+					ticks.push(Tick::Slot(slot));
+					(slot, Phase::Attestation)
+				},
+				Phase::Attestation => {
+					ticks.push(Tick::Attestation(slot));
+					(slot + 1, Phase::Start)
+				},
+			});
+		}
+
+		ticks
+	}
+
+	/// Block until at least one tick is due, and return all due ticks.
+	pub fn wait(&mut self) -> Vec<Tick> {
+		loop {
+			let ticks = self.poll();
+			if !ticks.is_empty() {
+				return ticks
+			}
+
+			match self.next_tick_time() {
+				Some(next) => self.clock.sleep_until(next),
+				None => return ticks,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::MinimalConfig;
+
+	#[test]
+	fn manual_clock_ticks() {
+		let clock = ManualClock::new(Duration::from_secs(90));
+		let mut timer = SlotTimer::<MinimalConfig, _>::new(clock.clone(), 100);
+
+		assert_eq!(timer.current_slot(), None);
+		assert_eq!(timer.poll(), Vec::new());
+		assert_eq!(timer.next_tick_time(), Some(Duration::from_secs(100)));
+
+		clock.set(Duration::from_secs(100));
+		assert_eq!(timer.poll(), vec![Tick::Epoch(0), Tick::Slot(0)]);
+
+		clock.advance(Duration::from_secs(2));
+		assert_eq!(timer.poll(), vec![Tick::Attestation(0)]);
+
+		clock.advance(Duration::from_secs(4));
+		assert_eq!(timer.current_slot(), Some(1));
+		assert_eq!(timer.wait(), vec![Tick::Slot(1)]);
+	}
+
+	#[test]
+	fn skips_missed_slots() {
+		let clock = ManualClock::new(Duration::from_secs(100));
+		let mut timer = SlotTimer::<MinimalConfig, _>::new(clock.clone(), 100);
+		assert_eq!(timer.poll(), vec![Tick::Epoch(0), Tick::Slot(0)]);
+
+		clock.set(Duration::from_secs(100 + 6 * 8));
+		assert_eq!(timer.poll(), vec![Tick::Epoch(1), Tick::Slot(8)]);
+	}
+
+	#[test]
+	fn slot_start_does_not_truncate() {
+		let timer = SlotTimer::<MinimalConfig, _>::new(ManualClock::default(), 100);
+		let slot = u32::max_value() as u64 + 1;
+
+		assert_eq!(timer.slot_start(slot), Some(Duration::from_secs(100 + slot * 6)));
+		assert_eq!(timer.slot_start(u64::max_value()), None);
+	}
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use core::hash::Hash;
 use core::mem;
 use blockchain::{Block, Auxiliary, BlockExecutor, AsExternalities};
@@ -172,13 +173,31 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error { }
 
+/// Callback invoked with the new head after fork choice has moved it.
+pub type HeadObserver<Id> = Arc<dyn Fn(&Id) + Send + Sync>;
+
 pub struct ArchiveGhostImporter<E: BlockExecutor, Ba: Store<Block=E::Block>> where
 	E: JustifiableExecutor,
 	Ba::Auxiliary: Auxiliary<E::Block>
 {
-	ghost: ArchiveGhost<Ba, E::ValidatorIndex>,
+	ghost: Arc<Mutex<ArchiveGhost<Ba, E::ValidatorIndex>>>,
 	import_lock: ImportLock,
 	executor: E,
+	head_observer: Option<HeadObserver<<E::Block as Block>::Identifier>>,
+}
+
+impl<E: BlockExecutor, Ba: Store<Block=E::Block>> Clone for ArchiveGhostImporter<E, Ba> where
+	E: JustifiableExecutor + Clone,
+	Ba::Auxiliary: Auxiliary<E::Block>
+{
+	fn clone(&self) -> Self {
+		Self {
+			ghost: self.ghost.clone(),
+			import_lock: self.import_lock.clone(),
+			executor: self.executor.clone(),
+			head_observer: self.head_observer.clone(),
+		}
+	}
 }
 
 impl<E: BlockExecutor, Ba: SharedCommittable + Store<Block=E::Block>> ArchiveGhostImporter<E, Ba> where
@@ -189,7 +208,63 @@ impl<E: BlockExecutor, Ba: SharedCommittable + Store<Block=E::Block>> ArchiveGho
 	pub fn new(executor: E, backend: Ba, import_lock: ImportLock) -> Self {
 		Self {
 			executor, import_lock,
-			ghost: ArchiveGhost::new(backend),
+			ghost: Arc::new(Mutex::new(ArchiveGhost::new(backend))),
+			head_observer: None,
+		}
+	}
+
+	/// Notify the given observer whenever a new head is set.
+	pub fn with_head_observer(
+		mut self,
+		observer: HeadObserver<<E::Block as Block>::Identifier>,
+	) -> Self {
+		self.head_observer = Some(observer);
+		self
+	}
+}
+
+impl<E: BlockExecutor, Ba: Store<Block=E::Block>> ArchiveGhostImporter<E, Ba> where
+	E: JustifiableExecutor,
+	Ba: ChainQuery + AncestorQuery,
+	Ba: SharedCommittable<Operation=Operation<E::Block, <Ba as Store>::State, <Ba as Store>::Auxiliary>>,
+	Ba::Auxiliary: Auxiliary<E::Block>,
+	Ba::State: AsExternalities<E::Externalities>,
+{
+	/// Re-run fork choice from the justified block of the current head, with
+	/// the votes seen so far.
+	pub fn update_head(&self) -> Result<(), Error> {
+		let ghost = self.ghost.lock().expect("Lock is never poisoned; qed");
+
+		let head = ghost.backend.head();
+		let mut state = ghost.backend.state_at(&head)
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+		let justified_block_id = match self.executor
+			.justified_block_id(state.as_externalities())
+			.map_err(|e| Error::Executor(Box::new(e)))?
+		{
+			Some(value) => value,
+			None => ghost.backend.genesis(),
+		};
+
+		let new_head = ghost.head(&justified_block_id)
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+		if new_head == head {
+			return Ok(())
+		}
+
+		let mut importer = ImportAction::new(
+			&ghost.backend, self.import_lock.lock()
+		);
+		importer.set_head(new_head.clone());
+		importer.commit().map_err(|e| Error::Backend(Box::new(e)))?;
+		self.notify_head(&new_head);
+
+		Ok(())
+	}
+
+	fn notify_head(&self, head: &<E::Block as Block>::Identifier) {
+		if let Some(observer) = self.head_observer.as_ref() {
+			observer(head);
 		}
 	}
 }
@@ -205,7 +280,7 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> BlockImporter for ArchiveGhost
 	type Error = Error;
 
 	fn import_block(&mut self, block: Ba::Block) -> Result<(), Self::Error> {
-		let mut state = self.ghost.backend
+		let mut state = self.ghost.lock().expect("Lock is never poisoned; qed").backend
 			.state_at(
 				&block.parent_id().ok_or(Error::IsGenesis)?
 			)
@@ -231,6 +306,7 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ArchiveGhostIm
 		&mut self,
 		mut raw: ImportOperation<Ba::Block, Ba::State>
 	) -> Result<(), Self::Error> {
+		let mut ghost = self.ghost.lock().expect("Lock is never poisoned; qed");
 		let (justified_active_validators, justified_block_id, votes) = {
 			let externalities = raw.state.as_externalities();
 			let justified_active_validators = self.executor
@@ -241,14 +317,14 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ArchiveGhostIm
 				.map_err(|e| Error::Executor(Box::new(e)))?
 			{
 				Some(value) => value,
-				None => self.ghost.backend.genesis(),
+				None => ghost.backend.genesis(),
 			};
 			let votes = self.executor
 				.votes(&raw. block, externalities)
 				.map_err(|e| Error::Executor(Box::new(e)))?;
 
 			let mut importer = ImportAction::new(
-				&self.ghost.backend, self.import_lock.lock()
+				&ghost.backend, self.import_lock.lock()
 			);
 			importer.import_raw(raw);
 			importer.commit().map_err(|e| Error::Backend(Box::new(e)))?;
@@ -257,25 +333,28 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ArchiveGhostIm
 		};
 
 		for (k, v) in votes {
-			self.ghost.update_overlay(k, v);
+			ghost.update_overlay(k, v);
 		}
-		self.ghost.update_active(&justified_active_validators);
-		let new_head = match self.ghost.head(&justified_block_id) {
+		ghost.update_active(&justified_active_validators);
+		let new_head = match ghost.head(&justified_block_id) {
 			Ok(value) => value,
 			Err(e) => {
-				self.ghost.reset_overlay();
+				ghost.reset_overlay();
 				return Err(Error::Backend(Box::new(e)))
 			},
 		};
 
 		let mut importer = ImportAction::new(
-			&self.ghost.backend, self.import_lock.lock()
+			&ghost.backend, self.import_lock.lock()
 		);
-		importer.set_head(new_head);
+		importer.set_head(new_head.clone());
 
 		match importer.commit() {
-			Ok(()) => { self.ghost.commit_overlay(); },
-			Err(_) => { self.ghost.reset_overlay(); },
+			Ok(()) => {
+				ghost.commit_overlay();
+				self.notify_head(&new_head);
+			},
+			Err(_) => { ghost.reset_overlay(); },
 		}
 
 		Ok(())
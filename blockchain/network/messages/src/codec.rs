@@ -35,6 +35,7 @@ impl<C: Config> Encoder for InboundCodec<C> {
 			RPCResponse::Hello(item) => item.encode(),
 			RPCResponse::BeaconBlocks(item) => item.encode(),
 			RPCResponse::RecentBeaconBlocks(item) => item.encode(),
+			RPCResponse::LightClientUpdate(item) => item.encode(),
 			RPCResponse::Unknown(_, value) => value,
 		};
 
@@ -58,6 +59,8 @@ impl<C: Config> Decoder for InboundCodec<C> {
 				RPCType::BeaconBlocks => RPCRequest::BeaconBlocks(Decode::decode(&bytes[..])?),
 				RPCType::RecentBeaconBlocks =>
 					RPCRequest::RecentBeaconBlocks(Decode::decode(&bytes[..])?),
+				RPCType::LightClientUpdate =>
+					RPCRequest::LightClientUpdate(Decode::decode(&bytes[..])?),
 			})),
 			None => Ok(None),
 		}
@@ -88,6 +91,7 @@ impl<C: Config> Encoder for OutboundCodec<C> {
 			(RPCType::Goodbye, RPCRequest::Goodbye(item)) => item.encode(),
 			(RPCType::BeaconBlocks, RPCRequest::BeaconBlocks(item)) => item.encode(),
 			(RPCType::RecentBeaconBlocks, RPCRequest::RecentBeaconBlocks(item)) => item.encode(),
+			(RPCType::LightClientUpdate, RPCRequest::LightClientUpdate(item)) => item.encode(),
 			_ => return Err(ssz::Error::Other("outbound codec invalid type")),
 		};
 
@@ -177,6 +181,19 @@ impl<C: Config> Decoder for OutboundCodec<C> {
 
 				RPCResponse::RecentBeaconBlocks(result)
 			},
+			RPCType::LightClientUpdate => {
+				let code = src.split_to(1)[0];
+				let bytes = match self.uvi.decode(src)? {
+					Some(bytes) => bytes,
+					None => return Ok(None),
+				};
+
+				if code == 0 {
+					RPCResponse::LightClientUpdate(Decode::decode(&bytes[..])?)
+				} else {
+					RPCResponse::Unknown(code, bytes.to_vec())
+				}
+			},
 		}))
 	}
 }
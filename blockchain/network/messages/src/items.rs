@@ -113,3 +113,11 @@ pub struct RecentBeaconBlocksRequest {
     /// The list of beacon block bodies being requested.
     pub block_roots: Vec<H256>,
 }
+
+/// Request the latest light client finality update from a peer.
+#[derive(Codec, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct LightClientUpdateRequest {
+    /// Finalized epoch already known to the requester. Only updates finalizing
+    /// a later epoch are returned.
+    pub finalized_epoch: Epoch,
+}
@@ -1,11 +1,15 @@
 mod items;
 mod codec;
 
-pub use items::{HelloMessage, GoodbyeReason, BeaconBlocksRequest, RecentBeaconBlocksRequest};
+pub use items::{
+	HelloMessage, GoodbyeReason, BeaconBlocksRequest, RecentBeaconBlocksRequest,
+	LightClientUpdateRequest,
+};
 pub use codec::{InboundCodec, OutboundCodec};
 
 use beacon::{
 	Config, types::{BeaconBlock, Attestation, VoluntaryExit, ProposerSlashing, AttesterSlashing},
+	light_client::LightClientUpdate,
 };
 use libp2p::gossipsub;
 
@@ -16,6 +20,7 @@ pub enum RPCType {
 	Goodbye = 1,
 	BeaconBlocks = 2,
 	RecentBeaconBlocks = 3,
+	LightClientUpdate = 4,
 }
 
 impl libp2p_rpc::RPCType for RPCType {
@@ -23,6 +28,7 @@ impl libp2p_rpc::RPCType for RPCType {
 		vec![
 			RPCType::Hello, RPCType::Goodbye,
 			RPCType::BeaconBlocks, RPCType::RecentBeaconBlocks,
+			RPCType::LightClientUpdate,
 		]
 	}
 }
@@ -34,6 +40,7 @@ impl libp2p::core::ProtocolName for RPCType {
 			RPCType::Goodbye => b"/eth2/beacon_chain/req/goodbye/1/ssz",
 			RPCType::BeaconBlocks => b"/eth2/beacon_chain/req/beacon_blocks_by_range/1/ssz",
 			RPCType::RecentBeaconBlocks => b"/eth2/beacon_chain/req/beacon_blocks_by_root/1/ssz",
+			RPCType::LightClientUpdate => b"/eth2/beacon_chain/req/light_client_update/1/ssz",
 		}
 	}
 }
@@ -45,6 +52,7 @@ pub enum RPCRequest {
 	Goodbye(GoodbyeReason),
 	BeaconBlocks(BeaconBlocksRequest),
 	RecentBeaconBlocks(RecentBeaconBlocksRequest),
+	LightClientUpdate(LightClientUpdateRequest),
 }
 
 impl libp2p_rpc::RPCRequest<RPCType> for RPCRequest {
@@ -68,6 +76,7 @@ impl libp2p_rpc::RPCRequest<RPCType> for RPCRequest {
 			Self::Goodbye(_) => RPCType::Goodbye,
 			Self::BeaconBlocks(_) => RPCType::BeaconBlocks,
 			Self::RecentBeaconBlocks(_) => RPCType::RecentBeaconBlocks,
+			Self::LightClientUpdate(_) => RPCType::LightClientUpdate,
 		}
 	}
 }
@@ -78,6 +87,7 @@ pub enum RPCResponse<C: Config> {
 	Hello(HelloMessage),
 	BeaconBlocks(Vec<BeaconBlock<C>>),
 	RecentBeaconBlocks(Vec<BeaconBlock<C>>),
+	LightClientUpdate(Option<LightClientUpdate<C>>),
 	Unknown(u8, Vec<u8>),
 }
 
@@ -114,6 +114,11 @@ impl<C: Config, TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess
 						return
 					},
 				};
+				if typ == PubsubType::Block {
+					self.events.push(Libp2pEvent::PubsubBlock(gs_msg.source, gs_msg.data));
+					return
+				}
+
 				let msg = match PubsubMessage::from_ssz_data(typ, &gs_msg.data) {
 					Ok(msg) => msg,
 					Err(_) => {
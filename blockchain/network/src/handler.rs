@@ -1,14 +1,32 @@
 use core::marker::PhantomData;
+use std::collections::{HashSet, HashMap};
 use blockchain::{Auxiliary, Block as BlockT, AsExternalities};
 use blockchain::backend::{Store, SharedCommittable, ChainQuery, ImportLock};
-use beacon::{Config, types::BeaconBlock, primitives::H256};
-use network_messages::{HelloMessage, BeaconBlocksRequest};
+use beacon::{Config, BeaconExecutive, utils, types::{BeaconBlock, BeaconBlockView}, primitives::H256};
+use beacon::light_client::{self, LightClientUpdate};
+use network_messages::{HelloMessage, BeaconBlocksRequest, LightClientUpdateRequest};
 use shasper_runtime::{StateExternalities, Block};
 use log::*;
 
-pub struct Handler<C, Ba> {
+/// Maximum number of gossiped blocks waiting for their parents.
+const MAX_PENDING_BLOCKS: usize = 64;
+
+/// Outcome of a gossiped block.
+pub enum GossipBlock<C: Config> {
+	/// Parent is known, and the block can be imported.
+	Ready(BeaconBlock<C>),
+	/// Parent is unknown and not yet requested. The block is queued.
+	UnknownParent(H256),
+	/// Parent is unknown but already requested. The block is queued.
+	Queued,
+	/// Parent is unknown, and the queue is full.
+	Dropped,
+}
+
+pub struct Handler<C: Config, Ba> {
 	import_lock: ImportLock,
 	backend: Ba,
+	pending: HashMap<H256, Vec<BeaconBlock<C>>>,
 	_marker: PhantomData<C>,
 }
 
@@ -21,6 +39,7 @@ impl<C, Ba> Handler<C, Ba> where
 	pub fn new(backend: Ba, import_lock: ImportLock) -> Self {
 		Self {
 			import_lock, backend,
+			pending: HashMap::new(),
 			_marker: PhantomData,
 		}
 	}
@@ -57,6 +76,54 @@ impl<C, Ba> Handler<C, Ba> where
 		}
 	}
 
+	/// Decode a gossiped block. The parent root is read through a view, and
+	/// blocks with unknown parents are only decoded while there is room to
+	/// queue them.
+	pub fn gossip_block(&mut self, data: &[u8]) -> Result<GossipBlock<C>, ssz::Error> {
+		let view = BeaconBlockView::<C>::new(data)?;
+		let parent_root = view.parent_root()?;
+		if self.backend.contains(&parent_root).unwrap_or(false) {
+			return view.decode().map(GossipBlock::Ready)
+		}
+
+		if self.pending.values().map(|blocks| blocks.len()).sum::<usize>() >= MAX_PENDING_BLOCKS {
+			return Ok(GossipBlock::Dropped)
+		}
+
+		let block = view.decode()?;
+		let requested = self.pending.contains_key(&parent_root);
+		self.pending.entry(parent_root).or_insert_with(Vec::new).push(block);
+
+		if requested {
+			Ok(GossipBlock::Queued)
+		} else {
+			Ok(GossipBlock::UnknownParent(parent_root))
+		}
+	}
+
+	/// Append queued gossiped blocks descending from the given blocks, so
+	/// that they are imported after their parents.
+	pub fn with_pending_children(&mut self, mut blocks: Vec<BeaconBlock<C>>) -> Vec<BeaconBlock<C>> {
+		let mut i = 0;
+		while i < blocks.len() {
+			let root = Block(blocks[i].clone()).id();
+			if let Some(children) = self.pending.remove(&root) {
+				blocks.extend(children);
+			}
+			i += 1;
+		}
+		blocks
+	}
+
+	/// Blocks of the given roots that are known to the backend.
+	pub fn blocks_by_root(&self, roots: Vec<H256>) -> Vec<BeaconBlock<C>> {
+		let _lock = self.import_lock.lock();
+		roots.into_iter()
+			.filter_map(|root| self.backend.block_at(&root).ok())
+			.map(Into::into)
+			.collect()
+	}
+
 	fn blocks_by_depth_no_lock(&self, start_depth: usize, count: usize) -> Vec<BeaconBlock<C>> {
 		let mut ret = Vec::new();
 		for d in start_depth..(start_depth + count) {
@@ -73,14 +140,14 @@ impl<C, Ba> Handler<C, Ba> where
 	}
 
 	pub fn blocks_by_depth(&self, start_depth: usize, count: usize) -> Vec<BeaconBlock<C>> {
-		let _ = self.import_lock.lock();
+		let _lock = self.import_lock.lock();
 		self.blocks_by_depth_no_lock(start_depth, count)
 	}
 
 	pub fn blocks_by_slot(
 		&self, mut start_hash: H256, start_slot: u64, count: usize
 	) -> Vec<BeaconBlock<C>> {
-		let _ = self.import_lock.lock();
+		let _lock = self.import_lock.lock();
 
 		if !self.backend.contains(&start_hash).unwrap() || start_hash == H256::default() {
 			self.blocks_by_depth_no_lock(1, count)
@@ -111,4 +178,94 @@ impl<C, Ba> Handler<C, Ba> where
 			self.blocks_by_depth_no_lock(start_depth, count)
 		}
 	}
+
+	pub fn light_client_update(
+		&self, request: LightClientUpdateRequest
+	) -> Option<LightClientUpdate<C>> {
+		let _lock = self.import_lock.lock();
+
+		// The attested block is the target of the previous epoch, so that
+		// attestations of all its committees can be included.
+		let head_hash = self.backend.head();
+		let mut head_state = self.backend.state_at(&head_hash).ok()?.state().clone();
+		let executive = BeaconExecutive::new(&mut head_state);
+		let epoch = executive.previous_epoch();
+		let attested_hash = executive.block_root(epoch).ok()?;
+		let attested_block = self.backend.block_at(&attested_hash).ok()?;
+		let attested_state = self.backend.state_at(&attested_hash).ok()?.state().clone();
+
+		let checkpoint = attested_state.finalized_checkpoint.clone();
+		if checkpoint.epoch <= request.finalized_epoch {
+			return None
+		}
+		let finalized_block = self.backend.block_at(&checkpoint.root).ok()?;
+
+		// Attestations of the epoch are included in blocks of the epoch and
+		// the next one, which are all descendants of the attested block.
+		let start_slot = utils::start_slot_of_epoch::<C>(epoch);
+		let mut candidates = Vec::new();
+		let mut current = head_hash;
+		loop {
+			let block = self.backend.block_at(&current).ok()?;
+			if block.0.slot < start_slot {
+				break
+			}
+
+			for attestation in block.0.body.attestations.iter() {
+				if attestation.data.target.epoch != epoch ||
+					attestation.data.target.root != attested_hash
+				{
+					continue
+				}
+
+				if let Ok(indexed) = executive.indexed_attestation(attestation.clone()) {
+					candidates.push(indexed);
+				}
+			}
+
+			current = match block.parent_id() {
+				Some(parent) => parent,
+				None => break,
+			};
+		}
+
+		// Greedily keep the attestations covering the most new attesters.
+		let mut attestations = Vec::new();
+		let mut attesters = HashSet::new();
+		while (attestations.len() as u64) < C::max_attestations_per_epoch() {
+			let best = candidates.iter()
+				.enumerate()
+				.map(|(i, indexed)| {
+					(i, indexed.custody_bit_0_indices.iter()
+						.chain(indexed.custody_bit_1_indices.iter())
+						.filter(|index| !attesters.contains(*index))
+						.count())
+				})
+				.max_by_key(|(_, gain)| *gain);
+
+			match best {
+				Some((i, gain)) if gain > 0 => {
+					let indexed = candidates.swap_remove(i);
+					attesters.extend(indexed.custody_bit_0_indices.iter()
+						.chain(indexed.custody_bit_1_indices.iter())
+						.cloned());
+					attestations.push(indexed);
+				},
+				_ => break,
+			}
+		}
+
+		match light_client::build_update(
+			&attested_state,
+			light_client::block_header(&attested_block.0),
+			light_client::block_header(&finalized_block.0),
+			attestations,
+		) {
+			Ok(update) => Some(update),
+			Err(e) => {
+				warn!("Building light client update failed: {}", e);
+				None
+			},
+		}
+	}
 }
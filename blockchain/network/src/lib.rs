@@ -33,10 +33,11 @@ pub use libp2p::{
 };
 pub use error::Error;
 pub use service::Service;
-pub use handler::Handler;
+pub use handler::{Handler, GossipBlock};
 
 use log::*;
 use core::time::Duration;
+use std::sync::Arc;
 use libp2p::identity;
 use futures01::{Async, stream::Stream};
 use futures::{Poll, StreamExt as _};
@@ -45,8 +46,9 @@ use blockchain::backend::{Store, SharedCommittable, ChainQuery, ImportLock};
 use blockchain::import::BlockImporter;
 use blockchain_network::sync::{NetworkSync, SyncConfig, SyncEvent};
 use beacon::Config;
+use beacon::types::Attestation;
 use shasper_runtime::{Block, StateExternalities};
-use network_messages::{HelloMessage, PubsubMessage};
+use network_messages::{HelloMessage, PubsubMessage, RecentBeaconBlocksRequest};
 use crate::rpc::{RPCEvent, RPCRequest, RPCResponse};
 
 pub const VERSION: &str = "v0.1";
@@ -62,12 +64,18 @@ pub enum Libp2pEvent<C: Config> {
     PeerDisconnected(PeerId),
     /// Received pubsub message.
     Pubsub(PeerId, PubsubMessage<C>),
+    /// Received pubsub block, still encoded.
+    PubsubBlock(PeerId, Vec<u8>),
 }
 
+/// Observer of attestations received over gossip.
+pub type AttestationObserver<C> = Arc<dyn Fn(Attestation<C>) + Send + Sync>;
+
 pub fn start_network_simple_sync<C, Ba, I>(
 	backend: Ba,
 	import_lock: ImportLock,
 	importer: I,
+	attestation_observer: AttestationObserver<C>,
 	config: NetworkConfig,
 ) -> Result<(), Error> where
 	C: Config,
@@ -88,7 +96,7 @@ pub fn start_network_simple_sync<C, Ba, I>(
 		request_timeout: 4,
 	};
 
-	let handler = Handler::<C, Ba>::new(backend, import_lock);
+	let mut handler = Handler::<C, Ba>::new(backend, import_lock);
 	let head_status = handler.status();
 	let mut sync = NetworkSync::<PeerId, HelloMessage, I>::new(
 		head_status,
@@ -114,9 +122,42 @@ pub fn start_network_simple_sync<C, Ba, I>(
 							trace!("Peer noted to disconnect: {:?}", peer);
 							sync.note_disconnected(peer);
 						},
+						Libp2pEvent::Pubsub(_, PubsubMessage::Attestation(attestation)) => {
+							attestation_observer(attestation);
+						},
 						Libp2pEvent::Pubsub(peer, message) => {
 							warn!("Unhandled pubsub message {:?}, {:?}", peer, message);
 						},
+						// Gossiped blocks are observed by the slasher and
+						// prune the pools once imported. Blocks with unknown
+						// parents are queued until the parent is received.
+						Libp2pEvent::PubsubBlock(peer, data) => {
+							match handler.gossip_block(&data) {
+								Ok(GossipBlock::Ready(block)) => {
+									let blocks = handler.with_pending_children(vec![block]);
+									sync.note_blocks(
+										blocks.into_iter().map(Into::into).collect(),
+										Some(peer)
+									);
+								},
+								Ok(GossipBlock::UnknownParent(parent_root)) => {
+									trace!("Requesting unknown parent {:?} from {:?}", parent_root, peer);
+									service.swarm.send_rpc(peer, RPCEvent::Request(
+										0,
+										RPCRequest::RecentBeaconBlocks(RecentBeaconBlocksRequest {
+											block_roots: vec![parent_root],
+										})
+									));
+								},
+								Ok(GossipBlock::Queued) => {
+									trace!("Queued gossiped block with requested parent from {:?}", peer);
+								},
+								Ok(GossipBlock::Dropped) => {
+									warn!("Dropped gossiped block with unknown parent from {:?}", peer);
+								},
+								Err(e) => warn!("Uninterpretable gossiped block from {:?}: {:?}", peer, e),
+							}
+						},
 						Libp2pEvent::RPC(peer, event) => {
 							trace!("Received RPC event {:?}, {:?}", peer, event);
 							match event {
@@ -131,6 +172,20 @@ pub fn start_network_simple_sync<C, Ba, I>(
 										)
 									));
 								},
+								RPCEvent::Request(request_id, RPCRequest::RecentBeaconBlocks(request)) => {
+									service.swarm.send_rpc(peer, RPCEvent::Response(
+										request_id, RPCResponse::RecentBeaconBlocks(
+											handler.blocks_by_root(request.block_roots)
+										)
+									));
+								},
+								RPCEvent::Request(request_id, RPCRequest::LightClientUpdate(request)) => {
+									service.swarm.send_rpc(peer, RPCEvent::Response(
+										request_id, RPCResponse::LightClientUpdate(
+											handler.light_client_update(request)
+										)
+									));
+								},
 								RPCEvent::Request(request_id, RPCRequest::Hello(hello)) => {
 									service.swarm.send_rpc(peer.clone(), RPCEvent::Response(
 										request_id, RPCResponse::Hello(
@@ -148,6 +203,13 @@ pub fn start_network_simple_sync<C, Ba, I>(
 										Some(peer)
 									);
 								},
+								RPCEvent::Response(_, RPCResponse::RecentBeaconBlocks(blocks)) => {
+									let blocks = handler.with_pending_children(blocks);
+									sync.note_blocks(
+										blocks.into_iter().map(Into::into).collect(),
+										Some(peer)
+									);
+								},
 								event => {
 									warn!("Unhandled RPC message {:?}, {:?}", peer, event);
 								},
@@ -0,0 +1,574 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validator API served by the node.
+
+use beacon::{BeaconExecutive, BeaconCache, Config, BLSConfig, Inherent, Transaction};
+use beacon::primitives::{H256, Signature, ValidatorId, Epoch, Slot};
+use beacon::types::{
+	AttestationData, Attestation, BeaconBlock, UnsealedBeaconBlock, Checkpoint, ProposerSlashing,
+	AttesterSlashing, VoluntaryExit, Deposit,
+};
+use blockchain::{AsExternalities, Auxiliary, Block as BlockT};
+use blockchain::backend::{ChainQuery, Store};
+use blockchain::import::SharedBlockImporter;
+use shasper_validator::api::{self, BeaconNode, NodeInfo, ValidatorDuty};
+use bm_le::tree_root;
+use jsonrpc_core::{IoHandler, Params, Value};
+use jsonrpc_http_server::{Server, ServerBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use log::{info, warn};
+use crate::{Block, Executor, Error, StateExternalities, AttestationPool, OperationPool};
+use crate::eth1::Eth1Follower;
+use crate::slasher::SlasherService;
+
+/// Validator API implementation on top of the node backend.
+pub struct NodeApi<B, I, C: Config, BLS: BLSConfig> {
+	backend: B,
+	importer: I,
+	executor: Executor<C, BLS>,
+	attestations: Arc<Mutex<AttestationPool<C, BLS>>>,
+	operations: Arc<Mutex<OperationPool<C, BLS>>>,
+	eth1: Option<Eth1Follower<C>>,
+	slasher: Option<SlasherService<C, BLS>>,
+}
+
+impl<B: Clone, I: Clone, C: Config, BLS: BLSConfig> Clone for NodeApi<B, I, C, BLS> {
+	fn clone(&self) -> Self {
+		Self {
+			backend: self.backend.clone(),
+			importer: self.importer.clone(),
+			executor: self.executor.clone(),
+			attestations: self.attestations.clone(),
+			operations: self.operations.clone(),
+			eth1: self.eth1.clone(),
+			slasher: self.slasher.clone(),
+		}
+	}
+}
+
+/// Prune the attestation and operation pools after the head has moved to the
+/// given block.
+pub fn prune_pools<B, C: Config, BLS: BLSConfig>(
+	backend: &B,
+	attestations: &Mutex<AttestationPool<C, BLS>>,
+	operations: &Mutex<OperationPool<C, BLS>>,
+	head: &H256,
+) -> Result<(), B::Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities,
+{
+	let head_block = backend.block_at(head)?;
+	let head_state = backend.state_at(head)?;
+	{
+		let mut attestations = attestations.lock().expect("Lock is never poisoned; qed");
+		attestations.prune_included(&head_block.0.body.attestations);
+		attestations.prune_expired(head_block.0.slot);
+		attestations.prune_invalid(head_state.state());
+		info!("Attestation pool has {} aggregates, {:?}", attestations.len(), attestations.metrics());
+	}
+
+	let mut operations = operations.lock().expect("Lock is never poisoned; qed");
+	operations.prune_included(&head_block.0);
+
+	let finalized_root = head_state.state().finalized_checkpoint.root;
+	if let Ok(finalized_state) = backend.state_at(&finalized_root) {
+		operations.prune(finalized_state.state());
+	}
+
+	Ok(())
+}
+
+fn node_error<E: std::fmt::Debug>(err: E) -> api::Error {
+	api::Error::Node(format!("{:?}", err))
+}
+
+impl<B, I, C: Config, BLS: BLSConfig> NodeApi<B, I, C, BLS> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
+	B::Auxiliary: Auxiliary<Block<C>>,
+	I: SharedBlockImporter<Block=Block<C>>,
+{
+	/// Create a new node API.
+	pub fn new(
+		backend: B,
+		importer: I,
+		attestations: Arc<Mutex<AttestationPool<C, BLS>>>,
+		operations: Arc<Mutex<OperationPool<C, BLS>>>,
+	) -> Self {
+		Self { backend, importer, attestations, operations, executor: Executor::new(), eth1: None, slasher: None }
+	}
+
+	/// Share the given beacon cache with the block importer.
+	pub fn with_cache(mut self, cache: Arc<Mutex<BeaconCache>>) -> Self {
+		self.executor = self.executor.with_cache(cache);
+		self
+	}
+
+	/// Use the given eth1 follower for eth1 data votes and deposits, instead
+	/// of the deposits submitted to the operation pool.
+	pub fn with_eth1(mut self, eth1: Eth1Follower<C>) -> Self {
+		self.eth1 = Some(eth1);
+		self
+	}
+
+	/// Feed submitted attestations to the given slasher.
+	pub fn with_slasher(mut self, slasher: SlasherService<C, BLS>) -> Self {
+		self.slasher = Some(slasher);
+		self
+	}
+
+	/// Head block and its state advanced to the given slot.
+	fn head_state_at(&self, slot: Slot) -> Result<(Block<C>, B::State), api::Error> {
+		let head = self.backend.head();
+		let head_block = self.backend.block_at(&head).map_err(node_error)?;
+		let mut state = self.backend.state_at(&head).map_err(node_error)?;
+
+		if head_block.0.slot > slot {
+			return Err(api::Error::Node(format!(
+				"Head slot {} is past requested slot {}", head_block.0.slot, slot
+			)))
+		}
+
+		self.executor.initialize_block(state.as_externalities(), slot)
+			.map_err(node_error)?;
+
+		Ok((head_block, state))
+	}
+
+	/// Current head state.
+	fn head_state(&self) -> Result<B::State, api::Error> {
+		self.backend.state_at(&self.backend.head()).map_err(node_error)
+	}
+
+	/// Apply an optional operation to the block, skipping it if it has
+	/// become invalid.
+	fn apply_operation(
+		&self,
+		block: &mut UnsealedBeaconBlock<C>,
+		state: &mut B::State,
+		transaction: Transaction<C>,
+	) {
+		if let Err(err) = self.executor.apply_extrinsic(block, state.as_externalities(), transaction) {
+			warn!("Error when applying an operation: {}", err);
+		}
+	}
+}
+
+impl<B, I, C: Config, BLS: BLSConfig> BeaconNode<C> for NodeApi<B, I, C, BLS> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
+	B::Auxiliary: Auxiliary<Block<C>>,
+	I: SharedBlockImporter<Block=Block<C>>,
+{
+	fn node_info(&self) -> Result<NodeInfo, api::Error> {
+		let genesis = self.backend.genesis();
+		let genesis_state = self.backend.state_at(&genesis).map_err(node_error)?;
+		let head_state = self.backend.state_at(&self.backend.head()).map_err(node_error)?;
+
+		Ok(NodeInfo {
+			genesis_time: genesis_state.state().genesis_time,
+			genesis_root: genesis,
+			genesis_validators_root: tree_root::<C::Digest, _>(&genesis_state.state().validators),
+			fork: head_state.state().fork.clone(),
+		})
+	}
+
+	fn duties(&self, epoch: Epoch, pubkeys: &[ValidatorId]) -> Result<Vec<ValidatorDuty>, api::Error> {
+		let start_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+		let head_slot = self.backend.block_at(&self.backend.head()).map_err(node_error)?.0.slot;
+		let (_, mut state) = self.head_state_at(
+			core::cmp::max(start_slot, head_slot)
+		)?;
+		let mut cache = self.executor.cache().lock().expect("Lock is never poisoned; qed");
+		let executive = BeaconExecutive::with_cache(
+			state.as_externalities().state_mut(),
+			Some(&mut *cache),
+		);
+		if executive.current_epoch() != epoch {
+			return Err(api::Error::Node(format!("Epoch {} is not available", epoch)))
+		}
+
+		let mut proposals = HashMap::<u64, Vec<Slot>>::new();
+		for slot in start_slot..(start_slot + C::slots_per_epoch()) {
+			let proposer = executive.beacon_proposer_index_at_slot(slot).map_err(node_error)?;
+			proposals.entry(proposer).or_default().push(slot);
+		}
+
+		let mut duties = Vec::new();
+		for pubkey in pubkeys {
+			let validator_index = executive.validator_index(pubkey);
+			let mut duty = ValidatorDuty {
+				pubkey: pubkey.clone(),
+				validator_index,
+				attestation_slot: None,
+				attestation_committee_index: None,
+				attestation_committee_position: None,
+				attestation_committee_length: None,
+				block_proposal_slots: Vec::new(),
+			};
+
+			if let Some(validator_index) = validator_index {
+				if let Some(assignment) = executive.committee_assignment(epoch, validator_index)
+					.map_err(node_error)?
+				{
+					duty.attestation_slot = Some(assignment.slot);
+					duty.attestation_committee_index = Some(assignment.index);
+					duty.attestation_committee_position = assignment.validators.iter()
+						.position(|v| *v == validator_index)
+						.map(|v| v as u64);
+					duty.attestation_committee_length = Some(assignment.validators.len() as u64);
+				}
+				duty.block_proposal_slots = proposals.get(&validator_index)
+					.cloned().unwrap_or_default();
+			}
+
+			duties.push(duty);
+		}
+
+		Ok(duties)
+	}
+
+	fn produce_block(&self, slot: Slot, randao_reveal: Signature) -> Result<BeaconBlock<C>, api::Error> {
+		let (head_block, mut state) = self.head_state_at(slot)?;
+		if head_block.0.slot == slot {
+			return Err(api::Error::Node(format!("Block at slot {} already exists", slot)))
+		}
+		info!("Building on top of {} at slot {}", head_block.id(), slot);
+
+		let eth1_data = match self.eth1.as_ref() {
+			Some(eth1) => eth1.eth1_vote(state.state()),
+			None => state.state().eth1_data.clone(),
+		};
+		let mut unsealed_block = self.executor.apply_inherent(
+			&head_block, state.as_externalities(),
+			Inherent { randao_reveal, eth1_data },
+		).map_err(node_error)?;
+
+		let operations = self.operations.lock().expect("Lock is never poisoned; qed");
+		for slashing in operations.proposer_slashings(state.state()) {
+			self.apply_operation(&mut unsealed_block, &mut state, Transaction::ProposerSlashing(slashing));
+		}
+		for slashing in operations.attester_slashings(state.state()) {
+			self.apply_operation(&mut unsealed_block, &mut state, Transaction::AttesterSlashing(slashing));
+		}
+
+		let attestations = self.attestations.lock().expect("Lock is never poisoned; qed")
+			.select(state.state(), C::max_attestations() as usize);
+		let mut collected_attestations = 0;
+		for attestation in attestations {
+			match self.executor.apply_extrinsic(
+				&mut unsealed_block, state.as_externalities(),
+				Transaction::Attestation(attestation)
+			) {
+				Ok(()) => {
+					collected_attestations += 1;
+				},
+				Err(err) => {
+					warn!("Error when submitting an attestation: {}", err);
+				},
+			}
+		}
+		info!("Pushed {} attestations", collected_attestations);
+
+		// Blocks must include all pending deposits up to the limit, so a
+		// missing or invalid deposit fails block production.
+		let required_deposits = core::cmp::min(
+			C::max_deposits(),
+			state.state().eth1_data.deposit_count.saturating_sub(state.state().eth1_deposit_index),
+		);
+		let deposits = match self.eth1.as_ref() {
+			Some(eth1) => eth1.deposits(state.state()).map_err(node_error)?,
+			None => operations.deposits(state.state()),
+		};
+		if deposits.len() as u64 != required_deposits {
+			return Err(api::Error::Node(format!(
+				"Only {} of {} required deposits are available", deposits.len(), required_deposits
+			)))
+		}
+		for deposit in deposits {
+			self.executor.apply_extrinsic(
+				&mut unsealed_block, state.as_externalities(),
+				Transaction::Deposit(deposit)
+			).map_err(node_error)?;
+		}
+		info!("Pushed {} deposits", required_deposits);
+
+		for exit in operations.voluntary_exits(state.state()) {
+			self.apply_operation(&mut unsealed_block, &mut state, Transaction::VoluntaryExit(exit));
+		}
+
+		self.executor.finalize_block(
+			&mut unsealed_block, state.as_externalities()
+		).map_err(node_error)?;
+
+		Ok(unsealed_block.fake_seal())
+	}
+
+	fn submit_block(&self, block: BeaconBlock<C>) -> Result<(), api::Error> {
+		self.importer.import_block(Block(block)).map_err(node_error)
+	}
+
+	fn produce_attestation_data(&self, slot: Slot, index: u64) -> Result<AttestationData, api::Error> {
+		let (head_block, mut state) = self.head_state_at(slot)?;
+		let head = head_block.id();
+		let mut cache = self.executor.cache().lock().expect("Lock is never poisoned; qed");
+		let executive = BeaconExecutive::with_cache(
+			state.as_externalities().state_mut(),
+			Some(&mut *cache),
+		);
+		if index >= executive.committee_count_at_slot(slot) {
+			return Err(api::Error::Node(format!("Invalid committee index {}", index)))
+		}
+
+		let target_epoch = executive.current_epoch();
+		let target_slot = beacon::utils::start_slot_of_epoch::<C>(target_epoch);
+		let target_root = if target_slot == slot {
+			head
+		} else {
+			executive.block_root(target_epoch).map_err(node_error)?
+		};
+
+		Ok(AttestationData {
+			beacon_block_root: head,
+			source: executive.current_justified_checkpoint.clone(),
+			target: Checkpoint {
+				epoch: target_epoch,
+				root: target_root,
+			},
+			slot,
+			index,
+		})
+	}
+
+	fn submit_attestation(&self, attestation: Attestation<C>) -> Result<(), api::Error> {
+		let mut state = self.head_state()?;
+		self.attestations.lock().expect("Lock is never poisoned; qed")
+			.push(attestation.clone(), state.state_mut())
+			.map_err(node_error)?;
+
+		if let Some(slasher) = self.slasher.as_ref() {
+			slasher.observe_attestation(&attestation, state.state_mut());
+		}
+
+		Ok(())
+	}
+
+	fn submit_proposer_slashing(&self, slashing: ProposerSlashing) -> Result<(), api::Error> {
+		let state = self.head_state()?;
+		self.operations.lock().expect("Lock is never poisoned; qed")
+			.insert_proposer_slashing(slashing, state.state())
+			.map_err(node_error)
+	}
+
+	fn submit_attester_slashing(&self, slashing: AttesterSlashing<C>) -> Result<(), api::Error> {
+		let state = self.head_state()?;
+		self.operations.lock().expect("Lock is never poisoned; qed")
+			.insert_attester_slashing(slashing, state.state())
+			.map_err(node_error)
+	}
+
+	fn submit_voluntary_exit(&self, exit: VoluntaryExit) -> Result<(), api::Error> {
+		let state = self.head_state()?;
+		self.operations.lock().expect("Lock is never poisoned; qed")
+			.insert_voluntary_exit(exit, state.state())
+			.map_err(node_error)
+	}
+
+	fn submit_deposit(&self, index: u64, deposit: Deposit) -> Result<(), api::Error> {
+		let state = self.head_state()?;
+		self.operations.lock().expect("Lock is never poisoned; qed")
+			.insert_deposit(index, deposit, state.state())
+			.map_err(node_error)
+	}
+}
+
+fn to_rpc<T: Serialize>(result: Result<T, api::Error>) -> jsonrpc_core::Result<Value> {
+	match result {
+		Ok(value) => Ok(serde_json::to_value(value)
+			.expect("API types are always serializable; qed")),
+		Err(err) => Err(jsonrpc_core::Error {
+			code: jsonrpc_core::ErrorCode::ServerError(1),
+			message: format!("{}", err),
+			data: None,
+		}),
+	}
+}
+
+/// Start serving the validator API over HTTP.
+pub fn start_server<C, A>(api: A, addr: &SocketAddr) -> Result<Server, std::io::Error> where
+	C: Config,
+	A: BeaconNode<C> + Clone + Send + Sync + 'static,
+{
+	let mut io = IoHandler::new();
+
+	let node = api.clone();
+	io.add_method(api::METHOD_NODE_INFO, move |_: Params| {
+		to_rpc(node.node_info())
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_DUTIES, move |params: Params| {
+		let (epoch, pubkeys): (Epoch, Vec<ValidatorId>) = params.parse()?;
+		to_rpc(node.duties(epoch, &pubkeys))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_PRODUCE_BLOCK, move |params: Params| {
+		let (slot, randao_reveal): (Slot, Signature) = params.parse()?;
+		to_rpc(node.produce_block(slot, randao_reveal))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_SUBMIT_BLOCK, move |params: Params| {
+		let (block,): (BeaconBlock<C>,) = params.parse()?;
+		to_rpc(node.submit_block(block))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_PRODUCE_ATTESTATION_DATA, move |params: Params| {
+		let (slot, index): (Slot, u64) = params.parse()?;
+		to_rpc(node.produce_attestation_data(slot, index))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_SUBMIT_ATTESTATION, move |params: Params| {
+		let (attestation,): (Attestation<C>,) = params.parse()?;
+		to_rpc(node.submit_attestation(attestation))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_SUBMIT_PROPOSER_SLASHING, move |params: Params| {
+		let (slashing,): (ProposerSlashing,) = params.parse()?;
+		to_rpc(node.submit_proposer_slashing(slashing))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_SUBMIT_ATTESTER_SLASHING, move |params: Params| {
+		let (slashing,): (AttesterSlashing<C>,) = params.parse()?;
+		to_rpc(node.submit_attester_slashing(slashing))
+	});
+	let node = api.clone();
+	io.add_method(api::METHOD_SUBMIT_VOLUNTARY_EXIT, move |params: Params| {
+		let (exit,): (VoluntaryExit,) = params.parse()?;
+		to_rpc(node.submit_voluntary_exit(exit))
+	});
+	let node = api;
+	io.add_method(api::METHOD_SUBMIT_DEPOSIT, move |params: Params| {
+		let (index, deposit): (u64, Deposit) = params.parse()?;
+		to_rpc(node.submit_deposit(index, deposit))
+	});
+
+	info!("Validator API listening on {}", addr);
+	ServerBuilder::new(io).start_http(addr)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::MinimalConfig;
+	use beacon::types::Fork;
+	use shasper_validator::rpc::HttpClient;
+
+	type C = MinimalConfig;
+
+	#[derive(Clone, Default)]
+	struct MockNode {
+		submitted: Arc<Mutex<Vec<&'static str>>>,
+	}
+
+	impl MockNode {
+		fn submitted(&self, method: &'static str) -> Result<(), api::Error> {
+			self.submitted.lock().expect("Lock is never poisoned; qed").push(method);
+			Ok(())
+		}
+	}
+
+	impl BeaconNode<C> for MockNode {
+		fn node_info(&self) -> Result<NodeInfo, api::Error> {
+			Ok(NodeInfo {
+				genesis_time: 42,
+				genesis_root: Default::default(),
+				genesis_validators_root: Default::default(),
+				fork: Fork::default(),
+			})
+		}
+
+		fn duties(&self, _epoch: Epoch, _pubkeys: &[ValidatorId]) -> Result<Vec<ValidatorDuty>, api::Error> {
+			Err(api::Error::Node("No duties".to_string()))
+		}
+
+		fn produce_block(&self, slot: Slot, _randao_reveal: Signature) -> Result<BeaconBlock<C>, api::Error> {
+			Ok(BeaconBlock { slot, ..Default::default() })
+		}
+
+		fn submit_block(&self, _block: BeaconBlock<C>) -> Result<(), api::Error> {
+			self.submitted("block")
+		}
+
+		fn produce_attestation_data(&self, slot: Slot, index: u64) -> Result<AttestationData, api::Error> {
+			Ok(AttestationData { slot, index, ..Default::default() })
+		}
+
+		fn submit_attestation(&self, _attestation: Attestation<C>) -> Result<(), api::Error> {
+			self.submitted("attestation")
+		}
+
+		fn submit_proposer_slashing(&self, _slashing: ProposerSlashing) -> Result<(), api::Error> {
+			self.submitted("proposer_slashing")
+		}
+
+		fn submit_attester_slashing(&self, _slashing: AttesterSlashing<C>) -> Result<(), api::Error> {
+			self.submitted("attester_slashing")
+		}
+
+		fn submit_voluntary_exit(&self, _exit: VoluntaryExit) -> Result<(), api::Error> {
+			self.submitted("voluntary_exit")
+		}
+
+		fn submit_deposit(&self, _index: u64, _deposit: Deposit) -> Result<(), api::Error> {
+			self.submitted("deposit")
+		}
+	}
+
+	#[test]
+	fn http_client_round_trip() {
+		let node = MockNode::default();
+		let server = start_server::<C, _>(node.clone(), &SocketAddr::from(([127, 0, 0, 1], 0)))
+			.unwrap();
+		let client = HttpClient::<C>::new(&format!("http://{}", server.address()));
+
+		assert_eq!(client.node_info().unwrap().genesis_time, 42);
+		assert_eq!(client.produce_block(3, Default::default()).unwrap().slot, 3);
+		assert_eq!(client.produce_attestation_data(5, 1).unwrap().index, 1);
+		assert!(match client.duties(0, &[]) {
+			Err(api::Error::Node(_)) => true,
+			_ => false,
+		});
+
+		assert_eq!(client.submit_block(Default::default()), Ok(()));
+		assert_eq!(client.submit_attestation(Default::default()), Ok(()));
+		assert_eq!(client.submit_proposer_slashing(Default::default()), Ok(()));
+		assert_eq!(client.submit_attester_slashing(Default::default()), Ok(()));
+		assert_eq!(client.submit_voluntary_exit(Default::default()), Ok(()));
+		assert_eq!(client.submit_deposit(3, Default::default()), Ok(()));
+		assert_eq!(
+			*node.submitted.lock().unwrap(),
+			vec![
+				"block", "attestation", "proposer_slashing", "attester_slashing",
+				"voluntary_exit", "deposit",
+			],
+		);
+
+		server.close();
+	}
+}
@@ -0,0 +1,159 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deposit and eth1 block caches.
+
+use beacon::Config;
+use beacon::primitives::H256;
+use beacon::types::{Deposit, Eth1Data};
+use beacon::utils::DepositTree;
+use bm_le::tree_root;
+use core::cmp::Ordering;
+use core::convert::TryInto;
+use std::collections::BTreeMap;
+use super::{Error, Eth1Block, DepositLog};
+
+/// Deposits seen in the deposit contract, in index order.
+pub struct DepositCache<C: Config> {
+	logs: Vec<DepositLog>,
+	tree: DepositTree<C>,
+}
+
+impl<C: Config> Default for DepositCache<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<C: Config> DepositCache<C> {
+	/// Create a new empty deposit cache.
+	pub fn new() -> Self {
+		Self { logs: Vec::new(), tree: DepositTree::new() }
+	}
+
+	/// Number of deposits in the cache.
+	pub fn len(&self) -> u64 {
+		self.logs.len() as u64
+	}
+
+	/// Whether the cache is empty.
+	pub fn is_empty(&self) -> bool {
+		self.logs.is_empty()
+	}
+
+	/// Insert a new deposit. Deposits must be inserted in index order, and
+	/// re-inserting a known deposit is a no-op.
+	pub fn insert(&mut self, log: DepositLog) -> Result<(), Error> {
+		if log.index < self.len() {
+			return if self.logs[log.index as usize] == log {
+				Ok(())
+			} else {
+				Err(Error::DepositIndexMismatch { expected: self.len(), got: log.index })
+			}
+		}
+
+		if log.index != self.len() {
+			return Err(Error::DepositIndexMismatch { expected: self.len(), got: log.index })
+		}
+
+		self.tree.push(tree_root::<C::Digest, _>(&log.data));
+		self.logs.push(log);
+		Ok(())
+	}
+
+	/// Number of deposits included up to and including the given eth1 block.
+	pub fn count_at_block(&self, block_number: u64) -> u64 {
+		// Logs are sorted by block number, so the search never finds an
+		// equal element and returns the partition point.
+		self.logs.binary_search_by(|log| if log.block_number <= block_number {
+			Ordering::Less
+		} else {
+			Ordering::Greater
+		}).unwrap_or_else(|index| index) as u64
+	}
+
+	/// Deposit root when the contract contained the given number of deposits.
+	pub fn root_at(&self, count: u64) -> Result<H256, Error> {
+		Ok(self.tree.root_at(count)?)
+	}
+
+	/// Eth1 data of the given eth1 block.
+	pub fn eth1_data(&self, block: &Eth1Block) -> Eth1Data {
+		let deposit_count = self.count_at_block(block.number);
+
+		Eth1Data {
+			deposit_root: self.root_at(deposit_count)
+				.expect("Deposit count is from the cache itself; qed"),
+			deposit_count,
+			block_hash: block.hash,
+		}
+	}
+
+	/// Deposits from index `start` up to `end`, with proofs against the
+	/// deposit root at `count` deposits.
+	pub fn deposits(&self, start: u64, end: u64, count: u64) -> Result<Vec<Deposit>, Error> {
+		(start..end).map(|index| {
+			Ok(Deposit {
+				proof: self.tree.proof(index, count)?.try_into()
+					.expect("Deposit proof has deposit contract tree depth plus one; qed"),
+				data: self.logs[index as usize].data.clone(),
+			})
+		}).collect()
+	}
+}
+
+/// Eth1 blocks considered final enough to vote on.
+#[derive(Default)]
+pub struct BlockCache {
+	blocks: BTreeMap<u64, Eth1Block>,
+}
+
+impl BlockCache {
+	/// Create a new empty block cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Insert a new block.
+	pub fn insert(&mut self, block: Eth1Block) {
+		self.blocks.insert(block.number, block);
+	}
+
+	/// Block at the given number.
+	pub fn get(&self, number: u64) -> Option<&Eth1Block> {
+		self.blocks.get(&number)
+	}
+
+	/// Whether the block at the given number is cached.
+	pub fn contains(&self, number: u64) -> bool {
+		self.blocks.contains_key(&number)
+	}
+
+	/// Find a cached block by hash.
+	pub fn find(&self, hash: &H256) -> Option<&Eth1Block> {
+		self.blocks.values().find(|block| &block.hash == hash)
+	}
+
+	/// Lowest cached block number.
+	pub fn lowest(&self) -> Option<u64> {
+		self.blocks.keys().next().cloned()
+	}
+
+	/// Remove all blocks below the given number.
+	pub fn prune(&mut self, lowest: u64) {
+		self.blocks = self.blocks.split_off(&lowest);
+	}
+}
@@ -0,0 +1,411 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Eth1 chain follower, tracking deposits and voting on eth1 data.
+
+mod cache;
+mod rpc;
+
+pub use self::cache::{DepositCache, BlockCache};
+pub use self::rpc::{Eth1Client, Eth1Block, DepositLog, decode_deposit_log, DEPOSIT_EVENT_TOPIC};
+
+use beacon::{BeaconState, Config, Error as BeaconError};
+use beacon::types::{Deposit, Eth1Data};
+use beacon::utils::integer_squareroot;
+use core::cmp::{min, max, Reverse};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+use log::{info, warn};
+
+/// Maximum number of eth1 blocks to query logs for at once.
+const LOGS_BATCH_SIZE: u64 = 1000;
+/// Number of follow distances behind the eth1 head to keep blocks for.
+const BLOCK_CACHE_FOLLOW_DISTANCES: u64 = 3;
+
+/// Eth1 follower error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// Transport error talking to the eth1 node.
+	Transport(String),
+	/// Eth1 node returned an error.
+	Rpc(String),
+	/// Eth1 node returned an invalid response.
+	InvalidResponse(String),
+	/// Deposit log data is invalid.
+	InvalidDepositLog,
+	/// Deposit log is not the next expected one.
+	DepositIndexMismatch { expected: u64, got: u64 },
+	/// Cached deposits do not match the deposit root in the state.
+	DepositRootMismatch,
+	/// Beacon error.
+	Beacon(BeaconError),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+impl std::error::Error for Error { }
+
+impl From<BeaconError> for Error {
+	fn from(error: BeaconError) -> Error {
+		Error::Beacon(error)
+	}
+}
+
+struct Eth1Cache<C: Config> {
+	deposits: DepositCache<C>,
+	blocks: BlockCache,
+	head: Option<u64>,
+	next_log_block: u64,
+}
+
+impl<C: Config> Eth1Cache<C> {
+	fn eth1_data_at_distance(&self, head: u64, distance: u64) -> Option<Eth1Data> {
+		head.checked_sub(distance)
+			.and_then(|number| self.blocks.get(number))
+			.map(|block| self.deposits.eth1_data(block))
+	}
+}
+
+/// Eth1 chain follower.
+pub struct Eth1Follower<C: Config> {
+	client: Arc<Eth1Client>,
+	deposit_contract: String,
+	deploy_block: u64,
+	cache: Arc<RwLock<Eth1Cache<C>>>,
+}
+
+impl<C: Config> Clone for Eth1Follower<C> {
+	fn clone(&self) -> Self {
+		Self {
+			client: self.client.clone(),
+			deposit_contract: self.deposit_contract.clone(),
+			deploy_block: self.deploy_block,
+			cache: self.cache.clone(),
+		}
+	}
+}
+
+impl<C: Config> Eth1Follower<C> {
+	/// Create a new follower of the deposit contract, deployed at the given
+	/// eth1 block.
+	pub fn new(client: Eth1Client, deposit_contract: &str, deploy_block: u64) -> Self {
+		Self {
+			client: Arc::new(client),
+			deposit_contract: deposit_contract.to_string(),
+			deploy_block,
+			cache: Arc::new(RwLock::new(Eth1Cache {
+				deposits: DepositCache::new(),
+				blocks: BlockCache::new(),
+				head: None,
+				next_log_block: deploy_block,
+			})),
+		}
+	}
+
+	fn read(&self) -> RwLockReadGuard<Eth1Cache<C>> {
+		self.cache.read().expect("Lock is never poisoned; qed")
+	}
+
+	fn write(&self) -> RwLockWriteGuard<Eth1Cache<C>> {
+		self.cache.write().expect("Lock is never poisoned; qed")
+	}
+
+	/// Number of deposits seen so far.
+	pub fn deposit_count(&self) -> u64 {
+		self.read().deposits.len()
+	}
+
+	/// Poll the eth1 node once, importing new deposits and blocks up to the
+	/// follow distance.
+	pub fn update(&self) -> Result<(), Error> {
+		let follow_distance = C::eth1_follow_distance();
+		let head = self.client.block_number()?;
+		let followed = match head.checked_sub(follow_distance) {
+			Some(followed) => followed,
+			None => return Ok(()),
+		};
+
+		let mut from = self.read().next_log_block;
+		while from <= followed {
+			let to = min(from + LOGS_BATCH_SIZE - 1, followed);
+			let logs = self.client.deposit_logs(&self.deposit_contract, from, to)?;
+
+			let mut cache = self.write();
+			for log in logs {
+				cache.deposits.insert(log)?;
+			}
+			cache.next_log_block = to + 1;
+			from = to + 1;
+		}
+
+		let lowest = max(
+			head.saturating_sub(follow_distance * BLOCK_CACHE_FOLLOW_DISTANCES),
+			self.deploy_block,
+		);
+		for number in lowest..=followed {
+			if !self.read().blocks.contains(number) {
+				let block = self.client.block_by_number(number)?;
+				self.write().blocks.insert(block);
+			}
+		}
+
+		let mut cache = self.write();
+		cache.blocks.prune(lowest);
+		cache.head = Some(head);
+
+		Ok(())
+	}
+
+	/// Start polling the eth1 node in a background thread.
+	pub fn start(self, interval: Duration) -> thread::JoinHandle<()> where
+		C: Send + Sync + 'static,
+	{
+		thread::spawn(move || {
+			loop {
+				match self.update() {
+					Ok(()) => info!("Eth1 follower synced, {} deposits", self.deposit_count()),
+					Err(err) => warn!("Eth1 follower update failed: {}", err),
+				}
+				thread::sleep(interval);
+			}
+		})
+	}
+
+	/// Eth1 data to vote for in a block built on the given state.
+	///
+	/// Falls back to the state's current eth1 data when the follower has no
+	/// blocks at the follow distance.
+	pub fn eth1_vote(&self, state: &BeaconState<C>) -> Eth1Data {
+		let cache = self.read();
+		let head = match cache.head {
+			Some(head) => head,
+			None => return state.eth1_data.clone(),
+		};
+		let follow_distance = C::eth1_follow_distance();
+		let default_vote = match cache.eth1_data_at_distance(head, follow_distance) {
+			Some(data) => data,
+			None => return state.eth1_data.clone(),
+		};
+
+		let previous_distance = cache.blocks.find(&state.eth1_data.block_hash)
+			.map(|block| head - block.number)
+			.or_else(|| cache.blocks.lowest().map(|lowest| head - lowest + 1))
+			.unwrap_or(follow_distance);
+
+		let new_eth1_data = (follow_distance..(2 * follow_distance))
+			.filter_map(|distance| cache.eth1_data_at_distance(head, distance))
+			.collect::<Vec<_>>();
+		let all_eth1_data = (follow_distance..previous_distance)
+			.filter_map(|distance| cache.eth1_data_at_distance(head, distance))
+			.collect::<Vec<_>>();
+
+		let period = C::slots_per_eth1_voting_period();
+		let period_tail = state.slot % period >= integer_squareroot(period);
+		let votes_to_consider = if period_tail {
+			&all_eth1_data
+		} else {
+			&new_eth1_data
+		};
+		let valid_votes = state.eth1_data_votes.iter()
+			.filter(|vote| votes_to_consider.contains(*vote))
+			.collect::<Vec<_>>();
+
+		valid_votes.iter()
+			.max_by_key(|vote| (
+				valid_votes.iter().filter(|v| v == vote).count(),
+				// Tiebreak by smallest distance.
+				Reverse(all_eth1_data.iter().position(|v| v == **vote).unwrap_or(usize::max_value())),
+			))
+			.map(|vote| (*vote).clone())
+			.unwrap_or(default_vote)
+	}
+
+	/// Deposits required to be included in a block on top of the given
+	/// state, after its eth1 data has been processed.
+	pub fn deposits(&self, state: &BeaconState<C>) -> Result<Vec<Deposit>, Error> {
+		let count = state.eth1_data.deposit_count;
+		let start = state.eth1_deposit_index;
+		if start >= count {
+			return Ok(Vec::new())
+		}
+
+		let cache = self.read();
+		if cache.deposits.root_at(count)? != state.eth1_data.deposit_root {
+			return Err(Error::DepositRootMismatch)
+		}
+
+		cache.deposits.deposits(start, min(count, start + C::max_deposits()), count)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::MinimalConfig;
+	use beacon::primitives::{H256, ValidatorId};
+	use beacon::types::DepositData;
+	use beacon::utils::DepositTree;
+	use bm_le::tree_root;
+	use jsonrpc_core::{IoHandler, Params, Value};
+	use jsonrpc_http_server::ServerBuilder;
+	use serde_json::json;
+
+	type C = MinimalConfig;
+
+	const HEAD: u64 = 40;
+
+	fn word(value: usize) -> Vec<u8> {
+		let mut word = vec![0u8; 32];
+		word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+		word
+	}
+
+	fn encode_deposit_log(data: &DepositData, index: u64) -> String {
+		let fields = vec![
+			data.pubkey[..].to_vec(),
+			data.withdrawal_credentials[..].to_vec(),
+			data.amount.to_le_bytes().to_vec(),
+			data.signature[..].to_vec(),
+			index.to_le_bytes().to_vec(),
+		];
+
+		let mut head = Vec::new();
+		let mut tail = Vec::new();
+		for field in &fields {
+			head.extend(word(fields.len() * 32 + tail.len()));
+			tail.extend(word(field.len()));
+			tail.extend(field);
+			while tail.len() % 32 != 0 {
+				tail.push(0);
+			}
+		}
+		head.extend(tail);
+
+		format!("0x{}", hex::encode(head))
+	}
+
+	fn block_hash(number: u64) -> H256 {
+		H256::from_low_u64_be(number + 1000)
+	}
+
+	fn deposit(index: u64) -> DepositData {
+		DepositData {
+			pubkey: ValidatorId::from_low_u64_be(index),
+			withdrawal_credentials: H256::from_low_u64_be(index),
+			amount: 32000000000,
+			signature: Default::default(),
+		}
+	}
+
+	fn mock_eth1(deposits: Vec<(DepositData, u64)>) -> jsonrpc_http_server::Server {
+		let mut io = IoHandler::new();
+		io.add_method("eth_blockNumber", |_: Params| {
+			Ok(Value::String(rpc::quantity(HEAD)))
+		});
+		io.add_method("eth_getBlockByNumber", |params: Params| {
+			let (number, _): (String, bool) = params.parse()?;
+			let number = rpc::parse_quantity(&number).unwrap();
+			Ok(json!({
+				"hash": format!("0x{}", hex::encode(&block_hash(number)[..])),
+				"number": rpc::quantity(number),
+				"timestamp": rpc::quantity(number * 14),
+			}))
+		});
+		io.add_method("eth_getLogs", move |params: Params| {
+			let (filter,): (Value,) = params.parse()?;
+			let from = rpc::parse_quantity(filter["fromBlock"].as_str().unwrap()).unwrap();
+			let to = rpc::parse_quantity(filter["toBlock"].as_str().unwrap()).unwrap();
+
+			Ok(Value::Array(deposits.iter().enumerate()
+				.filter(|(_, (_, number))| *number >= from && *number <= to)
+				.map(|(index, (data, number))| json!({
+					"blockNumber": rpc::quantity(*number),
+					"data": encode_deposit_log(data, index as u64),
+				}))
+				.collect()))
+		});
+
+		ServerBuilder::new(io)
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap()
+	}
+
+	#[test]
+	fn decodes_deposit_log() {
+		let data = deposit(3);
+		let log = decode_deposit_log(
+			&rpc::parse_data(&encode_deposit_log(&data, 7)).unwrap(), 12
+		).unwrap();
+
+		assert_eq!(log, DepositLog { data, index: 7, block_number: 12 });
+	}
+
+	#[test]
+	fn rejects_overflowing_deposit_log_offsets() {
+		let mut data = rpc::parse_data(&encode_deposit_log(&deposit(3), 7)).unwrap();
+		data[24..32].copy_from_slice(&u64::max_value().to_be_bytes());
+		assert_eq!(decode_deposit_log(&data, 12), Err(Error::InvalidDepositLog));
+	}
+
+	#[test]
+	fn follows_deposits_and_votes() {
+		let server = mock_eth1(vec![(deposit(0), 3), (deposit(1), 20), (deposit(2), 30)]);
+		let follower = Eth1Follower::<C>::new(
+			Eth1Client::new(&format!("http://{}", server.address())), "0x00", 0
+		);
+		follower.update().unwrap();
+
+		// The third deposit is within the follow distance.
+		assert_eq!(follower.deposit_count(), 2);
+
+		let tree = DepositTree::<C>::from_leaves(
+			(0..2).map(|i| tree_root::<<C as beacon::Config>::Digest, _>(&deposit(i)))
+		);
+		let mut state = BeaconState::<C>::default();
+		let vote = follower.eth1_vote(&state);
+		assert_eq!(vote, Eth1Data {
+			deposit_root: tree.root(),
+			deposit_count: 2,
+			block_hash: block_hash(HEAD - C::eth1_follow_distance()),
+		});
+
+		let majority = follower.read().eth1_data_at_distance(HEAD, 25).unwrap();
+		assert_eq!(majority.deposit_count, 1);
+		for _ in 0..2 {
+			state.eth1_data_votes.push(majority.clone());
+		}
+		state.eth1_data_votes.push(vote.clone());
+		assert_eq!(follower.eth1_vote(&state), majority);
+
+		state.eth1_data = vote;
+		let deposits = follower.deposits(&state).unwrap();
+		assert_eq!(deposits.len(), 2);
+		for (index, deposit) in deposits.iter().enumerate() {
+			assert!(beacon::utils::is_valid_merkle_branch::<C>(
+				tree_root::<<C as beacon::Config>::Digest, _>(&deposit.data),
+				&deposit.proof,
+				beacon::consts::DEPOSIT_CONTRACT_TREE_DEPTH + 1,
+				index as u64,
+				state.eth1_data.deposit_root,
+			));
+		}
+	}
+}
@@ -0,0 +1,226 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Eth1 JSON-RPC client.
+
+use beacon::primitives::{H256, ValidatorId, Signature};
+use beacon::types::DepositData;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::Error;
+
+/// Keccak-256 of `DepositEvent(bytes,bytes,bytes,bytes,bytes)`.
+pub const DEPOSIT_EVENT_TOPIC: &str =
+	"0x649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5";
+
+/// Eth1 block header info needed for voting.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Eth1Block {
+	/// Block hash.
+	pub hash: H256,
+	/// Block number.
+	pub number: u64,
+	/// Block timestamp.
+	pub timestamp: u64,
+}
+
+/// Deposit emitted by the deposit contract.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DepositLog {
+	/// Deposit data.
+	pub data: DepositData,
+	/// Deposit index in the contract.
+	pub index: u64,
+	/// Eth1 block number the deposit was included in.
+	pub block_number: u64,
+}
+
+#[derive(Serialize)]
+struct Request<'a, P: Serialize> {
+	jsonrpc: &'static str,
+	method: &'a str,
+	params: P,
+	id: usize,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+	message: String,
+}
+
+#[derive(Deserialize)]
+struct Response<R> {
+	result: Option<R>,
+	error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct RpcBlock {
+	hash: String,
+	number: String,
+	timestamp: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcLog {
+	block_number: String,
+	data: String,
+}
+
+/// Encode an integer as an eth1 JSON-RPC quantity.
+pub fn quantity(value: u64) -> String {
+	format!("0x{:x}", value)
+}
+
+/// Parse an eth1 JSON-RPC quantity.
+pub fn parse_quantity(value: &str) -> Result<u64, Error> {
+	let value = value.trim_start_matches("0x");
+	u64::from_str_radix(value, 16)
+		.map_err(|e| Error::InvalidResponse(format!("Invalid quantity: {}", e)))
+}
+
+/// Parse eth1 JSON-RPC data.
+pub fn parse_data(value: &str) -> Result<Vec<u8>, Error> {
+	hex::decode(value.trim_start_matches("0x"))
+		.map_err(|e| Error::InvalidResponse(format!("Invalid data: {}", e)))
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<usize, Error> {
+	let end = offset.checked_add(32).ok_or(Error::InvalidDepositLog)?;
+	let word = data.get(offset..end).ok_or(Error::InvalidDepositLog)?;
+	if word[..24].iter().any(|b| *b != 0) {
+		return Err(Error::InvalidDepositLog)
+	}
+
+	let mut bytes = [0u8; 8];
+	bytes.copy_from_slice(&word[24..]);
+	Ok(u64::from_be_bytes(bytes) as usize)
+}
+
+fn read_bytes(data: &[u8], field: usize, len: usize) -> Result<&[u8], Error> {
+	let offset = read_word(data, field * 32)?;
+	if read_word(data, offset)? != len {
+		return Err(Error::InvalidDepositLog)
+	}
+
+	let start = offset.checked_add(32).ok_or(Error::InvalidDepositLog)?;
+	let end = start.checked_add(len).ok_or(Error::InvalidDepositLog)?;
+	data.get(start..end).ok_or(Error::InvalidDepositLog)
+}
+
+/// Decode the ABI-encoded data of a deposit event.
+pub fn decode_deposit_log(data: &[u8], block_number: u64) -> Result<DepositLog, Error> {
+	let mut amount = [0u8; 8];
+	amount.copy_from_slice(read_bytes(data, 2, 8)?);
+	let mut index = [0u8; 8];
+	index.copy_from_slice(read_bytes(data, 4, 8)?);
+
+	Ok(DepositLog {
+		data: DepositData {
+			pubkey: ValidatorId::from_slice(read_bytes(data, 0, 48)?),
+			withdrawal_credentials: H256::from_slice(read_bytes(data, 1, 32)?),
+			amount: u64::from_le_bytes(amount),
+			signature: Signature::from_slice(read_bytes(data, 3, 96)?),
+		},
+		index: u64::from_le_bytes(index),
+		block_number,
+	})
+}
+
+/// Eth1 JSON-RPC client over HTTP.
+pub struct Eth1Client {
+	url: String,
+	client: reqwest::Client,
+	next_id: AtomicUsize,
+}
+
+impl Eth1Client {
+	/// Create a new client for the eth1 node at url.
+	pub fn new(url: &str) -> Self {
+		Self {
+			url: url.to_string(),
+			client: reqwest::Client::new(),
+			next_id: AtomicUsize::new(0),
+		}
+	}
+
+	fn call<P: Serialize, R: DeserializeOwned>(
+		&self,
+		method: &str,
+		params: P
+	) -> Result<R, Error> {
+		let request = Request {
+			jsonrpc: "2.0",
+			method,
+			params,
+			id: self.next_id.fetch_add(1, Ordering::SeqCst),
+		};
+
+		let response: Response<R> = self.client.post(&self.url)
+			.json(&request)
+			.send()
+			.and_then(|mut response| response.json())
+			.map_err(|e| Error::Transport(format!("{}", e)))?;
+
+		match (response.result, response.error) {
+			(_, Some(error)) => Err(Error::Rpc(error.message)),
+			(Some(result), None) => Ok(result),
+			(None, None) => Err(Error::InvalidResponse("Missing result".to_string())),
+		}
+	}
+
+	/// Current eth1 head block number.
+	pub fn block_number(&self) -> Result<u64, Error> {
+		let number: String = self.call("eth_blockNumber", ())?;
+		parse_quantity(&number)
+	}
+
+	/// Eth1 block at the given number.
+	pub fn block_by_number(&self, number: u64) -> Result<Eth1Block, Error> {
+		let block: RpcBlock = self.call("eth_getBlockByNumber", (quantity(number), false))?;
+		let hash = parse_data(&block.hash)?;
+		if hash.len() != 32 {
+			return Err(Error::InvalidResponse("Invalid block hash".to_string()))
+		}
+
+		Ok(Eth1Block {
+			hash: H256::from_slice(&hash),
+			number: parse_quantity(&block.number)?,
+			timestamp: parse_quantity(&block.timestamp)?,
+		})
+	}
+
+	/// Deposit logs of the contract between the given blocks, inclusive.
+	pub fn deposit_logs(
+		&self,
+		deposit_contract: &str,
+		from_block: u64,
+		to_block: u64,
+	) -> Result<Vec<DepositLog>, Error> {
+		let logs: Vec<RpcLog> = self.call("eth_getLogs", (json!({
+			"address": deposit_contract,
+			"topics": [DEPOSIT_EVENT_TOPIC],
+			"fromBlock": quantity(from_block),
+			"toBlock": quantity(to_block),
+		}),))?;
+
+		logs.into_iter().map(|log| {
+			decode_deposit_log(&parse_data(&log.data)?, parse_quantity(&log.block_number)?)
+		}).collect()
+	}
+}
@@ -0,0 +1,111 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Interop mocked start with deterministic validator keys.
+
+use beacon::{genesis_beacon_state, BeaconState, BLSConfig, Config, Error};
+use beacon::primitives::*;
+use beacon::types::*;
+use beacon::utils::DepositTree;
+use bm_le::tree_root;
+use crypto::bls;
+use sha2::{Sha256, Digest};
+use core::convert::TryInto;
+
+/// Eth1 block hash used by the interop genesis.
+pub const INTEROP_ETH1_BLOCK_HASH: [u8; 32] = [0x42; 32];
+
+/// Create signed deposit data of the given secret key.
+pub fn deposit_data<C: Config>(
+	secret: &bls::Secret,
+	withdrawal_credentials: H256,
+	amount: Gwei,
+) -> DepositData {
+	let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(secret).as_bytes()[..]);
+	let mut data = DepositData {
+		pubkey,
+		withdrawal_credentials,
+		amount,
+		signature: Default::default(),
+	};
+	data.signature = Signature::from_slice(&bls::Signature::new(
+		&tree_root::<C::Digest, _>(&SigningDepositData::from(data.clone()))[..],
+		beacon::genesis_domain(C::domain_deposit()),
+		secret
+	).as_bytes()[..]);
+
+	data
+}
+
+/// BLS withdrawal credentials of the given public key.
+pub fn withdrawal_credentials<C: Config>(pubkey: &ValidatorId) -> H256 {
+	let mut credentials = H256::from_slice(&Sha256::digest(&pubkey[..])[..]);
+	credentials[0] = C::bls_withdrawal_prefix_byte();
+	credentials
+}
+
+/// Generate genesis state from deposit data, building deposit proofs
+/// against the full deposit tree.
+pub fn genesis_state_from_deposit_datas<C: Config, BLS: BLSConfig>(
+	deposit_datas: &[DepositData],
+	genesis_time: Uint,
+	eth1_block_hash: H256,
+) -> Result<BeaconState<C>, Error> {
+	let tree = DepositTree::<C>::from_leaves(
+		deposit_datas.iter().map(|data| tree_root::<C::Digest, _>(data))
+	);
+	let deposits = deposit_datas.iter()
+		.enumerate()
+		.map(|(i, data)| {
+			Ok(Deposit {
+				proof: tree.proof(i as u64, tree.len())?.try_into()
+					.expect("Deposit proof has deposit contract tree depth plus one; qed"),
+				data: data.clone(),
+			})
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+	let eth1_data = Eth1Data {
+		deposit_root: tree.root(),
+		deposit_count: tree.len(),
+		block_hash: eth1_block_hash,
+	};
+
+	genesis_beacon_state::<C, BLS>(&deposits, genesis_time, eth1_data)
+}
+
+/// Generate the interop genesis state with the given validator count and
+/// genesis time.
+pub fn interop_genesis_state<C: Config, BLS: BLSConfig>(
+	validator_count: u64,
+	genesis_time: Uint,
+) -> Result<BeaconState<C>, Error> {
+	let deposit_datas = (0..validator_count).map(|index| {
+		let secret = bls::interop::secret(index);
+		let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&secret).as_bytes()[..]);
+
+		deposit_data::<C>(
+			&secret,
+			withdrawal_credentials::<C>(&pubkey),
+			C::max_effective_balance(),
+		)
+	}).collect::<Vec<_>>();
+
+	genesis_state_from_deposit_datas::<C, BLS>(
+		&deposit_datas,
+		genesis_time,
+		H256::from(INTEROP_ETH1_BLOCK_HASH),
+	)
+}
@@ -14,17 +14,23 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 mod pool;
+pub mod api;
 pub mod backend;
+pub mod eth1;
+pub mod interop;
+pub mod operations;
 pub mod preset;
+pub mod slasher;
 
 pub use pool::AttestationPool;
+pub use operations::OperationPool;
 pub use shasper_runtime::{Block, StateExternalities};
 
 use beacon::primitives::H256;
 use beacon::types::*;
-use beacon::{Error as BeaconError, BeaconState, BeaconExecutive, Config,
-			 BLSConfig, Inherent, Transaction};
-use std::sync::Arc;
+use beacon::{Error as BeaconError, BeaconState, BeaconExecutive, BeaconCache,
+			 Config, BLSConfig, Inherent, Transaction};
+use std::sync::{Arc, Mutex};
 use blockchain::{Block as BlockT, BlockExecutor, AsExternalities};
 use lmd_ghost::JustifiableExecutor;
 use core::marker::PhantomData;
@@ -132,14 +138,48 @@ impl From<BeaconError> for Error {
 	}
 }
 
-#[derive(Clone)]
+/// Observer of executed blocks.
+pub trait BlockObserver<C: Config>: Send + Sync {
+	/// Called after a block has been executed, with its post state.
+	fn observe_block(&self, block: &BeaconBlock<C>, state: &mut BeaconState<C>);
+}
+
 pub struct Executor<C: Config, BLS: BLSConfig> {
+	observer: Option<Arc<dyn BlockObserver<C>>>,
+	cache: Arc<Mutex<BeaconCache>>,
 	_marker: PhantomData<(C, BLS)>,
 }
 
+impl<C: Config, BLS: BLSConfig> Clone for Executor<C, BLS> {
+	fn clone(&self) -> Self {
+		Self {
+			observer: self.observer.clone(),
+			cache: self.cache.clone(),
+			_marker: PhantomData,
+		}
+	}
+}
+
 impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 	pub fn new() -> Self {
-		Self { _marker: PhantomData }
+		Self { observer: None, cache: Default::default(), _marker: PhantomData }
+	}
+
+	/// Use the given beacon cache, shared with other executors.
+	pub fn with_cache(mut self, cache: Arc<Mutex<BeaconCache>>) -> Self {
+		self.cache = cache;
+		self
+	}
+
+	/// Beacon cache used by this executor.
+	pub fn cache(&self) -> &Arc<Mutex<BeaconCache>> {
+		&self.cache
+	}
+
+	/// Notify the given observer of every executed block.
+	pub fn with_observer(mut self, observer: Arc<dyn BlockObserver<C>>) -> Self {
+		self.observer = Some(observer);
+		self
 	}
 
 	pub fn initialize_block(
@@ -147,7 +187,8 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 		state: &mut <Self as BlockExecutor>::Externalities,
 		target_slot: u64,
 	) -> Result<(), Error> {
-		Ok(beacon::initialize_block::<C>(state.state_mut(), target_slot)?)
+		let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+		Ok(beacon::initialize_block::<C>(state.state_mut(), target_slot, Some(&mut *cache))?)
 	}
 
 	pub fn apply_inherent(
@@ -156,7 +197,8 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 		state: &mut <Self as BlockExecutor>::Externalities,
 		inherent: Inherent,
 	) -> Result<UnsealedBeaconBlock<C>, Error> {
-		Ok(beacon::apply_inherent::<C, BLS>(&parent_block.0, state.state_mut(), inherent)?)
+		let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+		Ok(beacon::apply_inherent::<C, BLS>(&parent_block.0, state.state_mut(), inherent, Some(&mut *cache))?)
 	}
 
 	pub fn apply_extrinsic(
@@ -165,7 +207,8 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 		state: &mut <Self as BlockExecutor>::Externalities,
 		extrinsic: Transaction<C>,
 	) -> Result<(), Error> {
-		Ok(beacon::apply_transaction::<C, BLS>(block, state.state_mut(), extrinsic)?)
+		let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+		Ok(beacon::apply_transaction::<C, BLS>(block, state.state_mut(), extrinsic, Some(&mut *cache))?)
 	}
 
 	pub fn finalize_block(
@@ -173,7 +216,8 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 		block: &mut UnsealedBeaconBlock<C>,
 		state: &mut <Self as BlockExecutor>::Externalities,
 	) -> Result<(), Error> {
-		Ok(beacon::finalize_block::<C, BLS>(block, state.state_mut())?)
+		let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+		Ok(beacon::finalize_block::<C, BLS>(block, state.state_mut(), Some(&mut *cache))?)
 	}
 }
 
@@ -187,7 +231,15 @@ impl<C: Config, BLS: BLSConfig> BlockExecutor for Executor<C, BLS> {
 		block: &Block<C>,
 		state: &mut Self::Externalities,
 	) -> Result<(), Error> {
-		Ok(beacon::execute_block::<C, BLS>(&block.0, state.state_mut())?)
+		{
+			let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+			beacon::execute_block::<C, BLS>(&block.0, state.state_mut(), Some(&mut *cache))?;
+		}
+		if let Some(observer) = self.observer.as_ref() {
+			observer.observe_block(&block.0, state.state_mut());
+		}
+
+		Ok(())
 	}
 }
 
@@ -219,7 +271,8 @@ impl<C: Config, BLS: BLSConfig> JustifiableExecutor for Executor<C, BLS> {
 		block: &Self::Block,
 		state: &mut Self::Externalities,
 	) -> Result<Vec<(Self::ValidatorIndex, <Self::Block as BlockT>::Identifier)>, Self::Error> {
-		let executive = BeaconExecutive::new(state.state_mut());
+		let mut cache = self.cache.lock().expect("Lock is never poisoned; qed");
+		let executive = BeaconExecutive::with_cache(state.state_mut(), Some(&mut *cache));
 		Ok(executive.block_vote_targets(&block.0)?)
 	}
 }
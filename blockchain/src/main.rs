@@ -13,125 +13,44 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
-use beacon::{genesis_beacon_state, Config, Inherent, Transaction, BeaconExecutive};
+use beacon::{Config, BeaconCache, RuntimeConfig, RuntimeParameters};
 use beacon::primitives::*;
 use beacon::types::*;
-use blockchain::{AsExternalities, Auxiliary, Block as BlockT};
+use blockchain::{AsExternalities, Auxiliary};
 use blockchain::backend::{SharedMemoryBackend, SharedCommittable, ChainQuery, Store, ImportLock, Operation};
-use blockchain::import::{SharedBlockImporter, MutexImporter};
+use blockchain::import::MutexImporter;
 use blockchain_rocksdb::RocksBackend;
-use shasper_blockchain::{Block, Executor, MemoryState, RocksState, Error, StateExternalities, AttestationPool};
+use shasper_blockchain::{Block, Executor, MemoryState, RocksState, StateExternalities, AttestationPool, OperationPool};
+use shasper_blockchain::api::{NodeApi, start_server, prune_pools};
 use shasper_blockchain::preset::Preset;
+use shasper_blockchain::interop;
+use shasper_blockchain::eth1::{Eth1Client, Eth1Follower};
+use shasper_blockchain::slasher::{Slasher, SlasherService};
 use shasper_blockchain::backend::ShasperBackend;
 use shasper_network::NetworkConfig;
+use shasper_validator::ValidatorClient;
+use shasper_validator::api::BeaconNode;
+use shasper_validator::slashing_protection::SlashingProtection;
 use lmd_ghost::archive::{ArchiveGhostImporter, AncestorQuery};
 use clap::{App, Arg, ArgMatches};
 use libp2p::Multiaddr;
 use std::thread;
 use std::str::FromStr;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::collections::HashMap;
 use ssz::Decode;
-use core::time::Duration;
-use core::convert::TryInto;
-use serde::{Serialize, Deserialize};
-use log::{info, warn, trace};
+use log::{info, warn, debug};
 use bm_le::tree_root;
 use crypto::bls;
+use shasper_clock::{Clock, SystemClock, SlotTimer, Tick};
 
 type BLS = bls::BLSVerification;
 
-fn deposit_tree<C: Config>(deposits: &[DepositData]) -> Vec<Vec<H256>> {
-	let mut zerohashes = vec![H256::default()];
-	for layer in 1..32 {
-		zerohashes.push(C::hash(&[
-			zerohashes[layer - 1].as_ref(),
-			zerohashes[layer - 1].as_ref(),
-		]));
-	}
-
-	let mut values = deposits.iter().map(|d| {
-		tree_root::<C::Digest, _>(d)
-	}).collect::<Vec<_>>();
-	let values_len = values.len();
-	let mut tree = vec![values.clone()];
-
-	for h in 0..(beacon::consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize) {
-		if values.len() % 2 == 1 {
-			values.push(zerohashes[h]);
-		}
-		let mut new_values = Vec::new();
-		for i in 0..(values.len() / 2) {
-			new_values.push(C::hash(&[
-				values[i * 2].as_ref(),
-				values[i * 2 + 1].as_ref()
-			]));
-		}
-		values = new_values;
-		tree.push(values.clone());
-	}
-	assert!(values.len() == 1);
-	values.push({
-		let mut ret = values_len.to_le_bytes().to_vec();
-		while ret.len() < 32 {
-			ret.push(0);
-		}
-		H256::from_slice(&ret[..])
-	});
-	tree[32].push(values[1]);
-	tree.push(vec![C::hash(&[
-		values[0].as_ref(),
-		values[1].as_ref(),
-	])]);
-	assert!(tree.len() == 34);
-
-	tree
-}
-
-fn deposit_root(tree: &Vec<Vec<H256>>) -> H256 {
-	tree.last().expect("Merkle tree cannot be empty; qed")[0]
-}
-
-fn deposit_proof<C: Config>(tree: &Vec<Vec<H256>>, item_index: usize) -> Vec<H256> {
-	let mut zerohashes = vec![H256::default()];
-	for layer in 1..32 {
-		zerohashes.push(C::hash(&[
-			zerohashes[layer - 1].as_ref(),
-			zerohashes[layer - 1].as_ref(),
-		]));
-	}
-
-	let mut proof = Vec::new();
-	for i in 0..(beacon::consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize) {
-		let subindex = (item_index / 2usize.pow(i as u32)) ^ 1;
-		if subindex < tree[i].len() {
-			proof.push(tree[i][subindex]);
-		} else {
-			proof.push(zerohashes[i]);
-		}
-	}
-	proof.push(tree[32][1]);
-	proof
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct ValidatorKey {
-	pub privkey: String,
-	pub pubkey: String,
-}
-
-fn string_to_bytes(string: &str) -> Result<Vec<u8>, String> {
-    let string = if string.starts_with("0x") {
-        &string[2..]
-    } else {
-        string
-    };
-
-    hex::decode(string).map_err(|e| format!("Unable to decode public or private key: {}", e))
-}
-
 fn main() {
 	pretty_env_logger::init();
 
@@ -151,9 +70,18 @@ fn main() {
 			 .long("libp2p-nodes")
 			 .takes_value(true)
 			 .help("Comma-separated libp2p nodes to initially connect to"))
-		.arg(Arg::with_name("author")
-			 .long("author")
-			 .help("Whether to author blocks"))
+		.arg(Arg::with_name("dev-author")
+			 .long("dev-author")
+			 .help("Development only: run an in-process validator client with the given keys. \
+					Use the standalone validator client to keep keys out of the node"))
+		.arg(Arg::with_name("slasher")
+			 .long("slasher")
+			 .help("Whether to detect slashable offences and include them in blocks"))
+		.arg(Arg::with_name("api-port")
+			 .long("api-port")
+			 .takes_value(true)
+			 .default_value("5052")
+			 .help("Port of the validator API"))
 		.arg(Arg::with_name("genesis-state")
 			 .long("genesis-state")
 			 .takes_value(true)
@@ -161,7 +89,37 @@ fn main() {
 		.arg(Arg::with_name("validator-keys")
 			 .long("validator-keys")
 			 .takes_value(true)
-			 .help("Yaml validator keys"))
+			 .requires("dev-author")
+			 .help("Yaml validator keys of the in-process validator client"))
+		.arg(Arg::with_name("interop-genesis-validators")
+			 .long("interop-genesis-validators")
+			 .takes_value(true)
+			 .requires("interop-genesis-time")
+			 .help("Number of deterministic validators in the interop genesis state"))
+		.arg(Arg::with_name("interop-genesis-time")
+			 .long("interop-genesis-time")
+			 .takes_value(true)
+			 .help("Genesis time of the interop genesis state"))
+		.arg(Arg::with_name("interop-validators")
+			 .long("interop-validators")
+			 .takes_value(true)
+			 .requires("dev-author")
+			 .help("Range of deterministic interop validator keys of the in-process validator \
+					client, as in 0..16"))
+		.arg(Arg::with_name("eth1-endpoint")
+			 .long("eth1-endpoint")
+			 .takes_value(true)
+			 .requires("deposit-contract")
+			 .help("Eth1 JSON-RPC endpoint to follow deposits from"))
+		.arg(Arg::with_name("deposit-contract")
+			 .long("deposit-contract")
+			 .takes_value(true)
+			 .help("Address of the eth1 deposit contract"))
+		.arg(Arg::with_name("deposit-contract-deploy-block")
+			 .long("deposit-contract-deploy-block")
+			 .takes_value(true)
+			 .default_value("0")
+			 .help("Eth1 block the deposit contract was deployed at"))
 		.arg(Arg::with_name("chain")
 			 .long("chain")
 			 .takes_value(true)
@@ -170,6 +128,10 @@ fn main() {
 			 .long("config")
 			 .takes_value(true)
 			 .help("Config to use"))
+		.arg(Arg::with_name("config-file")
+			 .long("config-file")
+			 .takes_value(true)
+			 .help("Spec config file with values overriding the chosen config"))
 		.get_matches();
 
 	let preset = matches.value_of("chain").map(|name| {
@@ -184,6 +146,20 @@ fn main() {
 			"minimal"
 		});
 
+	if let Some(config_file) = matches.value_of("config-file") {
+		let file = std::fs::File::open(config_file).expect("Opening config file failed");
+		let parameters: RuntimeParameters = serde_yaml::from_reader(file)
+			.expect("Parsing config file failed");
+
+		info!("Using chain config: {} with values from {}", config_name, config_file);
+		return match config_name {
+			"minimal" => main_with_runtime_config::<beacon::MinimalConfig>(matches, preset, parameters),
+			"mainnet" => main_with_runtime_config::<beacon::MainnetConfig>(matches, preset, parameters),
+			"sapphire" => main_with_runtime_config::<beacon::SapphireConfig>(matches, preset, parameters),
+			e => panic!("Unknown config name: {:?}", e),
+		}
+	}
+
 	info!("Using chain config: {}", config_name);
 	match config_name {
 		"minimal" => main_with_config::<beacon::MinimalConfig>(matches, preset),
@@ -193,33 +169,30 @@ fn main() {
 	}
 }
 
+fn main_with_runtime_config<Base: Config>(
+	matches: ArgMatches,
+	preset: Option<Preset>,
+	parameters: RuntimeParameters,
+) where
+	Base: Unpin,
+	Block<RuntimeConfig<Base>>: ssz::Encode + ssz::Decode + Unpin + Send + Sync,
+{
+	RuntimeConfig::<Base>::install(parameters).expect("Invalid config file");
+	main_with_config::<RuntimeConfig<Base>>(matches, preset)
+}
+
 fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) where
 	C: Unpin + Clone + Send + Sync + 'static,
 	Block<C>: ssz::Encode + ssz::Decode + Unpin + Send + Sync,
 {
-	let mut keys: HashMap<ValidatorId, bls::Secret> = HashMap::new();
-
-	if let Some(validator_keys) = matches.value_of("validator-keys") {
-		const PRIVATE_KEY_BYTES: usize = 48;
-
-		let file = File::open(validator_keys).unwrap();
-		let coll = serde_yaml::from_reader::<_, Vec<ValidatorKey>>(BufReader::new(file)).unwrap();
-
-		for key in coll {
-			let privkey = string_to_bytes(&key.privkey).unwrap();
-
-			let sk = {
-				let mut bytes = vec![0; PRIVATE_KEY_BYTES - privkey.len()];
-				bytes.extend_from_slice(&privkey);
-				bls::Secret::from_bytes(&bytes)
-					.map_err(|e| format!("Failed to decode bytes into secret key: {:?}", e))
-					.unwrap()
-			};
-
-			let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&sk).as_bytes()[..]);
-
-			keys.insert(pubkey, sk);
-		}
+	let mut keys: HashMap<ValidatorId, bls::Secret> = match matches.value_of("validator-keys") {
+		Some(validator_keys) => shasper_validator::keys::load_yaml(validator_keys).unwrap(),
+		None => HashMap::new(),
+	};
+	if let Some(range) = matches.value_of("interop-validators") {
+		keys.extend(shasper_validator::keys::load_interop(
+			shasper_validator::keys::parse_range(range).unwrap()
+		));
 	}
 
 	let genesis_state = if let Some(genesis_file) = matches.value_of("genesis-state") {
@@ -230,55 +203,69 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 		Decode::decode(&mut &data[..]).unwrap()
 	} else if let Some(preset) = preset.as_ref() {
 		Decode::decode(&mut &preset.genesis_state).unwrap()
+	} else if let Some(count) = matches.value_of("interop-genesis-validators") {
+		let count = u64::from_str(count).unwrap();
+		let genesis_time = u64::from_str(
+			matches.value_of("interop-genesis-time")
+				.expect("Interop genesis time is required by interop genesis validators; qed")
+		).unwrap();
+
+		info!("Using interop genesis with {} validators at {}", count, genesis_time);
+		interop::interop_genesis_state::<C, BLS>(count, genesis_time).unwrap()
 	} else {
 		let mut deposit_datas = Vec::new();
 		for i in 0..10 {
 			let seckey = bls::Secret::random(&mut rand::thread_rng());
-			let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&seckey).as_bytes()[..]);
-			let mut data = DepositData {
-				pubkey: pubkey.clone(),
-				withdrawal_credentials: H256::from_low_u64_le(i as u64),
-				amount: 32000000000,
-				signature: Default::default(),
-			};
-			let signature = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<sha2::Sha256, _>(&SigningDepositData::from(data.clone()))[..],
-				beacon::genesis_domain(C::domain_deposit()),
-				&seckey
-			).as_bytes()[..]);
-			data.signature = signature;
+			let data = interop::deposit_data::<C>(
+				&seckey,
+				H256::from_low_u64_le(i as u64),
+				32000000000,
+			);
+			keys.insert(data.pubkey.clone(), seckey);
 			deposit_datas.push(data);
-			keys.insert(pubkey, seckey);
 		}
 
-		let deposit_tree = deposit_tree::<C>(&deposit_datas);
-		let deposits = deposit_datas.clone().into_iter()
-			.enumerate()
-			.map(|(i, deposit_data)| {
-				Deposit {
-					proof: deposit_proof::<C>(&deposit_tree, i).try_into().ok().unwrap(),
-					data: deposit_data,
-				}
-			})
-			.collect::<Vec<_>>();
-		let deposit_root = deposit_root(&deposit_tree);
-		let eth1_data = Eth1Data {
-			deposit_root,
-			deposit_count: deposits.len() as u64,
-			block_hash: Default::default(),
-		};
-		let genesis_state =
-			genesis_beacon_state::<C, BLS>(
-				&deposits, 0, eth1_data.clone()
-			).unwrap();
-
-		genesis_state
+		let genesis_time = SystemClock.now().as_secs();
+		interop::genesis_state_from_deposit_datas::<C, BLS>(
+			&deposit_datas, genesis_time, Default::default()
+		).unwrap()
 	};
 	let genesis_block = Block(BeaconBlock {
 		state_root: tree_root::<<C as Config>::Digest, _>(&genesis_state),
 		..Default::default()
 	});
-	let eth1_data = genesis_state.eth1_data.clone();
+
+	let author = if matches.is_present("dev-author") {
+		warn!("Signing with validator keys in the node process, which is for development only");
+		let protection = match matches.value_of("data") {
+			Some(path) => SlashingProtection::open(
+				Path::new(path).join("slashing-protection.json")
+			).unwrap(),
+			None => SlashingProtection::new(),
+		};
+		Some((keys, protection))
+	} else {
+		None
+	};
+
+	let eth1 = matches.value_of("eth1-endpoint").map(|endpoint| {
+		let follower = Eth1Follower::<C>::new(
+			Eth1Client::new(endpoint),
+			matches.value_of("deposit-contract")
+				.expect("Deposit contract is required by eth1 endpoint; qed"),
+			u64::from_str(
+				matches.value_of("deposit-contract-deploy-block")
+					.expect("Deposit contract deploy block has default value; qed")
+			).unwrap(),
+		);
+		follower.clone().start(Duration::from_secs(C::seconds_per_slot()));
+		follower
+	});
+
+	let api_addr = SocketAddr::from((
+		[127, 0, 0, 1],
+		u16::from_str(matches.value_of("api-port").expect("API port has default value; qed")).unwrap(),
+	));
 
 	let mut network_config = NetworkConfig::default();
 	network_config.libp2p_port = u16::from_str(matches.value_of("port").unwrap()).unwrap();
@@ -306,11 +293,12 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 		let lock = ImportLock::new();
 
 		run(network_config,
-			matches.is_present("author"),
+			api_addr,
+			author,
+			matches.is_present("slasher"),
 			backend,
 			lock,
-			eth1_data,
-			keys);
+			eth1);
 	} else {
 		info!("Using in-memory backend");
 		let backend = ShasperBackend::new(
@@ -322,21 +310,23 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 		let lock = ImportLock::new();
 
 		run(network_config,
-			matches.is_present("author"),
+			api_addr,
+			author,
+			matches.is_present("slasher"),
 			backend,
 			lock,
-			eth1_data,
-			keys);
+			eth1);
 	}
 }
 
 fn run<B, C: Config>(
 	config: NetworkConfig,
-	author: bool,
+	api_addr: SocketAddr,
+	author: Option<(HashMap<ValidatorId, bls::Secret>, SlashingProtection)>,
+	slasher: bool,
 	backend: B,
 	import_lock: ImportLock,
-	eth1_data: Eth1Data,
-	keys: HashMap<ValidatorId, bls::Secret>,
+	eth1: Option<Eth1Follower<C>>,
 ) where
 	Block<C>: ssz::Encode + ssz::Decode + Unpin + Send + Sync,
 	B: ChainQuery + AncestorQuery + Store<Block=Block<C>>,
@@ -346,190 +336,83 @@ fn run<B, C: Config>(
 	B: Send + Sync + 'static,
 	C: Unpin + Clone + Send + Sync + 'static,
 {
-	let executor = Executor::<C, BLS>::new();
-	let importer = MutexImporter::new(
-		ArchiveGhostImporter::new(executor, backend.clone(), import_lock.clone())
-	);
+	let operations = Arc::new(Mutex::new(OperationPool::new()));
+	let slasher = if slasher {
+		info!("Running slasher");
+		Some(SlasherService::new(Slasher::default(), operations.clone()))
+	} else {
+		None
+	};
 
-	if author {
-		let backend_build = backend.clone();
-		let importer_build = importer.clone();
-		thread::spawn(move || {
-			builder_thread(backend_build, importer_build, eth1_data, keys);
-		});
+	let cache = Arc::new(Mutex::new(BeaconCache::new()));
+	let mut executor = Executor::<C, BLS>::new().with_cache(cache.clone());
+	if let Some(slasher) = slasher.clone() {
+		executor = executor.with_observer(Arc::new(slasher));
 	}
-
-	shasper_network::start_network_simple_sync(backend, import_lock, importer, config)
-		.expect("Starting networking thread failed");
-}
-
-fn builder_thread<B, I, C: Config + Clone>(
-	backend: B,
-	importer: I,
-	eth1_data: Eth1Data,
-	keys: HashMap<ValidatorId, bls::Secret>,
-) where
-	B: ChainQuery + Store<Block=Block<C>>,
-	B::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
-	B::Auxiliary: Auxiliary<Block<C>>,
-	I: SharedBlockImporter<Block=Block<C>>
-{
-	let executor = Executor::<C, BLS>::new();
-	let mut attestations = AttestationPool::<C, BLS>::new();
-
-	loop {
-		thread::sleep(Duration::new(1, 0));
-
-		let head = backend.head();
-		info!("Building on top of {}", head);
-
-		let block = {
-			let head_block = backend.block_at(&head).unwrap();
-			let head_state = backend.state_at(&head).unwrap();
-			trace!("Justified epoch {}, finalized epoch {}",
-				   { head_state.state().current_justified_checkpoint.epoch },
-				   { head_state.state().finalized_checkpoint.epoch });
-
-			let mut state = backend.state_at(&head).unwrap();
-			let externalities = state.as_externalities();
-			let current_slot = head_block.0.slot + 1;
-			executor.initialize_block(externalities, current_slot).unwrap();
-			let executive = BeaconExecutive::new(externalities.state_mut());
-			let current_epoch = executive.current_epoch();
-
-			let randao_domain = executive.domain(C::domain_randao(), None);
-			let proposer_domain = executive.domain(C::domain_beacon_proposer(), None);
-			let attestation_domain = executive.domain(C::domain_beacon_attester(), None);
-
-			for (validator_id, validator_seckey) in &keys {
-				let validator_index = executive.validator_index(validator_id);
-
-				if let Some(validator_index) = validator_index {
-					let committee_assignment = executive
-						.committee_assignment(current_epoch, validator_index).unwrap();
-					if let Some(committee_assignment) = committee_assignment {
-						if committee_assignment.slot == current_slot {
-							trace!(
-								"Found validator {} attesting slot {} with index {}",
-								validator_id, current_slot, committee_assignment.index);
-							let committee = committee_assignment.validators;
-
-							let target_epoch = current_epoch;
-							let target_slot = beacon::utils::start_slot_of_epoch::<C>(target_epoch);
-							let target_root = if target_slot == current_slot {
-								head
-							} else {
-								executive.block_root(target_epoch).unwrap()
-							};
-							let source_epoch = executive.current_justified_checkpoint.epoch;
-							let source_root = executive.current_justified_checkpoint.root;
-							trace!(
-								"Casper source {} ({}) to target {} ({})",
-								source_epoch, source_root, target_epoch, target_root,
-							);
-
-							let data = AttestationData {
-								beacon_block_root: head_block.id(),
-								source: Checkpoint {
-									epoch: source_epoch,
-									root: source_root,
-								},
-								target: Checkpoint {
-									epoch: target_epoch,
-									root: target_root,
-								},
-								slot: committee_assignment.slot,
-								index: committee_assignment.index,
-							};
-							let signature = Signature::from_slice(&bls::Signature::new(
-								&tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
-									data: data.clone(),
-									custody_bit: false,
-								})[..],
-								attestation_domain,
-								&validator_seckey,
-							).as_bytes()[..]);
-
-							let index_into_committee = committee.iter()
-								.position(|v| *v == validator_index).unwrap();
-							let mut aggregation_bitfield = Vec::new();
-							aggregation_bitfield.resize(committee.len(), false);
-							aggregation_bitfield[index_into_committee] = true;
-							let mut custody_bitfield = Vec::new();
-							custody_bitfield.resize(committee.len(), false);
-
-							let attestation = Attestation {
-								aggregation_bits: aggregation_bitfield.into(),
-								data,
-								custody_bits: custody_bitfield.into(),
-								signature
-							};
-
-							attestations.push(attestation);
-						}
-					}
+	let attestations = Arc::new(Mutex::new(AttestationPool::new()));
+	let ghost = {
+		let backend = backend.clone();
+		let attestations = attestations.clone();
+		let operations = operations.clone();
+		ArchiveGhostImporter::new(executor, backend.clone(), import_lock.clone())
+			.with_head_observer(Arc::new(move |head: &H256| {
+				if let Err(e) = prune_pools(&backend, &attestations, &operations, head) {
+					warn!("Pruning operation pools at {} failed: {:?}", head, e);
 				}
-			}
-
-			let proposer_index = executive.beacon_proposer_index().unwrap();
-			let proposer_pubkey = executive.validator_pubkey(proposer_index).unwrap();
-			trace!("Current proposer {} ({}) on epoch {}", proposer_index, proposer_pubkey, current_epoch);
+			}))
+	};
+	let fork_choice = ghost.clone();
+	let importer = MutexImporter::new(ghost);
+	let mut api = NodeApi::<_, _, C, BLS>::new(
+		backend.clone(),
+		importer.clone(),
+		attestations,
+		operations,
+	).with_cache(cache);
+	if let Some(slasher) = slasher {
+		api = api.with_slasher(slasher);
+	}
+	if let Some(eth1) = eth1 {
+		api = api.with_eth1(eth1);
+	}
 
-			let seckey = match keys.get(&proposer_pubkey) {
-				Some(value) => value.clone(),
-				None => {
-					warn!("No secret key, skip building block.");
-					continue;
-				},
-			};
-			let randao_reveal = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<C::Digest, _>(&current_epoch)[..],
-				randao_domain,
-				&seckey
-			).as_bytes()[..]);
+	let _server = start_server(api.clone(), &api_addr)
+		.expect("Starting validator API failed");
 
-			let mut unsealed_block = executor.apply_inherent(
-				&head_block, state.as_externalities(),
-				Inherent {
-					randao_reveal,
-					eth1_data: eth1_data.clone(),
-				}
-			).unwrap();
+	let attestation_observer = {
+		let api = api.clone();
+		Arc::new(move |attestation: Attestation<C>| {
+			if let Err(e) = api.submit_attestation(attestation) {
+				debug!("Ignored gossiped attestation: {}", e);
+			}
+		})
+	};
 
-			let mut collected_attestations = Vec::new();
-			for (hash, attestation) in attestations.iter() {
-				match executor.apply_extrinsic(
-					&mut unsealed_block, state.as_externalities(),
-					Transaction::Attestation(attestation.clone())
-				) {
-					Ok(()) => {
-						collected_attestations.push(*hash);
-					},
-					Err(Error::Beacon(ref err)) if err == &beacon::Error::AttestationSubmittedTooQuickly => {},
-					Err(err) => {
-						warn!("Error when submitting an attestation: {}", err);
-					},
+	let genesis_time = backend.state_at(&backend.genesis())
+		.expect("Genesis state always exists; qed")
+		.state().genesis_time;
+	thread::spawn(move || {
+		let mut timer = SlotTimer::<C, _>::new(SystemClock, genesis_time);
+		loop {
+			for tick in timer.wait() {
+				if let Tick::Slot(slot) = tick {
+					if let Err(e) = fork_choice.update_head() {
+						warn!("Fork choice at slot {} failed: {:?}", slot, e);
+					}
 				}
 			}
-			info!("Pushed {} attestations", collected_attestations.len());
-			for hash in collected_attestations {
-				attestations.pop(&hash);
-			}
-
-			executor.finalize_block(
-				&mut unsealed_block, state.as_externalities()
-			).unwrap();
-
-			let mut block = unsealed_block.fake_seal();
-			let signature = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<C::Digest, _>(&UnsealedBeaconBlock::<C>::from(&block))[..],
-				proposer_domain,
-				&seckey
-			).as_bytes()[..]);
-			block.signature = signature;
-			Block(block)
-		};
+		}
+	});
 
-		importer.import_block(block).unwrap();
+	if let Some((keys, protection)) = author {
+		thread::spawn(move || {
+			ValidatorClient::new(api, keys)
+				.with_slashing_protection(protection)
+				.run(SystemClock);
+		});
 	}
+
+	shasper_network::start_network_simple_sync(
+		backend, import_lock, importer, attestation_observer, config,
+	).expect("Starting networking thread failed");
 }
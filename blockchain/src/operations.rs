@@ -0,0 +1,419 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pools of block operations waiting to be included.
+
+use beacon::{BeaconState, BeaconExecutive, Config, BLSConfig, consts, utils};
+use beacon::primitives::{H256, ValidatorIndex};
+use beacon::types::{
+	BeaconBlock, ProposerSlashing, AttesterSlashing, VoluntaryExit, Deposit, Validator,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use core::marker::PhantomData;
+use bm_le::tree_root;
+use crate::Error;
+
+fn validate<C: Config, F>(state: &BeaconState<C>, f: F) -> Result<(), Error> where
+	F: FnOnce(&mut BeaconExecutive<C>) -> Result<(), beacon::Error>,
+{
+	let mut state = state.clone();
+	let mut executive = BeaconExecutive::new(&mut state);
+	Ok(f(&mut executive)?)
+}
+
+fn is_slashable_later<C: Config>(validator: &Validator, state: &BeaconState<C>) -> bool {
+	!validator.slashed &&
+		utils::epoch_of_slot::<C>(state.slot) < validator.withdrawable_epoch
+}
+
+fn attester_slashing_indices<C: Config>(slashing: &AttesterSlashing<C>) -> Vec<ValidatorIndex> {
+	let attestation_1 = &slashing.attestation_1;
+	let attestation_2 = &slashing.attestation_2;
+	let indices_1 = attestation_1.custody_bit_0_indices.iter()
+		.chain(attestation_1.custody_bit_1_indices.iter())
+		.cloned()
+		.collect::<HashSet<_>>();
+
+	let mut indices = attestation_2.custody_bit_0_indices.iter()
+		.chain(attestation_2.custody_bit_1_indices.iter())
+		.cloned()
+		.filter(|index| indices_1.contains(index))
+		.collect::<Vec<_>>();
+	indices.sort();
+	indices.dedup();
+	indices
+}
+
+/// Pools of proposer slashings, attester slashings, voluntary exits and
+/// deposits.
+pub struct OperationPool<C: Config, BLS: BLSConfig> {
+	proposer_slashings: HashMap<ValidatorIndex, ProposerSlashing>,
+	attester_slashings: HashMap<H256, AttesterSlashing<C>>,
+	voluntary_exits: HashMap<ValidatorIndex, VoluntaryExit>,
+	/// Deposits by deposit index, with the deposit root their proof was
+	/// checked against.
+	deposits: BTreeMap<u64, (H256, Deposit)>,
+	_marker: PhantomData<BLS>,
+}
+
+impl<C: Config, BLS: BLSConfig> Default for OperationPool<C, BLS> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<C: Config, BLS: BLSConfig> OperationPool<C, BLS> {
+	/// Create a new empty operation pool.
+	pub fn new() -> Self {
+		Self {
+			proposer_slashings: Default::default(),
+			attester_slashings: Default::default(),
+			voluntary_exits: Default::default(),
+			deposits: Default::default(),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Insert a proposer slashing, validated against the given state.
+	pub fn insert_proposer_slashing(
+		&mut self,
+		slashing: ProposerSlashing,
+		state: &BeaconState<C>,
+	) -> Result<(), Error> {
+		if self.proposer_slashings.contains_key(&slashing.proposer_index) {
+			return Ok(())
+		}
+
+		validate(state, |executive| executive.process_proposer_slashing::<BLS>(slashing.clone()))?;
+		self.proposer_slashings.insert(slashing.proposer_index, slashing);
+		Ok(())
+	}
+
+	/// Insert an attester slashing, validated against the given state.
+	pub fn insert_attester_slashing(
+		&mut self,
+		slashing: AttesterSlashing<C>,
+		state: &BeaconState<C>,
+	) -> Result<(), Error> {
+		let hash = tree_root::<C::Digest, _>(&slashing);
+		if self.attester_slashings.contains_key(&hash) {
+			return Ok(())
+		}
+
+		validate(state, |executive| executive.process_attester_slashing::<BLS>(slashing.clone()))?;
+		self.attester_slashings.insert(hash, slashing);
+		Ok(())
+	}
+
+	/// Insert a voluntary exit, validated against the given state.
+	pub fn insert_voluntary_exit(
+		&mut self,
+		exit: VoluntaryExit,
+		state: &BeaconState<C>,
+	) -> Result<(), Error> {
+		if self.voluntary_exits.contains_key(&exit.validator_index) {
+			return Ok(())
+		}
+
+		validate(state, |executive| executive.process_voluntary_exit::<BLS>(exit.clone()))?;
+		self.voluntary_exits.insert(exit.validator_index, exit);
+		Ok(())
+	}
+
+	/// Insert a deposit at the given deposit index, with its proof checked
+	/// against the eth1 data of the given state.
+	pub fn insert_deposit(
+		&mut self,
+		index: u64,
+		deposit: Deposit,
+		state: &BeaconState<C>,
+	) -> Result<(), Error> {
+		if index < state.eth1_deposit_index || index >= state.eth1_data.deposit_count {
+			return Err(beacon::Error::DepositIndexMismatch.into())
+		}
+
+		let root = state.eth1_data.deposit_root;
+		if self.deposits.get(&index).map(|(existing, _)| *existing == root).unwrap_or(false) {
+			return Ok(())
+		}
+
+		if !utils::is_valid_merkle_branch::<C>(
+			tree_root::<C::Digest, _>(&deposit.data),
+			&deposit.proof,
+			consts::DEPOSIT_CONTRACT_TREE_DEPTH + 1,
+			index,
+			root,
+		) {
+			return Err(beacon::Error::DepositMerkleInvalid.into())
+		}
+
+		self.deposits.insert(index, (root, deposit));
+		Ok(())
+	}
+
+	/// Proposer slashings to include in a block on top of the given state.
+	pub fn proposer_slashings(&self, state: &BeaconState<C>) -> Vec<ProposerSlashing> {
+		self.proposer_slashings.values()
+			.filter(|slashing| state.validators.get(slashing.proposer_index as usize)
+					.map(|validator| validator.is_slashable(utils::epoch_of_slot::<C>(state.slot)))
+					.unwrap_or(false))
+			.take(C::max_proposer_slashings() as usize)
+			.cloned()
+			.collect()
+	}
+
+	/// Attester slashings to include in a block on top of the given state.
+	/// Slashings that would not slash any new validator are skipped.
+	pub fn attester_slashings(&self, state: &BeaconState<C>) -> Vec<AttesterSlashing<C>> {
+		let epoch = utils::epoch_of_slot::<C>(state.slot);
+		let mut covered = HashSet::new();
+		let mut slashings = Vec::new();
+
+		for slashing in self.attester_slashings.values() {
+			if slashings.len() >= C::max_attester_slashings() as usize {
+				break
+			}
+
+			let slashable = attester_slashing_indices(slashing).into_iter()
+				.filter(|index| !covered.contains(index))
+				.filter(|index| state.validators.get(*index as usize)
+						.map(|validator| validator.is_slashable(epoch))
+						.unwrap_or(false))
+				.collect::<Vec<_>>();
+			if slashable.is_empty() {
+				continue
+			}
+
+			covered.extend(slashable);
+			slashings.push(slashing.clone());
+		}
+
+		slashings
+	}
+
+	/// Voluntary exits to include in a block on top of the given state.
+	pub fn voluntary_exits(&self, state: &BeaconState<C>) -> Vec<VoluntaryExit> {
+		self.voluntary_exits.values()
+			.filter(|exit| state.validators.get(exit.validator_index as usize)
+					.map(|validator| validator.exit_epoch == consts::FAR_FUTURE_EPOCH)
+					.unwrap_or(false))
+			.take(C::max_voluntary_exits() as usize)
+			.cloned()
+			.collect()
+	}
+
+	/// Deposits to include in a block on top of the given state, after its
+	/// eth1 data has been processed, in deposit index order. Stops at the
+	/// first deposit missing from the pool.
+	pub fn deposits(&self, state: &BeaconState<C>) -> Vec<Deposit> {
+		let start = state.eth1_deposit_index;
+		let end = core::cmp::min(
+			state.eth1_data.deposit_count,
+			start + C::max_deposits(),
+		);
+
+		(start..end)
+			.map(|index| match self.deposits.get(&index) {
+				Some((root, deposit)) if *root == state.eth1_data.deposit_root =>
+					Some(deposit.clone()),
+				_ => None,
+			})
+			.take_while(|deposit| deposit.is_some())
+			.filter_map(|deposit| deposit)
+			.collect()
+	}
+
+	/// Remove operations included in the given block.
+	pub fn prune_included(&mut self, block: &BeaconBlock<C>) {
+		for slashing in block.body.proposer_slashings.iter() {
+			self.proposer_slashings.remove(&slashing.proposer_index);
+		}
+		for slashing in block.body.attester_slashings.iter() {
+			self.attester_slashings.remove(&tree_root::<C::Digest, _>(slashing));
+		}
+		for exit in block.body.voluntary_exits.iter() {
+			self.voluntary_exits.remove(&exit.validator_index);
+		}
+	}
+
+	/// Remove operations that can no longer be included on top of the
+	/// given finalized state.
+	pub fn prune(&mut self, finalized: &BeaconState<C>) {
+		let validator = |index: ValidatorIndex| finalized.validators.get(index as usize);
+
+		self.proposer_slashings.retain(|index, _| {
+			validator(*index).map(|v| is_slashable_later(v, finalized)).unwrap_or(true)
+		});
+		self.attester_slashings.retain(|_, slashing| {
+			attester_slashing_indices(slashing).into_iter().any(|index| {
+				validator(index).map(|v| is_slashable_later(v, finalized)).unwrap_or(true)
+			})
+		});
+		self.voluntary_exits.retain(|index, _| {
+			validator(*index).map(|v| v.exit_epoch == consts::FAR_FUTURE_EPOCH).unwrap_or(true)
+		});
+		self.deposits = self.deposits.split_off(&finalized.eth1_deposit_index);
+	}
+
+	/// Number of operations in the pool.
+	pub fn len(&self) -> usize {
+		self.proposer_slashings.len() + self.attester_slashings.len() +
+			self.voluntary_exits.len() + self.deposits.len()
+	}
+
+	/// Whether the pool is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::{MinimalConfig, BLSNoVerification};
+	use beacon::types::{BeaconBlockHeader, DepositData, Eth1Data};
+	use beacon::utils::DepositTree;
+	use core::convert::TryInto;
+	use crate::interop::interop_genesis_state;
+
+	type Pool = OperationPool<MinimalConfig, BLSNoVerification>;
+
+	fn state() -> BeaconState<MinimalConfig> {
+		let mut state = interop_genesis_state::<MinimalConfig, BLSNoVerification>(20, 0)
+			.expect("Interop genesis state is valid; qed");
+		state.slot = MinimalConfig::persistent_committee_period() *
+			MinimalConfig::slots_per_epoch();
+		state
+	}
+
+	fn proposer_slashing(index: ValidatorIndex) -> ProposerSlashing {
+		ProposerSlashing {
+			proposer_index: index,
+			header_1: BeaconBlockHeader { state_root: H256::repeat_byte(1), ..Default::default() },
+			header_2: BeaconBlockHeader { state_root: H256::repeat_byte(2), ..Default::default() },
+		}
+	}
+
+	fn voluntary_exit(index: ValidatorIndex) -> VoluntaryExit {
+		VoluntaryExit { validator_index: index, ..Default::default() }
+	}
+
+	#[test]
+	fn inserts_valid_and_dedups() {
+		let state = state();
+		let mut pool = Pool::new();
+
+		pool.insert_proposer_slashing(proposer_slashing(0), &state).unwrap();
+		pool.insert_proposer_slashing(proposer_slashing(0), &state).unwrap();
+		pool.insert_voluntary_exit(voluntary_exit(1), &state).unwrap();
+		pool.insert_voluntary_exit(voluntary_exit(1), &state).unwrap();
+		assert_eq!(pool.len(), 2);
+
+		assert!(pool.insert_proposer_slashing(proposer_slashing(20), &state).is_err());
+		let mut same = proposer_slashing(2);
+		same.header_2 = same.header_1.clone();
+		assert!(pool.insert_proposer_slashing(same, &state).is_err());
+		assert_eq!(pool.len(), 2);
+	}
+
+	#[test]
+	fn selects_within_block_limits() {
+		let state = state();
+		let mut pool = Pool::new();
+		for index in 0..20 {
+			pool.insert_proposer_slashing(proposer_slashing(index), &state).unwrap();
+			pool.insert_voluntary_exit(voluntary_exit(index), &state).unwrap();
+		}
+		assert_eq!(pool.len(), 40);
+
+		assert_eq!(
+			pool.proposer_slashings(&state).len(),
+			MinimalConfig::max_proposer_slashings() as usize,
+		);
+		assert_eq!(
+			pool.voluntary_exits(&state).len(),
+			MinimalConfig::max_voluntary_exits() as usize,
+		);
+
+		let mut slashed = state.clone();
+		slashed.validators[3].slashed = true;
+		slashed.validators[4].exit_epoch = 0;
+		assert!(pool.proposer_slashings(&slashed).iter().all(|s| s.proposer_index != 3));
+		assert!(pool.voluntary_exits(&slashed).iter().all(|e| e.validator_index != 4));
+	}
+
+	#[test]
+	fn orders_and_checks_deposits() {
+		let datas = (0..4u64).map(|index| DepositData {
+			amount: index,
+			..Default::default()
+		}).collect::<Vec<_>>();
+		let tree = DepositTree::<MinimalConfig>::from_leaves(
+			datas.iter().map(|data| tree_root::<<MinimalConfig as Config>::Digest, _>(data))
+		);
+		let deposit = |index: u64| Deposit {
+			proof: tree.proof(index, 4).unwrap().try_into().unwrap(),
+			data: datas[index as usize].clone(),
+		};
+
+		let mut state = state();
+		state.eth1_data = Eth1Data { deposit_root: tree.root(), deposit_count: 4, ..Default::default() };
+		state.eth1_deposit_index = 1;
+		let mut pool = Pool::new();
+
+		assert!(pool.insert_deposit(0, deposit(0), &state).is_err());
+		assert!(pool.insert_deposit(2, deposit(1), &state).is_err());
+		pool.insert_deposit(3, deposit(3), &state).unwrap();
+		assert!(pool.deposits(&state).is_empty());
+
+		pool.insert_deposit(1, deposit(1), &state).unwrap();
+		pool.insert_deposit(2, deposit(2), &state).unwrap();
+		assert_eq!(pool.deposits(&state), vec![deposit(1), deposit(2), deposit(3)]);
+
+		let mut other_root = state.clone();
+		other_root.eth1_data.deposit_root = H256::repeat_byte(1);
+		assert!(pool.deposits(&other_root).is_empty());
+
+		let mut finalized = state.clone();
+		finalized.eth1_deposit_index = 3;
+		pool.prune(&finalized);
+		assert_eq!(pool.len(), 1);
+	}
+
+	#[test]
+	fn prunes_included_and_finalized() {
+		let state = state();
+		let mut pool = Pool::new();
+		for index in 0..4 {
+			pool.insert_proposer_slashing(proposer_slashing(index), &state).unwrap();
+			pool.insert_voluntary_exit(voluntary_exit(index), &state).unwrap();
+		}
+
+		let mut block = BeaconBlock::<MinimalConfig>::default();
+		block.body.proposer_slashings.push(proposer_slashing(0));
+		block.body.voluntary_exits.push(voluntary_exit(0));
+		pool.prune_included(&block);
+		assert_eq!(pool.len(), 6);
+
+		let mut finalized = state.clone();
+		finalized.validators[1].slashed = true;
+		finalized.validators[2].exit_epoch = 0;
+		pool.prune(&finalized);
+		assert_eq!(pool.len(), 4);
+		assert!(pool.proposer_slashings(&state).iter().all(|s| s.proposer_index == 2 || s.proposer_index == 3));
+		assert!(pool.voluntary_exits(&state).iter().all(|e| e.validator_index == 1 || e.validator_index == 3));
+	}
+}
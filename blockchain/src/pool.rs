@@ -13,81 +13,433 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
-use beacon::{Config, BLSConfig};
-use beacon::primitives::H256;
+use beacon::{Config, BLSConfig, BeaconState, BeaconExecutive, Error, utils};
+use beacon::primitives::{H256, Slot};
 use beacon::types::{Attestation, AttestationDataAndCustodyBit};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use core::marker::PhantomData;
 use bm_le::tree_root;
 
+/// Committee member identified by slot, committee index and position.
+type CommitteeMember = (Slot, u64, usize);
+
+fn is_disjoint(a: &[bool], b: &[bool]) -> bool {
+	a.iter().zip(b.iter()).all(|(a, b)| !(*a && *b))
+}
+
+fn is_subset(a: &[bool], b: &[bool]) -> bool {
+	a.iter().zip(b.iter()).all(|(a, b)| !*a || *b)
+}
+
+fn members<'a, C: Config>(
+	attestation: &'a Attestation<C>
+) -> impl Iterator<Item=CommitteeMember> + 'a {
+	attestation.aggregation_bits.iter().enumerate()
+		.filter(|(_, bit)| **bit)
+		.map(move |(i, _)| (attestation.data.slot, attestation.data.index, i))
+}
+
+/// Default maximum number of aggregates kept in the pool.
+pub const DEFAULT_POOL_LIMIT: usize = 4096;
+
+/// Counters of the attestation pool.
+#[derive(Clone, Default, Debug)]
+pub struct PoolMetrics {
+	/// Attestations received.
+	pub received: u64,
+	/// Attestations aggregated into an existing aggregate.
+	pub aggregated: u64,
+	/// Attestations already covered by an existing aggregate.
+	pub duplicates: u64,
+	/// Aggregates dropped because they are too old to be included.
+	pub expired: u64,
+	/// Aggregates dropped because they can never be included on the head.
+	pub invalid: u64,
+	/// Aggregates evicted because the pool is full.
+	pub evicted: u64,
+}
+
+/// Pool of attestations, aggregated per attestation data.
 pub struct AttestationPool<C: Config, BLS: BLSConfig> {
 	pool: HashMap<H256, Vec<Attestation<C>>>,
+	limit: usize,
+	metrics: PoolMetrics,
 	_marker: PhantomData<BLS>,
 }
 
 impl<C: Config, BLS: BLSConfig> AttestationPool<C, BLS> {
 	pub fn new() -> Self {
+		Self::with_limit(DEFAULT_POOL_LIMIT)
+	}
+
+	/// Create a new pool keeping at most `limit` aggregates.
+	pub fn with_limit(limit: usize) -> Self {
 		Self {
 			pool: Default::default(),
+			limit,
+			metrics: Default::default(),
 			_marker: PhantomData,
 		}
 	}
 
-	pub fn push(&mut self, attestation: Attestation<C>) {
+	/// Number of aggregates in the pool.
+	pub fn len(&self) -> usize {
+		self.pool.values().map(|ats| ats.len()).sum()
+	}
+
+	/// Whether the pool is empty.
+	pub fn is_empty(&self) -> bool {
+		self.pool.is_empty()
+	}
+
+	/// Pool counters.
+	pub fn metrics(&self) -> &PoolMetrics {
+		&self.metrics
+	}
+
+	/// Push a new attestation, aggregating it into an existing aggregate of
+	/// the same data if their aggregation bits are disjoint. The signature
+	/// is checked against the given head state before anything is
+	/// aggregated.
+	pub fn push(
+		&mut self,
+		attestation: Attestation<C>,
+		state: &mut BeaconState<C>,
+	) -> Result<(), Error> {
+		if attestation.custody_bits.iter().any(|bit| *bit) ||
+			attestation.custody_bits.len() != attestation.aggregation_bits.len()
+		{
+			return Err(Error::AttestationInvalidCustody)
+		}
+
+		let executive = BeaconExecutive::new(state);
+		let indexed = executive.indexed_attestation(attestation.clone())?;
+		if !executive.is_valid_indexed_attestation::<BLS>(&indexed) {
+			return Err(Error::AttestationInvalidSignature)
+		}
+
+		self.insert(attestation)
+	}
+
+	fn insert(&mut self, attestation: Attestation<C>) -> Result<(), Error> {
+		self.metrics.received += 1;
+
 		let hash = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
 			data: attestation.data.clone(),
 			custody_bit: false,
 		});
+		let existings = self.pool.entry(hash).or_default();
 
-		self.pool.entry(hash)
-			.and_modify(|existings| {
-				let attestation = attestation.clone();
-				let mut aggregated = false;
-
-				for existing in existings.iter_mut() {
-					let has_duplicate = {
-						let mut has_duplicate = false;
-						for i in 0..existing.aggregation_bits.len() {
-							if attestation.aggregation_bits[i] {
-								has_duplicate = true;
-							}
-						}
-						has_duplicate
-					};
-
-					if has_duplicate {
-						continue
-					}
-
-					for (i, bit) in attestation.aggregation_bits.iter().cloned().enumerate() {
-						existing.aggregation_bits[i] |= bit;
-					}
-					for (i, bit) in attestation.custody_bits.iter().cloned().enumerate() {
-						existing.custody_bits[i] |= bit;
-					}
-					for i in 0..existing.custody_bits.len() {
-						assert_eq!(attestation.custody_bits[i], false);
-					}
-					existing.signature = BLS::aggregate_signatures(&[
-						existing.signature, attestation.signature.clone()
-					]);
-
-					aggregated = true;
-					break;
-				}
+		let mut aggregated = None;
+		for (i, existing) in existings.iter_mut().enumerate() {
+			if existing.aggregation_bits.len() != attestation.aggregation_bits.len() {
+				return Err(Error::AttestationBitFieldInvalid)
+			}
+
+			if is_subset(&attestation.aggregation_bits, &existing.aggregation_bits) {
+				self.metrics.duplicates += 1;
+				return Ok(())
+			}
 
-				if !aggregated {
-					existings.push(attestation);
+			if is_disjoint(&attestation.aggregation_bits, &existing.aggregation_bits) {
+				for (j, bit) in attestation.aggregation_bits.iter().enumerate() {
+					existing.aggregation_bits[j] |= *bit;
 				}
-			})
-			.or_insert(vec![attestation]);
-    }
+				existing.signature = BLS::aggregate_signatures(&[
+					existing.signature, attestation.signature,
+				]);
+
+				aggregated = Some(i);
+				break
+			}
+		}
+
+		match aggregated {
+			Some(i) => {
+				self.metrics.aggregated += 1;
+				// Drop other aggregates fully covered by the new one.
+				let aggregate = existings[i].clone();
+				existings.retain(|other| {
+					other == &aggregate ||
+						!is_subset(&other.aggregation_bits, &aggregate.aggregation_bits)
+				});
+			},
+			None => {
+				existings.push(attestation);
+				self.evict();
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Evict aggregates of the oldest slots until the pool is within limit.
+	fn evict(&mut self) {
+		while self.len() > self.limit {
+			let oldest = self.pool.iter()
+				.min_by_key(|(_, ats)| ats[0].data.slot)
+				.map(|(hash, _)| *hash);
+
+			match oldest {
+				Some(hash) => {
+					let removed = self.pool.remove(&hash).map(|ats| ats.len()).unwrap_or(0);
+					self.metrics.evicted += removed as u64;
+				},
+				None => break,
+			}
+		}
+	}
+
+	fn retain<F: FnMut(&Attestation<C>) -> bool>(&mut self, mut f: F) -> u64 {
+		let mut removed = 0;
+		for existings in self.pool.values_mut() {
+			let len = existings.len();
+			existings.retain(|attestation| f(attestation));
+			removed += (len - existings.len()) as u64;
+		}
+		self.pool.retain(|_, existings| !existings.is_empty());
+		removed
+	}
+
+	/// Remove aggregates that are too old to be included at the given slot.
+	pub fn prune_expired(&mut self, slot: Slot) {
+		let removed = self.retain(|attestation| {
+			slot <= attestation.data.slot + C::slots_per_epoch()
+		});
+		self.metrics.expired += removed;
+	}
+
+	/// Remove aggregates that can never be included on top of the given
+	/// head state, because their target epoch is too old or their source
+	/// does not match the justified checkpoint. Signatures are checked in
+	/// `push` and are not re-verified here.
+	pub fn prune_invalid(&mut self, state: &BeaconState<C>) {
+		let current_epoch = utils::epoch_of_slot::<C>(state.slot);
+		let previous_epoch = if current_epoch == C::genesis_epoch() {
+			C::genesis_epoch()
+		} else {
+			current_epoch - 1
+		};
 
-	pub fn pop(&mut self, key: &H256) {
-		self.pool.remove(key);
+		let removed = self.retain(|attestation| {
+			let data = &attestation.data;
+			if data.target.epoch > current_epoch {
+				true
+			} else if data.target.epoch == current_epoch {
+				data.source == state.current_justified_checkpoint
+			} else if data.target.epoch == previous_epoch {
+				data.source == state.previous_justified_checkpoint
+			} else {
+				false
+			}
+		});
+		self.metrics.invalid += removed;
+	}
+
+	/// Remove aggregates covered by the given included attestations.
+	pub fn prune_included(&mut self, included: &[Attestation<C>]) {
+		for attestation in included {
+			let hash = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+				data: attestation.data.clone(),
+				custody_bit: false,
+			});
+
+			if let Some(existings) = self.pool.get_mut(&hash) {
+				existings.retain(|existing| {
+					!is_subset(&existing.aggregation_bits, &attestation.aggregation_bits)
+				});
+				if existings.is_empty() {
+					self.pool.remove(&hash);
+				}
+			}
+		}
 	}
 
 	pub fn iter(&self) -> impl Iterator<Item=(&H256, &Attestation<C>)> {
 		self.pool.iter().flat_map(|(h, ats)| ats.iter().map(move |at| (h, at)))
 	}
+
+	/// Select attestations to include in a block on top of the given state,
+	/// greedily maximizing the number of newly covered attesters.
+	pub fn select(&self, state: &BeaconState<C>, max: usize) -> Vec<Attestation<C>> {
+		let mut covered = state.previous_epoch_attestations.iter()
+			.chain(state.current_epoch_attestations.iter())
+			.flat_map(|pending| {
+				let (slot, index) = (pending.data.slot, pending.data.index);
+				pending.aggregation_bits.iter().enumerate()
+					.filter(|(_, bit)| **bit)
+					.map(move |(i, _)| (slot, index, i))
+			})
+			.collect::<HashSet<CommitteeMember>>();
+
+		let mut candidates = self.iter()
+			.map(|(_, attestation)| attestation)
+			.filter(|attestation| {
+				attestation.data.slot + C::min_attestation_inclusion_delay() <= state.slot &&
+					state.slot <= attestation.data.slot + C::slots_per_epoch()
+			})
+			.collect::<Vec<_>>();
+
+		let mut selected = Vec::new();
+		while selected.len() < max {
+			let best = candidates.iter()
+				.enumerate()
+				.map(|(i, attestation)| {
+					(i, members(attestation).filter(|m| !covered.contains(m)).count())
+				})
+				.max_by_key(|(_, gain)| *gain);
+
+			match best {
+				Some((i, gain)) if gain > 0 => {
+					let attestation = candidates.swap_remove(i);
+					covered.extend(members(attestation));
+					selected.push(attestation.clone());
+				},
+				_ => break,
+			}
+		}
+
+		selected
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::{MinimalConfig, BLSNoVerification};
+	use beacon::primitives::Signature;
+	use beacon::types::AttestationData;
+	use crypto::bls::{self, BLSVerification};
+	use crate::interop::interop_genesis_state;
+
+	type Pool = AttestationPool<MinimalConfig, BLSNoVerification>;
+
+	fn attestation(index: u64, bits: &[bool]) -> Attestation<MinimalConfig> {
+		Attestation {
+			aggregation_bits: bits.to_vec().into(),
+			custody_bits: vec![false; bits.len()].into(),
+			data: AttestationData { slot: 1, index, ..Default::default() },
+			signature: Default::default(),
+		}
+	}
+
+	#[test]
+	fn aggregates_disjoint_only() {
+		let mut pool = Pool::new();
+		pool.insert(attestation(0, &[true, false, false, false])).unwrap();
+		pool.insert(attestation(0, &[false, true, false, false])).unwrap();
+		pool.insert(attestation(0, &[true, false, true, false])).unwrap();
+		pool.insert(attestation(0, &[true, false, false, false])).unwrap();
+
+		let mut bits = pool.iter()
+			.map(|(_, a)| a.aggregation_bits.to_vec())
+			.collect::<Vec<_>>();
+		bits.sort();
+		assert_eq!(bits, vec![
+			vec![true, false, true, false],
+			vec![true, true, false, false],
+		]);
+
+		let mut custody = attestation(0, &[false, false, false, true]);
+		custody.custody_bits[3] = true;
+		let mut state = BeaconState::<MinimalConfig>::default();
+		assert_eq!(pool.push(custody, &mut state), Err(Error::AttestationInvalidCustody));
+	}
+
+	#[test]
+	fn selects_by_max_coverage() {
+		let mut pool = Pool::new();
+		pool.insert(attestation(0, &[true, true, true, false])).unwrap();
+		pool.insert(attestation(0, &[false, false, true, true])).unwrap();
+		pool.insert(attestation(1, &[true, true, false, false])).unwrap();
+
+		let mut state = BeaconState::<MinimalConfig>::default();
+		state.slot = 2;
+
+		let selected = pool.select(&state, 2);
+		assert_eq!(selected, vec![
+			attestation(0, &[true, true, true, false]),
+			attestation(1, &[true, true, false, false]),
+		]);
+		assert_eq!(pool.select(&state, 16).len(), 3);
+	}
+
+	#[test]
+	fn expires_and_evicts() {
+		let mut pool = Pool::with_limit(2);
+		let mut old = attestation(0, &[true, false]);
+		old.data.slot = 0;
+		pool.insert(old).unwrap();
+		pool.insert(attestation(1, &[true, false])).unwrap();
+		pool.insert(attestation(2, &[true, false])).unwrap();
+
+		assert_eq!(pool.len(), 2);
+		assert_eq!(pool.metrics().evicted, 1);
+		assert!(pool.iter().all(|(_, a)| a.data.slot == 1));
+
+		pool.prune_expired(1 + MinimalConfig::slots_per_epoch());
+		assert_eq!(pool.len(), 2);
+		pool.prune_expired(2 + MinimalConfig::slots_per_epoch());
+		assert!(pool.is_empty());
+		assert_eq!(pool.metrics().expired, 2);
+	}
+
+	#[test]
+	fn prunes_by_target_and_source() {
+		let mut pool = Pool::new();
+		pool.insert(attestation(0, &[true, false])).unwrap();
+		let mut wrong_source = attestation(1, &[true, false]);
+		wrong_source.data.source.root = H256::repeat_byte(1);
+		pool.insert(wrong_source).unwrap();
+		let mut future = attestation(2, &[true, false]);
+		future.data.target.epoch = 1;
+		pool.insert(future).unwrap();
+
+		let mut state = BeaconState::<MinimalConfig>::default();
+		state.slot = 2;
+		pool.prune_invalid(&state);
+		assert_eq!(pool.len(), 2);
+
+		state.slot = 2 * MinimalConfig::slots_per_epoch();
+		pool.prune_invalid(&state);
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.metrics().invalid, 2);
+	}
+
+	#[test]
+	fn rejects_forged_signatures() {
+		let mut state = interop_genesis_state::<MinimalConfig, BLSVerification>(16, 0)
+			.expect("Interop genesis state is valid; qed");
+		let (committee, domain) = {
+			let executive = BeaconExecutive::new(&mut state);
+			(
+				executive.beacon_committee(1, 0).unwrap(),
+				executive.domain(MinimalConfig::domain_beacon_attester(), Some(0)),
+			)
+		};
+		let signed = |position: usize, signer: u64| {
+			let mut bits = vec![false; committee.len()];
+			bits[position] = true;
+			let mut attestation = attestation(0, &bits);
+			let signing_root = tree_root::<<MinimalConfig as Config>::Digest, _>(
+				&AttestationDataAndCustodyBit { data: attestation.data.clone(), custody_bit: false }
+			);
+			attestation.signature = Signature::from_slice(&bls::Signature::new(
+				&signing_root[..], domain, &bls::interop::secret(signer),
+			).as_bytes()[..]);
+			attestation
+		};
+
+		let mut pool = AttestationPool::<MinimalConfig, BLSVerification>::new();
+		pool.push(signed(0, committee[0]), &mut state).unwrap();
+		assert_eq!(
+			pool.push(signed(1, committee[0]), &mut state),
+			Err(Error::AttestationInvalidSignature),
+		);
+		assert_eq!(pool.iter().next().unwrap().1, &signed(0, committee[0]));
+
+		pool.push(signed(1, committee[1]), &mut state).unwrap();
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.metrics().aggregated, 1);
+	}
 }
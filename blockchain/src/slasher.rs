@@ -0,0 +1,354 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slasher detecting double proposals, and double and surround votes.
+
+use beacon::{BeaconState, BeaconExecutive, Config, BLSConfig, utils};
+use beacon::primitives::{H256, Epoch, Slot, ValidatorIndex};
+use beacon::types::{
+	Attestation, BeaconBlock, BeaconBlockHeader, IndexedAttestation, AttesterSlashing,
+	ProposerSlashing,
+};
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::collections::hash_map::Entry;
+use std::sync::{Arc, Mutex};
+use bm_le::tree_root;
+use log::{info, warn};
+use crate::{BlockObserver, OperationPool};
+
+/// Default number of epochs of history kept by the slasher.
+pub const DEFAULT_HISTORY_LENGTH: Epoch = 4096;
+
+fn attesting_indices<C: Config>(attestation: &IndexedAttestation<C>) -> Vec<ValidatorIndex> {
+	let mut indices = attestation.custody_bit_0_indices.iter()
+		.chain(attestation.custody_bit_1_indices.iter())
+		.cloned()
+		.collect::<Vec<_>>();
+	indices.sort();
+	indices.dedup();
+	indices
+}
+
+/// Attestation and proposal history used to detect slashable offences.
+///
+/// For each validator, `min_spans[e]` is the minimum of `target - e` over
+/// attestations with source after `e`, and `max_spans[e]` is the maximum of
+/// `target - e` over attestations with source before `e`. A new attestation
+/// surrounds an existing one if its span exceeds `min_spans[source]`, and is
+/// surrounded by one if its span is below `max_spans[source]`.
+pub struct Slasher<C: Config> {
+	history_length: Epoch,
+	attestations: HashMap<H256, IndexedAttestation<C>>,
+	targets: HashMap<(ValidatorIndex, Epoch), H256>,
+	min_spans: HashMap<ValidatorIndex, BTreeMap<Epoch, Epoch>>,
+	max_spans: HashMap<ValidatorIndex, BTreeMap<Epoch, Epoch>>,
+	proposals: HashMap<(ValidatorIndex, Slot), BeaconBlockHeader>,
+}
+
+impl<C: Config> Default for Slasher<C> {
+	fn default() -> Self {
+		Self::new(DEFAULT_HISTORY_LENGTH)
+	}
+}
+
+impl<C: Config> Slasher<C> {
+	/// Create a new slasher keeping the given number of epochs of history.
+	pub fn new(history_length: Epoch) -> Self {
+		Self {
+			history_length,
+			attestations: Default::default(),
+			targets: Default::default(),
+			min_spans: Default::default(),
+			max_spans: Default::default(),
+			proposals: Default::default(),
+		}
+	}
+
+	/// Record an attestation, returning slashings against any previously
+	/// recorded attestations it conflicts with.
+	pub fn process_attestation(
+		&mut self,
+		attestation: &IndexedAttestation<C>,
+	) -> Vec<AttesterSlashing<C>> {
+		let source = attestation.data.source.epoch;
+		let target = attestation.data.target.epoch;
+		if target < source {
+			return Vec::new()
+		}
+		let span = target - source;
+		let root = tree_root::<C::Digest, _>(&attestation.data);
+
+		let mut conflicts = HashSet::new();
+		let mut recorded = false;
+		for index in attesting_indices(attestation) {
+			match self.targets.get(&(index, target)) {
+				Some(existing) if existing == &root => continue,
+				Some(existing) => {
+					// Double vote.
+					conflicts.insert(*existing);
+					continue
+				},
+				None => (),
+			}
+
+			if let Some(min_span) = self.min_spans.get(&index).and_then(|s| s.get(&source)) {
+				if span > *min_span {
+					// Surrounding vote.
+					if let Some(existing) = self.targets.get(&(index, source + min_span)) {
+						conflicts.insert(*existing);
+					}
+				}
+			}
+			if let Some(max_span) = self.max_spans.get(&index).and_then(|s| s.get(&source)) {
+				if span < *max_span {
+					// Surrounded vote.
+					if let Some(existing) = self.targets.get(&(index, source + max_span)) {
+						conflicts.insert(*existing);
+					}
+				}
+			}
+
+			self.targets.insert((index, target), root);
+			self.update_spans(index, source, target);
+			recorded = true;
+		}
+
+		if recorded {
+			self.attestations.entry(root).or_insert_with(|| attestation.clone());
+		}
+
+		conflicts.into_iter()
+			.filter_map(|existing| self.attestations.get(&existing))
+			.map(|existing| AttesterSlashing {
+				attestation_1: existing.clone(),
+				attestation_2: attestation.clone(),
+			})
+			.collect()
+	}
+
+	fn update_spans(&mut self, index: ValidatorIndex, source: Epoch, target: Epoch) {
+		let lowest = source.saturating_sub(self.history_length);
+		let min_spans = self.min_spans.entry(index).or_default();
+		for epoch in (lowest..source).rev() {
+			let span = target - epoch;
+			match min_spans.get(&epoch) {
+				Some(existing) if *existing <= span => break,
+				_ => { min_spans.insert(epoch, span); },
+			}
+		}
+
+		let max_spans = self.max_spans.entry(index).or_default();
+		for epoch in (source + 1)..target {
+			let span = target - epoch;
+			match max_spans.get(&epoch) {
+				Some(existing) if *existing >= span => break,
+				_ => { max_spans.insert(epoch, span); },
+			}
+		}
+	}
+
+	/// Record a signed block header, returning a slashing if the proposer
+	/// already signed a different header at the same slot.
+	pub fn process_block_header(
+		&mut self,
+		proposer_index: ValidatorIndex,
+		header: &BeaconBlockHeader,
+	) -> Option<ProposerSlashing> {
+		match self.proposals.entry((proposer_index, header.slot)) {
+			Entry::Occupied(existing) => {
+				if existing.get() == header {
+					None
+				} else {
+					Some(ProposerSlashing {
+						proposer_index,
+						header_1: existing.get().clone(),
+						header_2: header.clone(),
+					})
+				}
+			},
+			Entry::Vacant(entry) => {
+				entry.insert(header.clone());
+				None
+			},
+		}
+	}
+
+	/// Remove history older than the history length before the given epoch.
+	pub fn prune(&mut self, current_epoch: Epoch) {
+		let lowest = current_epoch.saturating_sub(self.history_length);
+
+		self.targets.retain(|(_, target), _| *target >= lowest);
+		let roots = self.targets.values().cloned().collect::<HashSet<_>>();
+		self.attestations.retain(|root, _| roots.contains(root));
+		for spans in self.min_spans.values_mut().chain(self.max_spans.values_mut()) {
+			*spans = spans.split_off(&lowest);
+		}
+		self.proposals.retain(|(_, slot), _| utils::epoch_of_slot::<C>(*slot) >= lowest);
+	}
+}
+
+/// Slasher fed by imported blocks and submitted attestations, pushing found
+/// slashings into the operation pool.
+pub struct SlasherService<C: Config, BLS: BLSConfig> {
+	slasher: Arc<Mutex<Slasher<C>>>,
+	operations: Arc<Mutex<OperationPool<C, BLS>>>,
+}
+
+impl<C: Config, BLS: BLSConfig> Clone for SlasherService<C, BLS> {
+	fn clone(&self) -> Self {
+		Self {
+			slasher: self.slasher.clone(),
+			operations: self.operations.clone(),
+		}
+	}
+}
+
+impl<C: Config, BLS: BLSConfig> SlasherService<C, BLS> {
+	/// Create a new slasher service pushing into the given operation pool.
+	pub fn new(slasher: Slasher<C>, operations: Arc<Mutex<OperationPool<C, BLS>>>) -> Self {
+		Self { slasher: Arc::new(Mutex::new(slasher)), operations }
+	}
+
+	/// Observe an attestation, with a state able to compute its committee.
+	pub fn observe_attestation(&self, attestation: &Attestation<C>, state: &mut BeaconState<C>) {
+		let indexed = match BeaconExecutive::new(state).indexed_attestation(attestation.clone()) {
+			Ok(indexed) => indexed,
+			Err(err) => {
+				warn!("Slasher unable to index attestation: {:?}", err);
+				return
+			},
+		};
+
+		let slashings = self.slasher.lock().expect("Lock is never poisoned; qed")
+			.process_attestation(&indexed);
+		let mut operations = self.operations.lock().expect("Lock is never poisoned; qed");
+		for slashing in slashings {
+			info!("Found attester slashing at target epoch {}", indexed.data.target.epoch);
+			if let Err(err) = operations.insert_attester_slashing(slashing, state) {
+				warn!("Found attester slashing is not valid: {}", err);
+			}
+		}
+	}
+}
+
+impl<C: Config, BLS: BLSConfig> BlockObserver<C> for SlasherService<C, BLS> {
+	fn observe_block(&self, block: &BeaconBlock<C>, state: &mut BeaconState<C>) {
+		let proposer_index = match BeaconExecutive::new(state).beacon_proposer_index() {
+			Ok(index) => index,
+			Err(err) => {
+				warn!("Slasher unable to get proposer index: {:?}", err);
+				return
+			},
+		};
+		let header = BeaconBlockHeader {
+			slot: block.slot,
+			parent_root: block.parent_root,
+			state_root: block.state_root,
+			body_root: tree_root::<C::Digest, _>(&block.body),
+			signature: block.signature,
+		};
+
+		let slashing = {
+			let mut slasher = self.slasher.lock().expect("Lock is never poisoned; qed");
+			slasher.prune(utils::epoch_of_slot::<C>(block.slot));
+			slasher.process_block_header(proposer_index, &header)
+		};
+		if let Some(slashing) = slashing {
+			info!("Found proposer slashing of validator {} at slot {}", proposer_index, block.slot);
+			if let Err(err) = self.operations.lock().expect("Lock is never poisoned; qed")
+				.insert_proposer_slashing(slashing, state)
+			{
+				warn!("Found proposer slashing is not valid: {}", err);
+			}
+		}
+
+		for attestation in block.body.attestations.iter() {
+			self.observe_attestation(attestation, state);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beacon::MinimalConfig;
+	use beacon::types::{AttestationData, Checkpoint};
+
+	fn attestation(
+		indices: &[ValidatorIndex],
+		source: Epoch,
+		target: Epoch,
+		root: u64,
+	) -> IndexedAttestation<MinimalConfig> {
+		IndexedAttestation {
+			custody_bit_0_indices: indices.to_vec().into(),
+			custody_bit_1_indices: Vec::new().into(),
+			data: AttestationData {
+				beacon_block_root: H256::from_low_u64_be(root),
+				source: Checkpoint { epoch: source, root: Default::default() },
+				target: Checkpoint { epoch: target, root: Default::default() },
+				..Default::default()
+			},
+			signature: Default::default(),
+		}
+	}
+
+	#[test]
+	fn detects_double_votes() {
+		let mut slasher = Slasher::<MinimalConfig>::default();
+		assert!(slasher.process_attestation(&attestation(&[1, 2], 0, 1, 1)).is_empty());
+		assert!(slasher.process_attestation(&attestation(&[1, 2], 0, 1, 1)).is_empty());
+
+		let slashings = slasher.process_attestation(&attestation(&[2, 3], 0, 1, 2));
+		assert_eq!(slashings.len(), 1);
+		assert_eq!(slashings[0].attestation_1, attestation(&[1, 2], 0, 1, 1));
+	}
+
+	#[test]
+	fn detects_surround_votes() {
+		let mut slasher = Slasher::<MinimalConfig>::default();
+		assert!(slasher.process_attestation(&attestation(&[1], 2, 3, 1)).is_empty());
+		assert!(slasher.process_attestation(&attestation(&[1], 3, 5, 1)).is_empty());
+
+		// Surrounded by (3, 5).
+		let slashings = slasher.process_attestation(&attestation(&[1], 4, 4, 1));
+		assert_eq!(slashings.len(), 1);
+		assert_eq!(slashings[0].attestation_1, attestation(&[1], 3, 5, 1));
+
+		// Surrounding (2, 3).
+		let slashings = slasher.process_attestation(&attestation(&[1], 1, 6, 1));
+		assert_eq!(slashings.len(), 1);
+		assert_eq!(slashings[0].attestation_1, attestation(&[1], 2, 3, 1));
+
+		// Other validators are not affected.
+		assert!(slasher.process_attestation(&attestation(&[2], 0, 6, 1)).is_empty());
+	}
+
+	#[test]
+	fn detects_double_proposals() {
+		let mut slasher = Slasher::<MinimalConfig>::default();
+		let header = BeaconBlockHeader { slot: 3, ..Default::default() };
+		assert_eq!(slasher.process_block_header(5, &header), None);
+		assert_eq!(slasher.process_block_header(5, &header), None);
+
+		let other = BeaconBlockHeader { slot: 3, state_root: H256::from_low_u64_be(1), ..Default::default() };
+		assert_eq!(slasher.process_block_header(5, &other), Some(ProposerSlashing {
+			proposer_index: 5,
+			header_1: header,
+			header_2: other,
+		}));
+	}
+}
@@ -0,0 +1,139 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validator API exposed by the beacon node.
+
+use beacon::Config;
+use beacon::primitives::{H256, Signature, ValidatorId, Epoch, Slot, ValidatorIndex};
+use beacon::types::{
+	Fork, AttestationData, Attestation, BeaconBlock, ProposerSlashing, AttesterSlashing,
+	VoluntaryExit, Deposit,
+};
+use serde::{Serialize, Deserialize};
+
+/// Method name for fetching node info.
+pub const METHOD_NODE_INFO: &str = "validator_nodeInfo";
+/// Method name for fetching validator duties.
+pub const METHOD_DUTIES: &str = "validator_duties";
+/// Method name for producing an unsigned block.
+pub const METHOD_PRODUCE_BLOCK: &str = "validator_produceBlock";
+/// Method name for submitting a signed block.
+pub const METHOD_SUBMIT_BLOCK: &str = "validator_submitBlock";
+/// Method name for producing attestation data.
+pub const METHOD_PRODUCE_ATTESTATION_DATA: &str = "validator_produceAttestationData";
+/// Method name for submitting a signed attestation.
+pub const METHOD_SUBMIT_ATTESTATION: &str = "validator_submitAttestation";
+/// Method name for submitting a proposer slashing.
+pub const METHOD_SUBMIT_PROPOSER_SLASHING: &str = "validator_submitProposerSlashing";
+/// Method name for submitting an attester slashing.
+pub const METHOD_SUBMIT_ATTESTER_SLASHING: &str = "validator_submitAttesterSlashing";
+/// Method name for submitting a voluntary exit.
+pub const METHOD_SUBMIT_VOLUNTARY_EXIT: &str = "validator_submitVoluntaryExit";
+/// Method name for submitting a deposit.
+pub const METHOD_SUBMIT_DEPOSIT: &str = "validator_submitDeposit";
+
+/// Validator API error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// Transport failed.
+	Transport(String),
+	/// Node returned an error.
+	Node(String),
+	/// Response from the node is invalid.
+	InvalidResponse(String),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::Transport(err) => write!(f, "Transport error: {}", err),
+			Error::Node(err) => write!(f, "Node error: {}", err),
+			Error::InvalidResponse(err) => write!(f, "Invalid response: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error { }
+
+/// Chain information needed by the validator client.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NodeInfo {
+	/// Genesis time of the chain.
+	pub genesis_time: u64,
+	/// Genesis block root.
+	pub genesis_root: H256,
+	/// Hash tree root of the genesis validator registry, as used by EIP-3076
+	/// slashing protection interchange.
+	pub genesis_validators_root: H256,
+	/// Fork of the current head state.
+	pub fork: Fork,
+}
+
+/// Duties of a single validator in an epoch.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorDuty {
+	/// Validator public key.
+	pub pubkey: ValidatorId,
+	/// Validator index, if the validator is in the registry.
+	pub validator_index: Option<ValidatorIndex>,
+	/// Slot at which the validator attests.
+	pub attestation_slot: Option<Slot>,
+	/// Committee index of the attestation.
+	pub attestation_committee_index: Option<u64>,
+	/// Position of the validator in the committee.
+	pub attestation_committee_position: Option<u64>,
+	/// Length of the committee.
+	pub attestation_committee_length: Option<u64>,
+	/// Slots at which the validator proposes blocks.
+	pub block_proposal_slots: Vec<Slot>,
+}
+
+/// Beacon node interface used by the validator client.
+pub trait BeaconNode<C: Config> {
+	/// Get chain information.
+	fn node_info(&self) -> Result<NodeInfo, Error>;
+	/// Get duties of validators at epoch.
+	fn duties(&self, epoch: Epoch, pubkeys: &[ValidatorId]) -> Result<Vec<ValidatorDuty>, Error>;
+	/// Produce an unsigned block at slot on top of the current head.
+	fn produce_block(&self, slot: Slot, randao_reveal: Signature) -> Result<BeaconBlock<C>, Error>;
+	/// Submit a signed block.
+	fn submit_block(&self, block: BeaconBlock<C>) -> Result<(), Error>;
+	/// Produce attestation data for the committee at slot and index.
+	fn produce_attestation_data(&self, slot: Slot, index: u64) -> Result<AttestationData, Error>;
+	/// Submit a signed attestation.
+	fn submit_attestation(&self, attestation: Attestation<C>) -> Result<(), Error>;
+	/// Submit a proposer slashing to the operation pool.
+	fn submit_proposer_slashing(&self, slashing: ProposerSlashing) -> Result<(), Error>;
+	/// Submit an attester slashing to the operation pool.
+	fn submit_attester_slashing(&self, slashing: AttesterSlashing<C>) -> Result<(), Error>;
+	/// Submit a signed voluntary exit to the operation pool.
+	fn submit_voluntary_exit(&self, exit: VoluntaryExit) -> Result<(), Error>;
+	/// Submit a deposit at the given deposit index to the operation pool.
+	fn submit_deposit(&self, index: u64, deposit: Deposit) -> Result<(), Error>;
+}
+
+/// Get the signature domain for a message at epoch.
+pub fn domain(fork: &Fork, domain_type: u32, epoch: Epoch) -> u64 {
+	let fork_version = if epoch < fork.epoch {
+		fork.previous_version
+	} else {
+		fork.current_version
+	};
+
+	beacon::utils::bls_domain(domain_type, fork_version)
+}
@@ -0,0 +1,259 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::Config;
+use beacon::primitives::{Signature, ValidatorId, Epoch, Slot};
+use beacon::types::{Attestation, AttestationDataAndCustodyBit, UnsealedBeaconBlock};
+use bm_le::tree_root;
+use crypto::bls;
+use shasper_clock::{Clock, SlotTimer, Tick};
+use log::{info, warn, error, trace};
+use std::collections::HashMap;
+use core::marker::PhantomData;
+use crate::api::{self, BeaconNode, NodeInfo, ValidatorDuty};
+use crate::slashing_protection::{self, SlashingProtection};
+
+/// Validator client error.
+#[derive(Debug)]
+pub enum Error {
+	/// Beacon node API error.
+	Api(api::Error),
+	/// Signing refused by slashing protection.
+	SlashingProtection(slashing_protection::Error),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::Api(err) => write!(f, "{}", err),
+			Error::SlashingProtection(err) => write!(f, "Slashing protection: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error { }
+
+impl From<api::Error> for Error {
+	fn from(error: api::Error) -> Error {
+		Error::Api(error)
+	}
+}
+
+impl From<slashing_protection::Error> for Error {
+	fn from(error: slashing_protection::Error) -> Error {
+		Error::SlashingProtection(error)
+	}
+}
+
+/// Validator client. Tracks duties, and signs blocks and attestations
+/// produced by the beacon node.
+pub struct ValidatorClient<C: Config, N: BeaconNode<C>> {
+	node: N,
+	keys: HashMap<ValidatorId, bls::Secret>,
+	info: Option<NodeInfo>,
+	duties: Option<(Epoch, Vec<ValidatorDuty>)>,
+	protection: SlashingProtection,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config, N: BeaconNode<C>> ValidatorClient<C, N> {
+	/// Create a new validator client.
+	pub fn new(node: N, keys: HashMap<ValidatorId, bls::Secret>) -> Self {
+		Self {
+			node, keys,
+			info: None,
+			duties: None,
+			protection: SlashingProtection::new(),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Use the given slashing protection database instead of an in-memory one.
+	pub fn with_slashing_protection(mut self, protection: SlashingProtection) -> Self {
+		self.protection = protection;
+		self
+	}
+
+	/// Run the validator client, driven by the given clock.
+	pub fn run<Cl: Clock>(&mut self, clock: Cl) {
+		let info = loop {
+			match self.node.node_info() {
+				Ok(info) => break info,
+				Err(err) => {
+					warn!("Fetching node info failed: {}", err);
+					clock.sleep_until(clock.now() + core::time::Duration::new(1, 0));
+				},
+			}
+		};
+		info!("Validator client started with {} keys, genesis time {}",
+			  self.keys.len(), info.genesis_time);
+		if let Err(err) = self.protection.set_genesis_validators_root(info.genesis_validators_root) {
+			error!("Slashing protection database belongs to another chain: {}", err);
+			return
+		}
+
+		let mut timer = SlotTimer::<C, _>::new(clock, info.genesis_time);
+		self.info = Some(info);
+
+		loop {
+			for tick in timer.wait() {
+				let result = match tick {
+					Tick::Epoch(epoch) => self.update(epoch),
+					Tick::Slot(slot) => self.propose(slot),
+					Tick::Attestation(slot) => self.attest(slot),
+				};
+
+				if let Err(err) = result {
+					warn!("Validator duty at {:?} failed: {}", tick, err);
+				}
+			}
+		}
+	}
+
+	/// Refresh node info and duties for epoch.
+	pub fn update(&mut self, epoch: Epoch) -> Result<(), Error> {
+		let info = self.node.node_info()?;
+		self.protection.set_genesis_validators_root(info.genesis_validators_root)?;
+		self.info = Some(info);
+		let pubkeys = self.keys.keys().cloned().collect::<Vec<_>>();
+		let duties = self.node.duties(epoch, &pubkeys)?;
+		trace!("Updated duties for epoch {}: {:?}", epoch, duties);
+		self.duties = Some((epoch, duties));
+		Ok(())
+	}
+
+	fn duties(&mut self, slot: Slot) -> Result<(&NodeInfo, &[ValidatorDuty]), Error> {
+		let epoch = beacon::utils::epoch_of_slot::<C>(slot);
+		let outdated = match self.duties {
+			Some((duties_epoch, _)) => duties_epoch != epoch,
+			None => true,
+		};
+		if outdated || self.info.is_none() {
+			self.update(epoch)?;
+		}
+
+		match (self.info.as_ref(), self.duties.as_ref()) {
+			(Some(info), Some((_, duties))) => Ok((info, &duties[..])),
+			_ => Err(api::Error::InvalidResponse("Duties not available".to_string()).into()),
+		}
+	}
+
+	/// Propose a block at slot, if any of our validators is the proposer.
+	pub fn propose(&mut self, slot: Slot) -> Result<(), Error> {
+		let epoch = beacon::utils::epoch_of_slot::<C>(slot);
+		let (info, duties) = self.duties(slot)?;
+		let fork = info.fork.clone();
+		let proposer = match duties.iter().find(|duty| duty.block_proposal_slots.contains(&slot)) {
+			Some(duty) => duty.pubkey.clone(),
+			None => return Ok(()),
+		};
+		let seckey = match self.keys.get(&proposer) {
+			Some(seckey) => seckey,
+			None => return Ok(()),
+		};
+		info!("Proposing block at slot {} with validator {}", slot, proposer);
+
+		let randao_reveal = Signature::from_slice(&bls::Signature::new(
+			&tree_root::<C::Digest, _>(&epoch)[..],
+			api::domain(&fork, C::domain_randao(), epoch),
+			seckey
+		).as_bytes()[..]);
+
+		let mut block = self.node.produce_block(slot, randao_reveal)?;
+		if block.slot != slot || block.body.randao_reveal != randao_reveal {
+			return Err(api::Error::InvalidResponse(
+				"Produced block does not match the request".to_string()
+			).into())
+		}
+
+		let signing_root = tree_root::<C::Digest, _>(&UnsealedBeaconBlock::<C>::from(&block));
+		self.protection.check_and_record_block(&proposer, slot, signing_root)?;
+		block.signature = Signature::from_slice(&bls::Signature::new(
+			&signing_root[..],
+			api::domain(&fork, C::domain_beacon_proposer(), epoch),
+			seckey
+		).as_bytes()[..]);
+
+		Ok(self.node.submit_block(block)?)
+	}
+
+	/// Attest at slot for all of our validators assigned to it.
+	pub fn attest(&mut self, slot: Slot) -> Result<(), Error> {
+		let (info, duties) = self.duties(slot)?;
+		let fork = info.fork.clone();
+		let duties = duties.iter()
+			.filter(|duty| duty.attestation_slot == Some(slot))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		let mut datas = HashMap::new();
+		for duty in duties {
+			let (index, position, length) = match (
+				duty.attestation_committee_index,
+				duty.attestation_committee_position,
+				duty.attestation_committee_length,
+			) {
+				(Some(index), Some(position), Some(length)) => (index, position, length),
+				_ => continue,
+			};
+			let seckey = match self.keys.get(&duty.pubkey) {
+				Some(seckey) => seckey,
+				None => continue,
+			};
+
+			let data = match datas.get(&index).cloned() {
+				Some(data) => data,
+				None => {
+					let data = self.node.produce_attestation_data(slot, index)?;
+					datas.insert(index, data.clone());
+					data
+				},
+			};
+			if data.slot != slot || data.index != index {
+				return Err(api::Error::InvalidResponse(
+					"Produced attestation data does not match the request".to_string()
+				).into())
+			}
+
+			let signing_root = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+				data: data.clone(),
+				custody_bit: false,
+			});
+			self.protection.check_and_record_attestation(&duty.pubkey, &data, signing_root)?;
+			let signature = Signature::from_slice(&bls::Signature::new(
+				&signing_root[..],
+				api::domain(&fork, C::domain_beacon_attester(), data.target.epoch),
+				seckey,
+			).as_bytes()[..]);
+
+			let mut aggregation_bits = vec![false; length as usize];
+			aggregation_bits[position as usize] = true;
+			let custody_bits = vec![false; length as usize];
+
+			trace!("Attesting slot {} index {} with validator {}", slot, index, duty.pubkey);
+			if let Err(e) = self.node.submit_attestation(Attestation {
+				aggregation_bits: aggregation_bits.into(),
+				data,
+				custody_bits: custody_bits.into(),
+				signature,
+			}) {
+				warn!("Submitting attestation of validator {} failed: {}", duty.pubkey, e);
+			}
+		}
+
+		Ok(())
+	}
+}
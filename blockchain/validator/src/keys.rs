@@ -0,0 +1,106 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validator key loading.
+
+use beacon::primitives::ValidatorId;
+use crypto::{bls, keystore};
+use core::ops::Range;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Validator key in the YAML key file.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorKey {
+	/// Hex-encoded private key.
+	pub privkey: String,
+	/// Hex-encoded public key.
+	pub pubkey: String,
+}
+
+/// Decode a hex string, with or without `0x` prefix.
+pub fn string_to_bytes(string: &str) -> Result<Vec<u8>, String> {
+	let string = if string.starts_with("0x") {
+		&string[2..]
+	} else {
+		string
+	};
+
+	hex::decode(string).map_err(|e| format!("Unable to decode public or private key: {}", e))
+}
+
+/// Convert big-endian secret key bytes into a BLS secret key.
+pub fn secret_from_bytes(privkey: &[u8]) -> Result<bls::Secret, String> {
+	keystore::secret_from_bytes(privkey)
+		.map_err(|e| format!("Failed to decode bytes into secret key: {:?}", e))
+}
+
+/// Read raw big-endian secret keys from a YAML key file.
+pub fn read_yaml<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<u8>>, String> {
+	let file = File::open(path).map_err(|e| format!("Unable to open key file: {}", e))?;
+	let coll = serde_yaml::from_reader::<_, Vec<ValidatorKey>>(BufReader::new(file))
+		.map_err(|e| format!("Unable to parse key file: {}", e))?;
+
+	coll.into_iter().map(|key| string_to_bytes(&key.privkey)).collect()
+}
+
+/// Load validator keys from a YAML key file.
+pub fn load_yaml<P: AsRef<Path>>(path: P) -> Result<HashMap<ValidatorId, bls::Secret>, String> {
+	let mut keys = HashMap::new();
+	for privkey in read_yaml(path)? {
+		let secret = secret_from_bytes(&privkey)?;
+		keys.insert(keystore::validator_id(&secret), secret);
+	}
+
+	Ok(keys)
+}
+
+/// Load validator keys from a directory of EIP-2335 keystores, with password
+/// files named after the public keys in the secrets directory.
+pub fn load_keystores<P: AsRef<Path>, Q: AsRef<Path>>(
+	keystores: P,
+	secrets: Q,
+) -> Result<HashMap<ValidatorId, bls::Secret>, String> {
+	keystore::load_dir(keystores, secrets)
+		.map_err(|e| format!("Unable to load keystores: {}", e))
+}
+
+/// Parse a validator index range of the form `a..b`.
+pub fn parse_range(range: &str) -> Result<Range<u64>, String> {
+	let mut parts = range.splitn(2, "..");
+	let start = parts.next().unwrap_or_default();
+	let end = parts.next().ok_or_else(|| format!("Invalid validator range: {}", range))?;
+
+	let start = start.parse::<u64>().map_err(|e| format!("Invalid range start: {}", e))?;
+	let end = end.parse::<u64>().map_err(|e| format!("Invalid range end: {}", e))?;
+	if start > end {
+		return Err(format!("Invalid validator range: {}", range))
+	}
+
+	Ok(start..end)
+}
+
+/// Load deterministic interop validator keys for the given index range.
+pub fn load_interop(range: Range<u64>) -> HashMap<ValidatorId, bls::Secret> {
+	range.map(|index| {
+		let secret = bls::interop::secret(index);
+		(keystore::validator_id(&secret), secret)
+	}).collect()
+}
@@ -0,0 +1,304 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::Config;
+use beacon::primitives::ValidatorId;
+use crypto::bls::{self, derive};
+use crypto::keystore::{self, Keystore, Kdf};
+use shasper_validator::{ValidatorClient, keys};
+use shasper_validator::rpc::HttpClient;
+use shasper_validator::slashing_protection::SlashingProtection;
+use shasper_clock::SystemClock;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+use sha2::{Sha256, Digest};
+use log::info;
+
+fn main() {
+	pretty_env_logger::init();
+
+	let matches = App::new("Shasper validator client")
+		.arg(Arg::with_name("node")
+			 .long("node")
+			 .takes_value(true)
+			 .default_value("http://127.0.0.1:5052")
+			 .help("Validator API endpoint of the beacon node"))
+		.arg(Arg::with_name("validator-keys")
+			 .long("validator-keys")
+			 .takes_value(true)
+			 .help("Yaml validator keys"))
+		.arg(Arg::with_name("keystores")
+			 .long("keystores")
+			 .takes_value(true)
+			 .requires("secrets")
+			 .help("Directory of EIP-2335 keystores"))
+		.arg(Arg::with_name("secrets")
+			 .long("secrets")
+			 .takes_value(true)
+			 .help("Directory of keystore password files, named after the public keys"))
+		.arg(Arg::with_name("interop-validators")
+			 .long("interop-validators")
+			 .takes_value(true)
+			 .help("Range of deterministic interop validator keys to use, as in 0..16"))
+		.arg(Arg::with_name("config")
+			 .long("config")
+			 .takes_value(true)
+			 .default_value("minimal")
+			 .help("Config to use"))
+		.arg(Arg::with_name("slashing-protection-db")
+			 .long("slashing-protection-db")
+			 .takes_value(true)
+			 .default_value("slashing-protection.json")
+			 .help("Slashing protection database file"))
+		.subcommand(SubCommand::with_name("slashing-protection")
+					.about("Import or export slashing protection interchange files")
+					.subcommand(SubCommand::with_name("import")
+								.arg(Arg::with_name("FILE")
+									 .required(true)
+									 .help("EIP-3076 interchange file to import")))
+					.subcommand(SubCommand::with_name("export")
+								.arg(Arg::with_name("FILE")
+									 .required(true)
+									 .help("EIP-3076 interchange file to export to"))))
+		.subcommand(SubCommand::with_name("keystore")
+					.about("Manage EIP-2335 keystores")
+					.subcommand(SubCommand::with_name("create")
+								.about("Create keystores from existing secret keys")
+								.arg(Arg::with_name("secret")
+									 .long("secret")
+									 .takes_value(true)
+									 .multiple(true)
+									 .help("Hex-encoded secret key"))
+								.arg(Arg::with_name("password-file")
+									 .long("password-file")
+									 .takes_value(true)
+									 .required(true)
+									 .help("File containing the keystore password"))
+								.arg(Arg::with_name("output-dir")
+									 .long("output-dir")
+									 .takes_value(true)
+									 .required(true)
+									 .help("Directory to write keystores to"))
+								.arg(Arg::with_name("secrets-dir")
+									 .long("secrets-dir")
+									 .takes_value(true)
+									 .help("Directory to write password files to"))
+								.arg(Arg::with_name("kdf")
+									 .long("kdf")
+									 .takes_value(true)
+									 .possible_values(&["scrypt", "pbkdf2"])
+									 .default_value("scrypt")
+									 .help("Key derivation function"))))
+		.subcommand(SubCommand::with_name("key")
+					.about("Derive validator keys")
+					.subcommand(SubCommand::with_name("new-mnemonic")
+								.about("Generate a new BIP-39 mnemonic"))
+					.subcommand(SubCommand::with_name("derive")
+								.about("Derive EIP-2334 signing and withdrawal keys from a mnemonic")
+								.arg(Arg::with_name("mnemonic")
+									 .long("mnemonic")
+									 .takes_value(true)
+									 .required(true)
+									 .help("BIP-39 mnemonic"))
+								.arg(Arg::with_name("passphrase")
+									 .long("passphrase")
+									 .takes_value(true)
+									 .default_value("")
+									 .help("BIP-39 passphrase"))
+								.arg(Arg::with_name("index")
+									 .long("index")
+									 .takes_value(true)
+									 .default_value("0")
+									 .help("First validator index to derive"))
+								.arg(Arg::with_name("count")
+									 .long("count")
+									 .takes_value(true)
+									 .default_value("1")
+									 .help("Number of validators to derive"))
+								.arg(Arg::with_name("password-file")
+									 .long("password-file")
+									 .takes_value(true)
+									 .requires("output-dir")
+									 .help("File containing the keystore password"))
+								.arg(Arg::with_name("output-dir")
+									 .long("output-dir")
+									 .takes_value(true)
+									 .requires("password-file")
+									 .help("Directory to write signing keystores to"))
+								.arg(Arg::with_name("secrets-dir")
+									 .long("secrets-dir")
+									 .takes_value(true)
+									 .help("Directory to write password files to"))
+								.arg(Arg::with_name("kdf")
+									 .long("kdf")
+									 .takes_value(true)
+									 .possible_values(&["scrypt", "pbkdf2"])
+									 .default_value("scrypt")
+									 .help("Key derivation function"))))
+		.get_matches();
+
+	if let Some(key_matches) = matches.subcommand_matches("key") {
+		if key_matches.subcommand_matches("new-mnemonic").is_some() {
+			println!("{}", derive::generate_mnemonic());
+		} else if let Some(derive_matches) = key_matches.subcommand_matches("derive") {
+			derive_keys(derive_matches);
+		} else {
+			panic!("Unknown key subcommand");
+		}
+		return
+	}
+
+	if let Some(keystore_matches) = matches.subcommand_matches("keystore") {
+		if let Some(create_matches) = keystore_matches.subcommand_matches("create") {
+			create_keystores(&raw_secrets(&matches, create_matches), create_matches);
+		} else {
+			panic!("Unknown keystore subcommand");
+		}
+		return
+	}
+
+	let protection_path = matches.value_of("slashing-protection-db")
+		.expect("Slashing protection db has default value; qed");
+	if let Some(matches) = matches.subcommand_matches("slashing-protection") {
+		let mut protection = SlashingProtection::open(protection_path).unwrap();
+
+		if let Some(matches) = matches.subcommand_matches("import") {
+			let file = File::open(matches.value_of("FILE").expect("File is required; qed")).unwrap();
+			protection.import(serde_json::from_reader(BufReader::new(file)).unwrap()).unwrap();
+		} else if let Some(matches) = matches.subcommand_matches("export") {
+			let file = File::create(matches.value_of("FILE").expect("File is required; qed")).unwrap();
+			serde_json::to_writer_pretty(file, &protection.export()).unwrap();
+		} else {
+			panic!("Unknown slashing protection subcommand");
+		}
+		return
+	}
+
+	let config_name = matches.value_of("config").expect("Config has default value; qed");
+	info!("Using chain config: {}", config_name);
+	match config_name {
+		"minimal" => main_with_config::<beacon::MinimalConfig>(matches),
+		"mainnet" => main_with_config::<beacon::MainnetConfig>(matches),
+		"sapphire" => main_with_config::<beacon::SapphireConfig>(matches),
+		e => panic!("Unknown config name: {:?}", e),
+	}
+}
+
+fn raw_secrets(matches: &ArgMatches, create_matches: &ArgMatches) -> Vec<(Vec<u8>, String)> {
+	let mut secrets = create_matches.values_of("secret")
+		.map(|values| values.map(|v| keys::string_to_bytes(v).unwrap()).collect::<Vec<_>>())
+		.unwrap_or_default();
+
+	if let Some(validator_keys) = matches.value_of("validator-keys") {
+		secrets.extend(keys::read_yaml(validator_keys).unwrap());
+	}
+
+	secrets.into_iter().map(|secret| (secret, String::new())).collect()
+}
+
+fn derive_keys(matches: &ArgMatches) {
+	let seed = derive::mnemonic_to_seed(
+		matches.value_of("mnemonic").expect("Mnemonic is required; qed"),
+		matches.value_of("passphrase").expect("Passphrase has default value; qed"),
+	).unwrap();
+	let index = u32::from_str(matches.value_of("index").expect("Index has default value; qed")).unwrap();
+	let count = u32::from_str(matches.value_of("count").expect("Count has default value; qed")).unwrap();
+
+	let mut secrets = Vec::new();
+	for index in index..(index + count) {
+		let signing_path = derive::signing_key_path(index);
+		let signing = derive::derive_path(&seed, &signing_path).unwrap();
+		let withdrawal = derive::derive_path(&seed, &derive::withdrawal_key_path(index)).unwrap();
+		let withdrawal_pubkey = keystore::validator_id(&withdrawal.to_secret());
+
+		let mut withdrawal_credentials = Sha256::digest(&withdrawal_pubkey[..]).to_vec();
+		withdrawal_credentials[0] = 0;
+
+		println!("Validator {}", index);
+		println!("  signing pubkey: 0x{}", hex::encode(&keystore::validator_id(&signing.to_secret())[..]));
+		println!("  withdrawal pubkey: 0x{}", hex::encode(&withdrawal_pubkey[..]));
+		println!("  withdrawal credentials: 0x{}", hex::encode(&withdrawal_credentials));
+
+		secrets.push((signing.as_bytes().to_vec(), signing_path));
+	}
+
+	if matches.is_present("output-dir") {
+		create_keystores(&secrets, matches);
+	}
+}
+
+fn create_keystores(secrets: &[(Vec<u8>, String)], matches: &ArgMatches) {
+	let password = fs::read_to_string(
+		matches.value_of("password-file").expect("Password file is required; qed")
+	).unwrap();
+	let password = password.trim_end_matches(|c| c == '\n' || c == '\r');
+	let output_dir = Path::new(matches.value_of("output-dir").expect("Output dir is required; qed"));
+	fs::create_dir_all(output_dir).unwrap();
+
+	for (secret, path) in secrets {
+		let mut padded = vec![0; keystore::SECRET_LENGTH.saturating_sub(secret.len())];
+		padded.extend_from_slice(secret);
+
+		let kdf = match matches.value_of("kdf") {
+			Some("pbkdf2") => Kdf::pbkdf2(),
+			_ => Kdf::scrypt(),
+		};
+		let keystore = Keystore::encrypt(&padded, password, path, kdf).unwrap();
+
+		let file = output_dir.join(format!("keystore-{}.json", keystore.pubkey));
+		fs::write(&file, keystore.to_json()).unwrap();
+		info!("Created keystore {}", file.display());
+
+		if let Some(secrets_dir) = matches.value_of("secrets-dir") {
+			let secrets_dir = Path::new(secrets_dir);
+			fs::create_dir_all(secrets_dir).unwrap();
+			fs::write(secrets_dir.join(keystore.password_file_name()), password).unwrap();
+		}
+	}
+}
+
+fn load_keys(matches: &ArgMatches) -> HashMap<ValidatorId, bls::Secret> {
+	if let Some(range) = matches.value_of("interop-validators") {
+		keys::load_interop(keys::parse_range(range).unwrap())
+	} else if let Some(keystores) = matches.value_of("keystores") {
+		keys::load_keystores(
+			keystores,
+			matches.value_of("secrets").expect("Secrets is required by keystores; qed"),
+		).unwrap()
+	} else {
+		keys::load_yaml(
+			matches.value_of("validator-keys").expect("Validator keys, keystores or interop validators are required")
+		).unwrap()
+	}
+}
+
+fn main_with_config<C: Config>(matches: ArgMatches) {
+	let keys = load_keys(&matches);
+	let node = HttpClient::<C>::new(
+		matches.value_of("node").expect("Node has default value; qed")
+	);
+	let protection = SlashingProtection::open(
+		matches.value_of("slashing-protection-db").expect("Slashing protection db has default value; qed")
+	).unwrap();
+
+	ValidatorClient::new(node, keys)
+		.with_slashing_protection(protection)
+		.run(SystemClock);
+}
@@ -0,0 +1,139 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! JSON-RPC over HTTP client for the validator API.
+
+use beacon::Config;
+use beacon::primitives::{Signature, ValidatorId, Epoch, Slot};
+use beacon::types::{
+	AttestationData, Attestation, BeaconBlock, ProposerSlashing, AttesterSlashing, VoluntaryExit,
+	Deposit,
+};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use core::marker::PhantomData;
+use crate::api::*;
+
+#[derive(Serialize)]
+struct Request<'a, P: Serialize> {
+	jsonrpc: &'static str,
+	method: &'a str,
+	params: P,
+	id: usize,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+	message: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+	#[serde(default)]
+	result: serde_json::Value,
+	error: Option<ResponseError>,
+}
+
+/// Validator API client talking to a beacon node over HTTP.
+pub struct HttpClient<C: Config> {
+	url: String,
+	client: reqwest::Client,
+	next_id: AtomicUsize,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config> HttpClient<C> {
+	/// Create a new client for the node at url.
+	pub fn new(url: &str) -> Self {
+		Self {
+			url: url.to_string(),
+			client: reqwest::Client::new(),
+			next_id: AtomicUsize::new(0),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Call a method on the node.
+	pub fn call<P: Serialize, R: DeserializeOwned>(
+		&self,
+		method: &str,
+		params: P
+	) -> Result<R, Error> {
+		let request = Request {
+			jsonrpc: "2.0",
+			method,
+			params,
+			id: self.next_id.fetch_add(1, Ordering::SeqCst),
+		};
+
+		let response: Response = self.client.post(&self.url)
+			.json(&request)
+			.send()
+			.and_then(|mut response| response.json())
+			.map_err(|e| Error::Transport(format!("{}", e)))?;
+
+		if let Some(error) = response.error {
+			return Err(Error::Node(error.message))
+		}
+
+		// Methods returning unit send a `null` result, which `R` decides how
+		// to handle.
+		serde_json::from_value(response.result)
+			.map_err(|e| Error::InvalidResponse(format!("{}", e)))
+	}
+}
+
+impl<C: Config> BeaconNode<C> for HttpClient<C> {
+	fn node_info(&self) -> Result<NodeInfo, Error> {
+		self.call(METHOD_NODE_INFO, ())
+	}
+
+	fn duties(&self, epoch: Epoch, pubkeys: &[ValidatorId]) -> Result<Vec<ValidatorDuty>, Error> {
+		self.call(METHOD_DUTIES, (epoch, pubkeys))
+	}
+
+	fn produce_block(&self, slot: Slot, randao_reveal: Signature) -> Result<BeaconBlock<C>, Error> {
+		self.call(METHOD_PRODUCE_BLOCK, (slot, randao_reveal))
+	}
+
+	fn submit_block(&self, block: BeaconBlock<C>) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_BLOCK, (block,))
+	}
+
+	fn produce_attestation_data(&self, slot: Slot, index: u64) -> Result<AttestationData, Error> {
+		self.call(METHOD_PRODUCE_ATTESTATION_DATA, (slot, index))
+	}
+
+	fn submit_attestation(&self, attestation: Attestation<C>) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_ATTESTATION, (attestation,))
+	}
+
+	fn submit_proposer_slashing(&self, slashing: ProposerSlashing) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_PROPOSER_SLASHING, (slashing,))
+	}
+
+	fn submit_attester_slashing(&self, slashing: AttesterSlashing<C>) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_ATTESTER_SLASHING, (slashing,))
+	}
+
+	fn submit_voluntary_exit(&self, exit: VoluntaryExit) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_VOLUNTARY_EXIT, (exit,))
+	}
+
+	fn submit_deposit(&self, index: u64, deposit: Deposit) -> Result<(), Error> {
+		self.call(METHOD_SUBMIT_DEPOSIT, (index, deposit))
+	}
+}
@@ -0,0 +1,557 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slashing protection database, with EIP-3076 interchange import and export.
+//!
+//! The database is persisted as an interchange snapshot, plus an append-only
+//! journal of records signed since the snapshot was written. The journal is
+//! folded into the snapshot, which is replaced atomically, when the database
+//! is opened or an interchange is imported.
+//!
+//! An open database is held exclusively through a lock file next to it, so
+//! that two validator clients can never sign from the same records. A lock
+//! file left behind by a crashed client has to be removed by hand.
+
+use beacon::primitives::{H256, ValidatorId, Epoch, Slot};
+use beacon::types::{AttestationData, Checkpoint};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Interchange format version supported.
+pub const INTERCHANGE_FORMAT_VERSION: u64 = 5;
+
+/// Slashing protection error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// A different block was already signed at the slot.
+	DoubleProposal(Slot),
+	/// A different attestation was already signed for the target epoch.
+	DoubleVote(Epoch),
+	/// The attestation surrounds, or is surrounded by, a signed attestation.
+	SurroundVote {
+		/// Source epoch of the conflicting attestation.
+		source_epoch: Epoch,
+		/// Target epoch of the conflicting attestation.
+		target_epoch: Epoch,
+	},
+	/// Genesis validators root does not match the database.
+	GenesisMismatch,
+	/// The database is already opened by another instance.
+	Locked(PathBuf),
+	/// Unsupported interchange format version.
+	UnsupportedVersion(u64),
+	/// IO error.
+	IO(String),
+	/// Invalid interchange file.
+	InvalidFormat(String),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+impl std::error::Error for Error { }
+
+mod quoted {
+	use serde::{Serializer, Deserializer, Deserialize, de::Error};
+
+	pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&value.to_string())
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+		let value = String::deserialize(deserializer)?;
+		value.parse().map_err(|e| D::Error::custom(format!("{}", e)))
+	}
+}
+
+/// Interchange metadata.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct InterchangeMetadata {
+	/// Format version.
+	#[serde(with = "quoted")]
+	pub interchange_format_version: u64,
+	/// Genesis validators root of the chain, the hash tree root of the
+	/// genesis state validator registry.
+	pub genesis_validators_root: H256,
+}
+
+/// Signed block record.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SignedBlock {
+	/// Slot of the block.
+	#[serde(with = "quoted")]
+	pub slot: Slot,
+	/// Signing root of the block.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub signing_root: Option<H256>,
+}
+
+/// Signed attestation record.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SignedAttestation {
+	/// Source epoch of the attestation.
+	#[serde(with = "quoted")]
+	pub source_epoch: Epoch,
+	/// Target epoch of the attestation.
+	#[serde(with = "quoted")]
+	pub target_epoch: Epoch,
+	/// Signing root of the attestation.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub signing_root: Option<H256>,
+}
+
+impl SignedAttestation {
+	/// Attestation data standing in for the signed attestation. Only
+	/// epochs and signing root are recorded, and the signing root
+	/// identifies the full data, so it takes the place of the block root.
+	fn surrogate(&self) -> AttestationData {
+		AttestationData {
+			beacon_block_root: self.signing_root.unwrap_or_default(),
+			source: Checkpoint { epoch: self.source_epoch, root: Default::default() },
+			target: Checkpoint { epoch: self.target_epoch, root: Default::default() },
+			..Default::default()
+		}
+	}
+}
+
+/// Interchange records of a single validator.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct InterchangeData {
+	/// Validator public key.
+	pub pubkey: ValidatorId,
+	/// Signed blocks.
+	pub signed_blocks: Vec<SignedBlock>,
+	/// Signed attestations.
+	pub signed_attestations: Vec<SignedAttestation>,
+}
+
+/// EIP-3076 slashing protection interchange.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Interchange {
+	/// Metadata.
+	pub metadata: InterchangeMetadata,
+	/// Per-validator records.
+	pub data: Vec<InterchangeData>,
+}
+
+/// Journal entry appended for each new record.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum JournalEntry {
+	GenesisValidatorsRoot(H256),
+	Block { pubkey: ValidatorId, block: SignedBlock },
+	Attestation { pubkey: ValidatorId, attestation: SignedAttestation },
+}
+
+fn io_error(err: std::io::Error) -> Error {
+	Error::IO(format!("{}", err))
+}
+
+fn journal_path(path: &Path) -> PathBuf {
+	path.with_extension("journal")
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+	path.with_extension("lock")
+}
+
+/// Exclusive lock of a database, released when dropped.
+struct Lock(PathBuf);
+
+impl Lock {
+	fn acquire(path: &Path) -> Result<Self, Error> {
+		let lock = lock_path(path);
+		let mut file = match OpenOptions::new().write(true).create_new(true).open(&lock) {
+			Ok(file) => file,
+			Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists =>
+				return Err(Error::Locked(lock)),
+			Err(e) => return Err(io_error(e)),
+		};
+		let lock = Self(lock);
+		writeln!(file, "{}", std::process::id()).map_err(io_error)?;
+		file.sync_all().map_err(io_error)?;
+		Ok(lock)
+	}
+}
+
+impl Drop for Lock {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.0);
+	}
+}
+
+#[derive(Clone, Default, Debug)]
+struct Records {
+	blocks: Vec<SignedBlock>,
+	attestations: Vec<SignedAttestation>,
+}
+
+/// Slashing protection database, keyed by validator public key.
+pub struct SlashingProtection {
+	path: Option<PathBuf>,
+	lock: Option<Lock>,
+	journal: Option<File>,
+	genesis_validators_root: Option<H256>,
+	records: HashMap<ValidatorId, Records>,
+}
+
+impl SlashingProtection {
+	/// Create a new in-memory database.
+	pub fn new() -> Self {
+		Self {
+			path: None,
+			lock: None,
+			journal: None,
+			genesis_validators_root: None,
+			records: HashMap::new(),
+		}
+	}
+
+	/// Open a database persisted at path, creating it if it does not exist.
+	/// Fails if the database is already opened by another instance.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let mut db = Self::new();
+		db.lock = Some(Lock::acquire(path)?);
+		if path.exists() {
+			let file = File::open(path).map_err(io_error)?;
+			let interchange: Interchange = serde_json::from_reader(BufReader::new(file))
+				.map_err(|e| Error::InvalidFormat(format!("{}", e)))?;
+			db.merge(interchange)?;
+		}
+
+		let journal = journal_path(path);
+		if journal.exists() {
+			let file = File::open(&journal).map_err(io_error)?;
+			let lines = BufReader::new(file).lines().collect::<Result<Vec<_>, _>>()
+				.map_err(io_error)?;
+			for (i, line) in lines.iter().enumerate() {
+				match serde_json::from_str(line) {
+					Ok(entry) => db.apply(entry)?,
+					// The last entry may be partially written if we crashed
+					// while appending it. It was never used to sign anything.
+					Err(_) if i + 1 == lines.len() => (),
+					Err(e) => return Err(Error::InvalidFormat(format!("{}", e))),
+				}
+			}
+		}
+
+		db.path = Some(path.to_path_buf());
+		db.compact()?;
+		Ok(db)
+	}
+
+	/// Set the genesis validators root, failing if the database belongs to
+	/// another chain.
+	pub fn set_genesis_validators_root(&mut self, root: H256) -> Result<(), Error> {
+		match self.genesis_validators_root {
+			Some(existing) if existing != root => Err(Error::GenesisMismatch),
+			Some(_) => Ok(()),
+			None => {
+				self.append(&JournalEntry::GenesisValidatorsRoot(root))?;
+				self.genesis_validators_root = Some(root);
+				Ok(())
+			},
+		}
+	}
+
+	/// Check whether signing a block is safe, and record it if so.
+	pub fn check_and_record_block(
+		&mut self,
+		pubkey: &ValidatorId,
+		slot: Slot,
+		signing_root: H256,
+	) -> Result<(), Error> {
+		if let Some(records) = self.records.get(pubkey) {
+			for block in &records.blocks {
+				if block.slot == slot {
+					if block.signing_root == Some(signing_root) {
+						return Ok(())
+					}
+					return Err(Error::DoubleProposal(slot))
+				}
+			}
+		}
+
+		let block = SignedBlock { slot, signing_root: Some(signing_root) };
+		self.append(&JournalEntry::Block { pubkey: pubkey.clone(), block: block.clone() })?;
+		self.records.entry(pubkey.clone()).or_default().blocks.push(block);
+		Ok(())
+	}
+
+	/// Check whether signing an attestation is safe, and record it if so.
+	pub fn check_and_record_attestation(
+		&mut self,
+		pubkey: &ValidatorId,
+		data: &AttestationData,
+		signing_root: H256,
+	) -> Result<(), Error> {
+		let new = SignedAttestation {
+			source_epoch: data.source.epoch,
+			target_epoch: data.target.epoch,
+			signing_root: Some(signing_root),
+		};
+		let surrogate = new.surrogate();
+
+		let existing_attestations = self.records.get(pubkey)
+			.map(|records| &records.attestations[..])
+			.unwrap_or(&[]);
+		for existing in existing_attestations {
+			if existing == &new {
+				return Ok(())
+			}
+
+			let existing_surrogate = existing.surrogate();
+			if surrogate.is_slashable(&existing_surrogate) ||
+				existing_surrogate.is_slashable(&surrogate)
+			{
+				if existing.target_epoch == new.target_epoch {
+					return Err(Error::DoubleVote(new.target_epoch))
+				}
+				return Err(Error::SurroundVote {
+					source_epoch: existing.source_epoch,
+					target_epoch: existing.target_epoch,
+				})
+			}
+		}
+
+		self.append(&JournalEntry::Attestation { pubkey: pubkey.clone(), attestation: new.clone() })?;
+		self.records.entry(pubkey.clone()).or_default().attestations.push(new);
+		Ok(())
+	}
+
+	/// Import an interchange, merging its records into the database.
+	pub fn import(&mut self, interchange: Interchange) -> Result<(), Error> {
+		self.merge(interchange)?;
+		self.compact()
+	}
+
+	fn merge(&mut self, interchange: Interchange) -> Result<(), Error> {
+		if interchange.metadata.interchange_format_version != INTERCHANGE_FORMAT_VERSION {
+			return Err(Error::UnsupportedVersion(interchange.metadata.interchange_format_version))
+		}
+		match self.genesis_validators_root {
+			Some(existing) if existing != interchange.metadata.genesis_validators_root =>
+				return Err(Error::GenesisMismatch),
+			_ => self.genesis_validators_root = Some(interchange.metadata.genesis_validators_root),
+		}
+
+		for data in interchange.data {
+			let records = self.records.entry(data.pubkey).or_default();
+			for block in data.signed_blocks {
+				if !records.blocks.contains(&block) {
+					records.blocks.push(block);
+				}
+			}
+			for attestation in data.signed_attestations {
+				if !records.attestations.contains(&attestation) {
+					records.attestations.push(attestation);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn apply(&mut self, entry: JournalEntry) -> Result<(), Error> {
+		match entry {
+			JournalEntry::GenesisValidatorsRoot(root) => match self.genesis_validators_root {
+				Some(existing) if existing != root => return Err(Error::GenesisMismatch),
+				_ => self.genesis_validators_root = Some(root),
+			},
+			JournalEntry::Block { pubkey, block } => {
+				let records = self.records.entry(pubkey).or_default();
+				if !records.blocks.contains(&block) {
+					records.blocks.push(block);
+				}
+			},
+			JournalEntry::Attestation { pubkey, attestation } => {
+				let records = self.records.entry(pubkey).or_default();
+				if !records.attestations.contains(&attestation) {
+					records.attestations.push(attestation);
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// Export the database as an interchange.
+	pub fn export(&self) -> Interchange {
+		let mut data = self.records.iter()
+			.map(|(pubkey, records)| InterchangeData {
+				pubkey: pubkey.clone(),
+				signed_blocks: records.blocks.clone(),
+				signed_attestations: records.attestations.clone(),
+			})
+			.collect::<Vec<_>>();
+		data.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+
+		Interchange {
+			metadata: InterchangeMetadata {
+				interchange_format_version: INTERCHANGE_FORMAT_VERSION,
+				genesis_validators_root: self.genesis_validators_root.unwrap_or_default(),
+			},
+			data,
+		}
+	}
+
+	/// Durably append an entry to the journal, before the record is used.
+	fn append(&mut self, entry: &JournalEntry) -> Result<(), Error> {
+		let journal = match self.journal.as_mut() {
+			Some(journal) => journal,
+			None => return Ok(()),
+		};
+
+		let mut line = serde_json::to_vec(entry)
+			.expect("Journal entries are always serializable; qed");
+		line.push(b'\n');
+		journal.write_all(&line).map_err(io_error)?;
+		journal.sync_data().map_err(io_error)
+	}
+
+	/// Write the snapshot atomically, and start a new empty journal.
+	fn compact(&mut self) -> Result<(), Error> {
+		let path = match self.path.as_ref() {
+			Some(path) => path,
+			None => return Ok(()),
+		};
+
+		let tmp = path.with_extension("tmp");
+		let mut file = File::create(&tmp).map_err(io_error)?;
+		serde_json::to_writer_pretty(&mut file, &self.export())
+			.map_err(|e| Error::IO(format!("{}", e)))?;
+		file.sync_all().map_err(io_error)?;
+		fs::rename(&tmp, path).map_err(io_error)?;
+
+		// Entries still in the old journal are already in the snapshot, and
+		// replaying them again is harmless, so a crash here loses nothing.
+		let journal = journal_path(path);
+		File::create(&journal).map_err(io_error)?;
+		self.journal = Some(OpenOptions::new().append(true).open(&journal).map_err(io_error)?);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn data(source: Epoch, target: Epoch) -> AttestationData {
+		AttestationData {
+			source: Checkpoint { epoch: source, root: Default::default() },
+			target: Checkpoint { epoch: target, root: Default::default() },
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn refuses_double_proposal() {
+		let mut db = SlashingProtection::new();
+		let pubkey = ValidatorId::default();
+
+		assert_eq!(db.check_and_record_block(&pubkey, 1, H256::repeat_byte(1)), Ok(()));
+		assert_eq!(db.check_and_record_block(&pubkey, 1, H256::repeat_byte(1)), Ok(()));
+		assert_eq!(db.check_and_record_block(&pubkey, 1, H256::repeat_byte(2)),
+				   Err(Error::DoubleProposal(1)));
+		assert_eq!(db.check_and_record_block(&pubkey, 2, H256::repeat_byte(2)), Ok(()));
+	}
+
+	#[test]
+	fn refuses_double_and_surround_votes() {
+		let mut db = SlashingProtection::new();
+		let pubkey = ValidatorId::default();
+
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(1)), Ok(()));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(1)), Ok(()));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(2)),
+				   Err(Error::DoubleVote(3)));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(1, 4), H256::repeat_byte(3)),
+				   Err(Error::SurroundVote { source_epoch: 2, target_epoch: 3 }));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(5, 8), H256::repeat_byte(4)), Ok(()));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(6, 7), H256::repeat_byte(5)),
+				   Err(Error::SurroundVote { source_epoch: 5, target_epoch: 8 }));
+	}
+
+	#[test]
+	fn interchange_roundtrip() {
+		let mut db = SlashingProtection::new();
+		let pubkey = ValidatorId::repeat_byte(1);
+		db.set_genesis_validators_root(H256::repeat_byte(9)).unwrap();
+		db.check_and_record_block(&pubkey, 10, H256::repeat_byte(1)).unwrap();
+		db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(2)).unwrap();
+
+		let json = serde_json::to_string(&db.export()).unwrap();
+		assert!(json.contains("\"slot\":\"10\""));
+
+		let mut imported = SlashingProtection::new();
+		imported.import(serde_json::from_str(&json).unwrap()).unwrap();
+		assert_eq!(imported.export(), db.export());
+		assert_eq!(imported.check_and_record_block(&pubkey, 10, H256::repeat_byte(3)),
+				   Err(Error::DoubleProposal(10)));
+
+		let mut other = SlashingProtection::new();
+		other.set_genesis_validators_root(H256::repeat_byte(8)).unwrap();
+		assert_eq!(other.import(serde_json::from_str(&json).unwrap()), Err(Error::GenesisMismatch));
+	}
+
+	#[test]
+	fn persists_through_journal() {
+		let dir = std::env::temp_dir()
+			.join(format!("shasper-slashing-protection-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("slashing-protection.json");
+		let pubkey = ValidatorId::repeat_byte(1);
+
+		{
+			let mut db = SlashingProtection::open(&path).unwrap();
+			db.set_genesis_validators_root(H256::repeat_byte(9)).unwrap();
+			db.check_and_record_block(&pubkey, 10, H256::repeat_byte(1)).unwrap();
+			db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(2)).unwrap();
+		}
+		assert!(fs::metadata(journal_path(&path)).unwrap().len() > 0);
+
+		let mut db = SlashingProtection::open(&path).unwrap();
+		assert_eq!(fs::metadata(journal_path(&path)).unwrap().len(), 0);
+		assert_eq!(db.set_genesis_validators_root(H256::repeat_byte(8)), Err(Error::GenesisMismatch));
+		assert_eq!(db.check_and_record_block(&pubkey, 10, H256::repeat_byte(3)),
+				   Err(Error::DoubleProposal(10)));
+		assert_eq!(db.check_and_record_attestation(&pubkey, &data(2, 3), H256::repeat_byte(4)),
+				   Err(Error::DoubleVote(3)));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn refuses_second_open() {
+		let dir = std::env::temp_dir()
+			.join(format!("shasper-slashing-protection-lock-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("slashing-protection.json");
+
+		let db = SlashingProtection::open(&path).unwrap();
+		assert_eq!(SlashingProtection::open(&path).err(), Some(Error::Locked(lock_path(&path))));
+		drop(db);
+		assert!(SlashingProtection::open(&path).is_ok());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,258 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! EIP-2333 key derivation, EIP-2334 paths and BIP-39 mnemonic seeds.
+
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Digest};
+use num_bigint::BigUint;
+use bip39::{Mnemonic, MnemonicType, Language, Seed};
+use crate::bls;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// BLS12-381 curve order.
+pub(crate) const CURVE_ORDER: &str = "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+/// Salt of the HKDF_mod_r key generation.
+const KEYGEN_SALT: &[u8] = b"BLS-SIG-KEYGEN-SALT-";
+/// Purpose in EIP-2334 paths.
+pub const PURPOSE: u32 = 12381;
+/// Eth2 coin type in EIP-2334 paths.
+pub const COIN_TYPE: u32 = 3600;
+
+/// Key derivation error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// Seed is shorter than 32 bytes.
+	InvalidSeed,
+	/// Derivation path is invalid.
+	InvalidPath(String),
+	/// Mnemonic is invalid.
+	InvalidMnemonic(String),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+impl std::error::Error for Error { }
+
+/// Big-endian 32-byte secret key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+	/// Secret key bytes, big-endian.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0[..]
+	}
+
+	/// Convert into a BLS secret key.
+	pub fn to_secret(&self) -> bls::Secret {
+		let mut bytes = vec![0; 16];
+		bytes.extend_from_slice(&self.0[..]);
+		bls::Secret::from_bytes(&bytes)
+			.expect("Derived keys are always less than curve order; qed")
+	}
+
+	pub(crate) fn from_uint(value: &BigUint) -> Self {
+		SecretKey(i2osp_32(value))
+	}
+
+	fn to_uint(&self) -> BigUint {
+		BigUint::from_bytes_be(&self.0[..])
+	}
+}
+
+impl std::fmt::Debug for SecretKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "SecretKey(..)")
+	}
+}
+
+fn i2osp_32(value: &BigUint) -> [u8; 32] {
+	let bytes = value.to_bytes_be();
+	let mut ret = [0u8; 32];
+	ret[(32 - bytes.len())..].copy_from_slice(&bytes);
+	ret
+}
+
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+	let mut mac = HmacSha256::new_varkey(salt).expect("HMAC accepts keys of any size; qed");
+	mac.input(ikm);
+	mac.result().code().to_vec()
+}
+
+fn hkdf_expand(prk: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+	// RFC 5869 allows at most 255 blocks of output.
+	let blocks = (length + 31) / 32;
+	assert!(blocks <= 255, "HKDF output length is at most 255 blocks; qed");
+
+	let mut okm = Vec::with_capacity(blocks * 32);
+	let mut previous = Vec::new();
+
+	for counter in 1..=(blocks as u8) {
+		let mut mac = HmacSha256::new_varkey(prk).expect("HMAC accepts keys of any size; qed");
+		mac.input(&previous);
+		mac.input(info);
+		mac.input(&[counter]);
+		previous = mac.result().code().to_vec();
+		okm.extend_from_slice(&previous);
+	}
+
+	okm.truncate(length);
+	okm
+}
+
+fn ikm_to_lamport_sk(ikm: &[u8], salt: &[u8]) -> Vec<u8> {
+	let prk = hkdf_extract(salt, ikm);
+	hkdf_expand(&prk, b"", 32 * 255)
+}
+
+fn parent_sk_to_lamport_pk(parent_sk: &SecretKey, index: u32) -> Vec<u8> {
+	let salt = index.to_be_bytes();
+	let ikm = parent_sk.0;
+	let not_ikm = ikm.iter().map(|b| !b).collect::<Vec<_>>();
+
+	let lamport_0 = ikm_to_lamport_sk(&ikm[..], &salt[..]);
+	let lamport_1 = ikm_to_lamport_sk(&not_ikm[..], &salt[..]);
+
+	let mut lamport_pk = Sha256::new();
+	for chunk in lamport_0.chunks(32).chain(lamport_1.chunks(32)) {
+		lamport_pk.input(&Sha256::digest(chunk)[..]);
+	}
+	lamport_pk.result().to_vec()
+}
+
+fn hkdf_mod_r(ikm: &[u8]) -> SecretKey {
+	let order = CURVE_ORDER.parse::<BigUint>().expect("Curve order is valid; qed");
+	let mut salt = KEYGEN_SALT.to_vec();
+	let mut ikm_postfixed = ikm.to_vec();
+	ikm_postfixed.push(0);
+
+	loop {
+		salt = Sha256::digest(&salt).to_vec();
+		let prk = hkdf_extract(&salt, &ikm_postfixed);
+		let okm = hkdf_expand(&prk, &[0, 48], 48);
+		let sk = BigUint::from_bytes_be(&okm) % &order;
+
+		if sk != BigUint::from(0u32) {
+			return SecretKey::from_uint(&sk)
+		}
+	}
+}
+
+/// Derive the master secret key from a seed.
+pub fn derive_master_sk(seed: &[u8]) -> Result<SecretKey, Error> {
+	if seed.len() < 32 {
+		return Err(Error::InvalidSeed)
+	}
+
+	Ok(hkdf_mod_r(seed))
+}
+
+/// Derive a child secret key from its parent.
+pub fn derive_child_sk(parent_sk: &SecretKey, index: u32) -> SecretKey {
+	hkdf_mod_r(&parent_sk_to_lamport_pk(parent_sk, index))
+}
+
+/// Parse an EIP-2334 path, such as `m/12381/3600/0/0/0`.
+pub fn parse_path(path: &str) -> Result<Vec<u32>, Error> {
+	let mut components = path.split('/');
+	if components.next() != Some("m") {
+		return Err(Error::InvalidPath(format!("Path must start with m: {}", path)))
+	}
+
+	let indices = components
+		.map(|c| c.parse::<u32>().map_err(|_| Error::InvalidPath(format!("Invalid index {:?}", c))))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	match indices.first() {
+		Some(&PURPOSE) => Ok(indices),
+		_ => Err(Error::InvalidPath(format!("Path purpose must be {}: {}", PURPOSE, path))),
+	}
+}
+
+/// Derive the secret key at an EIP-2334 path from a seed.
+pub fn derive_path(seed: &[u8], path: &str) -> Result<SecretKey, Error> {
+	let mut sk = derive_master_sk(seed)?;
+	for index in parse_path(path)? {
+		sk = derive_child_sk(&sk, index);
+	}
+	Ok(sk)
+}
+
+/// EIP-2334 withdrawal key path of a validator.
+pub fn withdrawal_key_path(index: u32) -> String {
+	format!("m/{}/{}/{}/0", PURPOSE, COIN_TYPE, index)
+}
+
+/// EIP-2334 signing key path of a validator.
+pub fn signing_key_path(index: u32) -> String {
+	format!("m/{}/{}/{}/0/0", PURPOSE, COIN_TYPE, index)
+}
+
+/// Generate a new 24-word English mnemonic.
+pub fn generate_mnemonic() -> String {
+	Mnemonic::new(MnemonicType::Words24, Language::English).phrase().to_string()
+}
+
+/// Convert a BIP-39 English mnemonic and passphrase into a seed.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, Error> {
+	let mnemonic = Mnemonic::from_phrase(phrase, Language::English)
+		.map_err(|e| Error::InvalidMnemonic(format!("{}", e)))?;
+	Ok(Seed::new(&mnemonic, passphrase).as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SEED: &str = "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04";
+
+	#[test]
+	fn mnemonic_seed() {
+		let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+		assert_eq!(mnemonic_to_seed(phrase, "TREZOR").unwrap(), hex::decode(SEED).unwrap());
+		assert!(mnemonic_to_seed("abandon about", "").is_err());
+	}
+
+	#[test]
+	fn eip2333_test_vector() {
+		let master = derive_master_sk(&hex::decode(SEED).unwrap()).unwrap();
+		assert_eq!(
+			master.to_uint().to_string(),
+			"6083874454709270928345386274498605044986640685124978867557563392430687146096"
+		);
+
+		let child = derive_child_sk(&master, 0);
+		assert_eq!(
+			child.to_uint().to_string(),
+			"20397789859736650942317412262472558107875392172444076792671091975210932703118"
+		);
+	}
+
+	#[test]
+	fn parses_paths() {
+		assert_eq!(parse_path(&signing_key_path(7)).unwrap(), vec![12381, 3600, 7, 0, 0]);
+		assert_eq!(parse_path("m/12381/60/0/0").unwrap(), vec![12381, 60, 0, 0]);
+		assert!(parse_path("12381/3600/0").is_err());
+		assert!(parse_path("m/44/60/0").is_err());
+		assert!(parse_path("m/12381/x").is_err());
+	}
+}
@@ -0,0 +1,54 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic insecure keys used for interop testnets.
+
+use sha2::{Sha256, Digest};
+use num_bigint::BigUint;
+use crate::bls::{self, derive::{SecretKey, CURVE_ORDER}};
+
+/// Interop secret key of the validator at index.
+pub fn secret_key(index: u64) -> SecretKey {
+	let order = CURVE_ORDER.parse::<BigUint>().expect("Curve order is valid; qed");
+
+	let mut preimage = [0u8; 32];
+	preimage[..8].copy_from_slice(&index.to_le_bytes());
+	let sk = BigUint::from_bytes_le(&Sha256::digest(&preimage[..])) % &order;
+
+	SecretKey::from_uint(&sk)
+}
+
+/// Interop BLS secret key of the validator at index.
+pub fn secret(index: u64) -> bls::Secret {
+	secret_key(index).to_secret()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn interop_keys() {
+		assert_eq!(
+			hex::encode(secret_key(0).as_bytes()),
+			"25295f0d1d592a90b333e26e85149708208e9f8e8bc18f6c77bd62f8ad7a6866"
+		);
+		assert_eq!(
+			hex::encode(secret_key(1).as_bytes()),
+			"51d0b65185db6989ab0b560d6deed19c7ead0e24b9b6372cbecb1f26bdfad000"
+		);
+	}
+}
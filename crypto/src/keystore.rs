@@ -0,0 +1,527 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! EIP-2335 BLS keystores.
+
+use crate::bls;
+use beacon::primitives::ValidatorId;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use sha2::{Sha256, Digest};
+use hmac::Hmac;
+use aes_ctr::Aes128Ctr;
+use aes_ctr::stream_cipher::{NewStreamCipher, SyncStreamCipher};
+use aes_ctr::stream_cipher::generic_array::GenericArray;
+use unicode_normalization::UnicodeNormalization;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+/// Keystore format version.
+pub const KEYSTORE_VERSION: u32 = 4;
+/// Length of the secret key in the keystore.
+pub const SECRET_LENGTH: usize = 32;
+/// Length of the decryption key derived from the password.
+pub const DKLEN: u32 = 32;
+
+/// Keystore error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// Keystore json is invalid.
+	InvalidJson(String),
+	/// Key derivation function is not supported.
+	UnsupportedKdf(String),
+	/// Checksum function is not supported.
+	UnsupportedChecksum(String),
+	/// Cipher function is not supported.
+	UnsupportedCipher(String),
+	/// Module parameters are invalid.
+	InvalidParams(String),
+	/// Password is incorrect.
+	InvalidPassword,
+	/// Decrypted secret is invalid.
+	InvalidSecret,
+	/// Decrypted secret does not match the public key.
+	PubkeyMismatch,
+	/// IO error.
+	IO(String),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+impl std::error::Error for Error { }
+
+/// Crypto module of a keystore.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Module {
+	/// Function name.
+	pub function: String,
+	/// Function parameters.
+	pub params: Value,
+	/// Module message, hex-encoded.
+	pub message: String,
+}
+
+/// Crypto section of a keystore.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KeystoreCrypto {
+	/// Key derivation function.
+	pub kdf: Module,
+	/// Checksum function.
+	pub checksum: Module,
+	/// Cipher function.
+	pub cipher: Module,
+}
+
+/// EIP-2335 keystore.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Keystore {
+	/// Crypto section.
+	pub crypto: KeystoreCrypto,
+	/// Description.
+	#[serde(default)]
+	pub description: String,
+	/// Hex-encoded public key.
+	pub pubkey: String,
+	/// EIP-2334 derivation path.
+	pub path: String,
+	/// UUID of the keystore.
+	pub uuid: String,
+	/// Keystore version.
+	pub version: u32,
+}
+
+/// Key derivation function.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Kdf {
+	/// Scrypt.
+	Scrypt {
+		/// Derived key length.
+		dklen: u32,
+		/// Cost parameter.
+		n: u32,
+		/// Block size parameter.
+		r: u32,
+		/// Parallelization parameter.
+		p: u32,
+		/// Salt.
+		salt: Vec<u8>,
+	},
+	/// PBKDF2 with HMAC-SHA256.
+	Pbkdf2 {
+		/// Derived key length.
+		dklen: u32,
+		/// Iteration count.
+		c: u32,
+		/// Salt.
+		salt: Vec<u8>,
+	},
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+	let mut bytes = vec![0; len];
+	rand::thread_rng().fill_bytes(&mut bytes);
+	bytes
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, Error> {
+	hex::decode(value).map_err(|e| Error::InvalidParams(format!("{}", e)))
+}
+
+fn param_u32(params: &Value, name: &str) -> Result<u32, Error> {
+	let value = params.get(name).and_then(|v| v.as_u64())
+		.ok_or(Error::InvalidParams(format!("Missing parameter {}", name)))?;
+	u32::try_from(value)
+		.map_err(|_| Error::InvalidParams(format!("Parameter {} out of range", name)))
+}
+
+fn param_hex(params: &Value, name: &str) -> Result<Vec<u8>, Error> {
+	let value = params.get(name).and_then(|v| v.as_str())
+		.ok_or(Error::InvalidParams(format!("Missing parameter {}", name)))?;
+	decode_hex(value)
+}
+
+impl Kdf {
+	/// Scrypt with the recommended parameters and a random salt.
+	pub fn scrypt() -> Self {
+		Kdf::Scrypt { dklen: DKLEN, n: 262144, r: 8, p: 1, salt: random_bytes(32) }
+	}
+
+	/// PBKDF2 with the recommended parameters and a random salt.
+	pub fn pbkdf2() -> Self {
+		Kdf::Pbkdf2 { dklen: DKLEN, c: 262144, salt: random_bytes(32) }
+	}
+
+	/// Parse the key derivation function from a keystore module.
+	pub fn from_module(module: &Module) -> Result<Self, Error> {
+		let params = &module.params;
+		match module.function.as_str() {
+			"scrypt" => Ok(Kdf::Scrypt {
+				dklen: param_u32(params, "dklen")?,
+				n: param_u32(params, "n")?,
+				r: param_u32(params, "r")?,
+				p: param_u32(params, "p")?,
+				salt: param_hex(params, "salt")?,
+			}),
+			"pbkdf2" => {
+				match params.get("prf").and_then(|v| v.as_str()) {
+					Some("hmac-sha256") => (),
+					prf => return Err(Error::UnsupportedKdf(format!("pbkdf2 with {:?}", prf))),
+				}
+				Ok(Kdf::Pbkdf2 {
+					dklen: param_u32(params, "dklen")?,
+					c: param_u32(params, "c")?,
+					salt: param_hex(params, "salt")?,
+				})
+			},
+			function => Err(Error::UnsupportedKdf(function.to_string())),
+		}
+	}
+
+	/// Convert the key derivation function into a keystore module.
+	pub fn to_module(&self) -> Module {
+		let (function, params) = match self {
+			Kdf::Scrypt { dklen, n, r, p, salt } => ("scrypt", json!({
+				"dklen": dklen, "n": n, "r": r, "p": p, "salt": hex::encode(salt),
+			})),
+			Kdf::Pbkdf2 { dklen, c, salt } => ("pbkdf2", json!({
+				"dklen": dklen, "c": c, "prf": "hmac-sha256", "salt": hex::encode(salt),
+			})),
+		};
+
+		Module { function: function.to_string(), params, message: String::new() }
+	}
+
+	/// Derive the decryption key from a processed password.
+	pub fn derive(&self, password: &[u8]) -> Result<Vec<u8>, Error> {
+		match self {
+			Kdf::Scrypt { dklen, n, r, p, salt } => {
+				if *dklen < DKLEN || !n.is_power_of_two() || *n < 2 {
+					return Err(Error::InvalidParams("Invalid scrypt parameters".to_string()))
+				}
+				let params = scrypt::ScryptParams::new(n.trailing_zeros() as u8, *r, *p)
+					.map_err(|_| Error::InvalidParams("Invalid scrypt parameters".to_string()))?;
+				let mut key = vec![0; *dklen as usize];
+				scrypt::scrypt(password, salt, &params, &mut key)
+					.map_err(|_| Error::InvalidParams("Invalid scrypt key length".to_string()))?;
+				Ok(key)
+			},
+			Kdf::Pbkdf2 { dklen, c, salt } => {
+				if *dklen < DKLEN {
+					return Err(Error::InvalidParams("Invalid pbkdf2 parameters".to_string()))
+				}
+				let mut key = vec![0; *dklen as usize];
+				pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, *c as usize, &mut key);
+				Ok(key)
+			},
+		}
+	}
+}
+
+/// Process a password as specified by EIP-2335: NFKD normalization, and
+/// removal of control codes.
+pub fn process_password(password: &str) -> Vec<u8> {
+	password.nfkd()
+		.filter(|c| {
+			let c = *c as u32;
+			!(c <= 0x1f || (c >= 0x7f && c <= 0x9f))
+		})
+		.collect::<String>()
+		.into_bytes()
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	if a.len() != b.len() {
+		return false
+	}
+
+	a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn checksum(key: &[u8], cipher_message: &[u8]) -> Vec<u8> {
+	let mut hasher = Sha256::new();
+	hasher.input(&key[16..32]);
+	hasher.input(cipher_message);
+	hasher.result().to_vec()
+}
+
+fn apply_cipher(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), Error> {
+	if iv.len() != 16 {
+		return Err(Error::InvalidParams("Invalid cipher iv length".to_string()))
+	}
+
+	let mut cipher = Aes128Ctr::new(
+		GenericArray::from_slice(&key[0..16]),
+		GenericArray::from_slice(iv),
+	);
+	cipher.apply_keystream(data);
+	Ok(())
+}
+
+/// Convert big-endian secret key bytes into a BLS secret key.
+pub fn secret_from_bytes(secret: &[u8]) -> Result<bls::Secret, Error> {
+	const PRIVATE_KEY_BYTES: usize = 48;
+
+	if secret.len() > PRIVATE_KEY_BYTES {
+		return Err(Error::InvalidSecret)
+	}
+
+	let mut bytes = vec![0; PRIVATE_KEY_BYTES - secret.len()];
+	bytes.extend_from_slice(secret);
+	bls::Secret::from_bytes(&bytes).map_err(|_| Error::InvalidSecret)
+}
+
+/// Get the validator id of a BLS secret key.
+pub fn validator_id(secret: &bls::Secret) -> ValidatorId {
+	ValidatorId::from_slice(&bls::Public::from_secret_key(secret).as_bytes()[..])
+}
+
+impl Keystore {
+	/// Encrypt a 32-byte big-endian secret key into a keystore.
+	pub fn encrypt(
+		secret: &[u8],
+		password: &str,
+		path: &str,
+		kdf: Kdf,
+	) -> Result<Self, Error> {
+		if secret.len() != SECRET_LENGTH {
+			return Err(Error::InvalidSecret)
+		}
+		let pubkey = validator_id(&secret_from_bytes(secret)?);
+
+		let key = kdf.derive(&process_password(password))?;
+		let iv = random_bytes(16);
+		let mut cipher_message = secret.to_vec();
+		apply_cipher(&key, &iv, &mut cipher_message)?;
+
+		Ok(Self {
+			crypto: KeystoreCrypto {
+				kdf: kdf.to_module(),
+				checksum: Module {
+					function: "sha256".to_string(),
+					params: json!({}),
+					message: hex::encode(checksum(&key, &cipher_message)),
+				},
+				cipher: Module {
+					function: "aes-128-ctr".to_string(),
+					params: json!({ "iv": hex::encode(&iv) }),
+					message: hex::encode(&cipher_message),
+				},
+			},
+			description: String::new(),
+			pubkey: hex::encode(&pubkey[..]),
+			path: path.to_string(),
+			uuid: uuid::Uuid::new_v4().to_string(),
+			version: KEYSTORE_VERSION,
+		})
+	}
+
+	/// Decrypt the 32-byte big-endian secret key.
+	pub fn decrypt(&self, password: &str) -> Result<Vec<u8>, Error> {
+		if self.version != KEYSTORE_VERSION {
+			return Err(Error::InvalidJson(format!("Unsupported version {}", self.version)))
+		}
+		if self.crypto.checksum.function != "sha256" {
+			return Err(Error::UnsupportedChecksum(self.crypto.checksum.function.clone()))
+		}
+		if self.crypto.cipher.function != "aes-128-ctr" {
+			return Err(Error::UnsupportedCipher(self.crypto.cipher.function.clone()))
+		}
+
+		let kdf = Kdf::from_module(&self.crypto.kdf)?;
+		let key = kdf.derive(&process_password(password))?;
+		let mut cipher_message = decode_hex(&self.crypto.cipher.message)?;
+
+		let expected = decode_hex(&self.crypto.checksum.message)?;
+		if !constant_time_eq(&checksum(&key, &cipher_message), &expected) {
+			return Err(Error::InvalidPassword)
+		}
+
+		let iv = param_hex(&self.crypto.cipher.params, "iv")?;
+		apply_cipher(&key, &iv, &mut cipher_message)?;
+		Ok(cipher_message)
+	}
+
+	/// Decrypt into a BLS secret key, checking it against the public key.
+	pub fn decrypt_secret(&self, password: &str) -> Result<bls::Secret, Error> {
+		let secret = secret_from_bytes(&self.decrypt(password)?)?;
+		if hex::encode(&validator_id(&secret)[..]) != self.pubkey.trim_start_matches("0x") {
+			return Err(Error::PubkeyMismatch)
+		}
+		Ok(secret)
+	}
+
+	/// Parse a keystore from json.
+	pub fn from_json(json: &str) -> Result<Self, Error> {
+		serde_json::from_str(json).map_err(|e| Error::InvalidJson(format!("{}", e)))
+	}
+
+	/// Serialize the keystore into json.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).expect("Keystore is always serializable; qed")
+	}
+
+	/// Password file name for the keystore, in the secrets directory.
+	pub fn password_file_name(&self) -> String {
+		format!("0x{}", self.pubkey.trim_start_matches("0x"))
+	}
+}
+
+/// Load all keystores in a directory, decrypting each with the password
+/// file named after its public key in the secrets directory.
+pub fn load_dir<P: AsRef<Path>, Q: AsRef<Path>>(
+	keystores: P,
+	secrets: Q,
+) -> Result<HashMap<ValidatorId, bls::Secret>, Error> {
+	let mut keys = HashMap::new();
+
+	let entries = fs::read_dir(keystores).map_err(|e| Error::IO(format!("{}", e)))?;
+	for entry in entries {
+		let path = entry.map_err(|e| Error::IO(format!("{}", e)))?.path();
+		if path.extension().and_then(|v| v.to_str()) != Some("json") {
+			continue
+		}
+
+		let keystore = Keystore::from_json(
+			&fs::read_to_string(&path).map_err(|e| Error::IO(format!("{}", e)))?
+		)?;
+		let password = fs::read_to_string(secrets.as_ref().join(keystore.password_file_name()))
+			.map_err(|e| Error::IO(format!("{}", e)))?;
+		let secret = keystore.decrypt_secret(password.trim_end_matches(|c| c == '\n' || c == '\r'))?;
+
+		keys.insert(validator_id(&secret), secret);
+	}
+
+	Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PASSWORD: &str = "\u{1d531}\u{1d522}\u{1d530}\u{1d531}\u{1d52d}\u{1d51e}\u{1d530}\u{1d530}\u{1d534}\u{1d52c}\u{1d52f}\u{1d521}\u{1f511}";
+	const SECRET: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+	#[test]
+	fn processes_password() {
+		assert_eq!(process_password(PASSWORD), "testpassword\u{1f511}".as_bytes());
+		assert_eq!(process_password("a\u{0}b\u{7f}c\u{85}"), b"abc");
+	}
+
+	#[test]
+	fn decrypts_pbkdf2_test_vector() {
+		let keystore = Keystore::from_json(r#"{
+			"crypto": {
+				"kdf": {
+					"function": "pbkdf2",
+					"params": {
+						"dklen": 32,
+						"c": 262144,
+						"prf": "hmac-sha256",
+						"salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+					},
+					"message": ""
+				},
+				"checksum": {
+					"function": "sha256",
+					"params": {},
+					"message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+				},
+				"cipher": {
+					"function": "aes-128-ctr",
+					"params": {
+						"iv": "264daa3f303d7259501c93d997d84fe6"
+					},
+					"message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+				}
+			},
+			"description": "This is a test keystore that uses PBKDF2 to secure the secret.",
+			"pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+			"path": "m/12381/60/0/0",
+			"uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+			"version": 4
+		}"#).unwrap();
+
+		assert_eq!(keystore.decrypt(PASSWORD).unwrap(), hex::decode(SECRET).unwrap());
+		assert_eq!(keystore.decrypt("wrong"), Err(Error::InvalidPassword));
+	}
+
+	#[test]
+	fn decrypts_scrypt_test_vector() {
+		let keystore = Keystore::from_json(r#"{
+			"crypto": {
+				"kdf": {
+					"function": "scrypt",
+					"params": {
+						"dklen": 32,
+						"n": 262144,
+						"p": 1,
+						"r": 8,
+						"salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+					},
+					"message": ""
+				},
+				"checksum": {
+					"function": "sha256",
+					"params": {},
+					"message": "d2217fe5f3e9a1e34581ef8a78f7c9928e436d36dacc5e846690a5581e8ea484"
+				},
+				"cipher": {
+					"function": "aes-128-ctr",
+					"params": {
+						"iv": "264daa3f303d7259501c93d997d84fe6"
+					},
+					"message": "06ae90d55fe0a6e9c5c3bc5b170827b2e5cce3929ed3f116c2811e6366dfe20f"
+				}
+			},
+			"description": "This is a test keystore that uses scrypt to secure the secret.",
+			"pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+			"path": "m/12381/60/3141592653/589793238",
+			"uuid": "1d85ae20-35c5-4611-98e8-aa14a633906f",
+			"version": 4
+		}"#).unwrap();
+
+		assert_eq!(keystore.decrypt(PASSWORD).unwrap(), hex::decode(SECRET).unwrap());
+		assert_eq!(keystore.decrypt("wrong"), Err(Error::InvalidPassword));
+	}
+
+	#[test]
+	fn rejects_oversized_parameters() {
+		let params = json!({ "n": 1u64 << 32 });
+		assert!(param_u32(&params, "n").is_err());
+	}
+
+	#[test]
+	fn encrypt_decrypt_roundtrip() {
+		let secret = hex::decode(SECRET).unwrap();
+		let kdf = Kdf::Scrypt { dklen: 32, n: 16, r: 8, p: 1, salt: random_bytes(32) };
+		let keystore = Keystore::encrypt(&secret, PASSWORD, "m/12381/3600/0/0/0", kdf).unwrap();
+		let keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+
+		assert_eq!(keystore.decrypt(PASSWORD).unwrap(), secret);
+		assert!(keystore.decrypt_secret(PASSWORD).is_ok());
+	}
+}
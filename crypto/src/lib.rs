@@ -40,6 +40,12 @@ pub mod bls {
 	pub type AggregatePublic = bls_crate::AggregatePublicKey;
 	pub type AggregateSignature = bls_crate::AggregateSignature;
 	pub use self::verification::BLSVerification;
+	pub use bls_crate::{hash_on_g2, compress_g2};
+
+	#[cfg(feature = "std")]
+	pub mod derive;
+	#[cfg(feature = "std")]
+	pub mod interop;
 
 	mod verification {
 		use crate::bls;
@@ -108,3 +114,6 @@ pub mod bls {
 		}
 	}
 }
+
+#[cfg(feature = "std")]
+pub mod keystore;
@@ -19,7 +19,7 @@
 extern crate proc_macro;
 
 use quote::{quote, quote_spanned};
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, parse_quote, DeriveInput, Data, Fields, Generics, Ident, Type, WherePredicate};
 use syn::spanned::Spanned;
 use deriving::{struct_fields, has_attribute};
 
@@ -32,6 +32,16 @@ pub fn codec_derive(input: TokenStream) -> TokenStream {
 
 	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
+	if union_variants(&input.data).is_some() {
+		let expanded = quote! {
+			impl #impl_generics ssz::Codec for #name #ty_generics #where_clause {
+				type Size = ssz::VariableSize;
+			}
+		};
+
+		return proc_macro::TokenStream::from(expanded)
+	}
+
 	let where_fields = struct_fields(&input.data)
 		.expect("Not supported derive type")
         .iter()
@@ -68,6 +78,39 @@ pub fn encode_derive(input: TokenStream) -> TokenStream {
 
 	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
+	if let Some(variants) = union_variants(&input.data) {
+		let where_fields = variants.iter().filter_map(|(_, ty)| ty.as_ref()).map(|ty| {
+			quote_spanned! { ty.span() => #ty: ssz::Encode }
+		});
+
+		let arms = variants.iter().enumerate().map(|(i, (variant, ty))| {
+			let selector = i as u8;
+
+			match ty {
+				Some(_) => quote_spanned! { variant.span() =>
+					#name::#variant(ref value) =>
+						ssz::encode_union(#selector, Some(ssz::Encode::encode(value))),
+				},
+				None => quote_spanned! { variant.span() =>
+					#name::#variant => ssz::encode_union(#selector, None),
+				},
+			}
+		});
+
+		let expanded = quote! {
+			impl #impl_generics ssz::Encode for #name #ty_generics where #where_clause #(#where_fields),* {
+				fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+					let encoded = match *self {
+						#(#arms)*
+					};
+					f(&encoded)
+				}
+			}
+		};
+
+		return proc_macro::TokenStream::from(expanded)
+	}
+
 	let where_fields = struct_fields(&input.data)
 		.expect("Not supported derive type")
         .iter()
@@ -130,6 +173,42 @@ pub fn decode_derive(input: TokenStream) -> TokenStream {
     let name = input.ident;
 	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
+	if let Some(variants) = union_variants(&input.data) {
+		let where_fields = variants.iter().filter_map(|(_, ty)| ty.as_ref()).map(|ty| {
+			quote_spanned! { ty.span() => #ty: ssz::Decode }
+		});
+
+		let arms = variants.iter().enumerate().map(|(i, (variant, ty))| {
+			let selector = i as u8;
+
+			match ty {
+				Some(ty) => quote_spanned! { variant.span() =>
+					(#selector, value) => Ok(#name::#variant(<#ty as ssz::Decode>::decode(value)?)),
+				},
+				None => quote_spanned! { variant.span() =>
+					(#selector, value) => if value.is_empty() {
+						Ok(#name::#variant)
+					} else {
+						Err(ssz::Error::IncorrectSize)
+					},
+				},
+			}
+		});
+
+		let expanded = quote! {
+			impl #impl_generics ssz::Decode for #name #ty_generics where #where_clause #(#where_fields),* {
+				fn decode(value: &[u8]) -> Result<Self, ssz::Error> {
+					match ssz::decode_union(value)? {
+						#(#arms)*
+						_ => Err(ssz::Error::InvalidType),
+					}
+				}
+			}
+		};
+
+		return proc_macro::TokenStream::from(expanded)
+	}
+
 	let where_fields = struct_fields(&input.data)
 		.expect("Not supported derive type")
         .iter()
@@ -166,41 +245,35 @@ pub fn decode_derive(input: TokenStream) -> TokenStream {
 			}
 		});
 
+	let container = name.to_string();
+	let count = struct_fields(&input.data)
+		.expect("Not supported derive type")
+		.len();
+
 	let fields = struct_fields(&input.data)
         .expect("Not supported derive type")
         .iter()
 		.enumerate()
         .map(|(i, f)| {
             let name = &f.ident;
+			let field = name.as_ref().map(|name| name.to_string()).unwrap_or_default();
 			let ty = &f.ty;
 
 			let decode = if has_attribute("bm", &f.attrs, "compact") {
-				quote_spanned! { f.span() => {
-					<ssz::Compact<#ty> as ssz::Decode>::decode(item)?.0
-				} }
+				quote_spanned! { f.span() =>
+					<ssz::Compact<#ty> as ssz::Decode>::decode(&value[start..end]).map(|v| v.0)
+				}
 			} else {
-				quote_spanned! { f.span() => {
-					<#ty as ssz::Decode>::decode(item)?
-				} }
+				quote_spanned! { f.span() =>
+					<#ty as ssz::Decode>::decode(&value[start..end])
+				}
 			};
 
 			quote_spanned! {
                 f.span() =>
-                    #name: match &series.0[#i] {
-						ssz::SeriesItem::Fixed(ref item) => {
-							if <<#ty as ssz::Codec>::Size as ssz::Size>::is_fixed() {
-								#decode
-							} else {
-								return Err(ssz::Error::InvalidType)
-							}
-						},
-						ssz::SeriesItem::Variable(ref item) => {
-							if <<#ty as ssz::Codec>::Size as ssz::Size>::is_variable() {
-								#decode
-							} else {
-								return Err(ssz::Error::InvalidType)
-							}
-						},
+                    #name: {
+						let (start, end) = ranges[#i];
+						#decode.map_err(|e| e.in_field(&#container, &#field, start))?
 					},
             }
 		});
@@ -209,7 +282,8 @@ pub fn decode_derive(input: TokenStream) -> TokenStream {
 		impl #impl_generics ssz::Decode for #name #ty_generics where #where_clause #(#where_fields),* {
 			fn decode(value: &[u8]) -> Result<Self, ssz::Error> {
 				let types = [#(#size_fields),*];
-				let series = ssz::Series::decode_vector(value, &types)?;
+				let mut ranges = [(0, 0); #count];
+				ssz::Series::vector_ranges(value, &types, &mut ranges)?;
 				Ok(Self {
 					#(#fields)*
 				})
@@ -219,3 +293,226 @@ pub fn decode_derive(input: TokenStream) -> TokenStream {
 
 	proc_macro::TokenStream::from(expanded)
 }
+
+#[proc_macro_derive(UnionTree)]
+pub fn union_tree_derive(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident;
+	let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+	let variants = union_variants(&input.data).expect("UnionTree can only be derived for enums");
+
+	let into_generics = with_predicates(
+		&input.generics,
+		variants.iter().filter_map(|(_, ty)| ty.as_ref())
+			.map(|ty| parse_quote!(#ty: bm_le::IntoTree)),
+	);
+	let into_where_clause = into_generics.where_clause.as_ref();
+	let from_generics = with_predicates(
+		&input.generics,
+		variants.iter().filter_map(|(_, ty)| ty.as_ref())
+			.map(|ty| parse_quote!(#ty: bm_le::FromTree)),
+	);
+	let from_where_clause = from_generics.where_clause.as_ref();
+
+	let into_arms = variants.iter().enumerate().map(|(i, (variant, ty))| {
+		let selector = i as u8;
+
+		match ty {
+			Some(_) => quote_spanned! { variant.span() =>
+				#name::#variant(ref value) => ssz::union_into_tree(#selector, value, db),
+			},
+			None => quote_spanned! { variant.span() =>
+				#name::#variant => ssz::empty_union_into_tree(db),
+			},
+		}
+	});
+
+	let from_arms = variants.iter().enumerate().map(|(i, (variant, ty))| {
+		let selector = i as u8;
+
+		match ty {
+			Some(ty) => quote_spanned! { variant.span() =>
+				#selector => Ok(#name::#variant(<#ty as bm_le::FromTree>::from_tree(&value, db)?)),
+			},
+			None => quote_spanned! { variant.span() =>
+				#selector => Ok(#name::#variant),
+			},
+		}
+	});
+
+	let expanded = quote! {
+		impl #impl_generics bm_le::IntoTree for #name #ty_generics #into_where_clause {
+			fn into_tree<DB: bm_le::WriteBackend>(
+				&self,
+				db: &mut DB,
+			) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+				DB::Construct: bm_le::CompatibleConstruct,
+			{
+				match *self {
+					#(#into_arms)*
+				}
+			}
+		}
+
+		impl #impl_generics bm_le::FromTree for #name #ty_generics #from_where_clause {
+			fn from_tree<DB: bm_le::ReadBackend>(
+				root: &<DB::Construct as bm_le::Construct>::Value,
+				db: &mut DB,
+			) -> Result<Self, bm_le::Error<DB::Error>> where
+				DB::Construct: bm_le::CompatibleConstruct,
+			{
+				let (selector, value) = ssz::union_from_tree(root, db)?;
+				match selector {
+					#(#from_arms)*
+					_ => Err(bm_le::Error::CorruptedDatabase),
+				}
+			}
+		}
+	};
+
+	proc_macro::TokenStream::from(expanded)
+}
+
+#[proc_macro_derive(View, attributes(ssz, bm))]
+pub fn view_derive(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let name = input.ident;
+	let vis = input.vis;
+	let view_name = Ident::new(&format!("{}View", name), name.span());
+	let (_, ty_generics, _) = input.generics.split_for_impl();
+
+	let mut view_generics = input.generics.clone();
+	view_generics.params.insert(0, syn::parse_quote!('ssz));
+	let (view_impl_generics, view_ty_generics, where_clause) = view_generics.split_for_impl();
+
+	let fields = struct_fields(&input.data)
+		.expect("View can only be derived for structs");
+	let count = fields.len();
+
+	let impl_generics = with_predicates(&view_generics, fields.iter().map(|f| {
+		let ty = &f.ty;
+
+		if has_attribute("bm", &f.attrs, "compact") {
+			parse_quote!(ssz::Compact<#ty>: ssz::Decode)
+		} else {
+			parse_quote!(#ty: ssz::Decode)
+		}
+	}));
+	let impl_where_clause = impl_generics.where_clause.as_ref();
+
+	let size_fields = fields.iter().map(|f| {
+		let ty = &f.ty;
+
+		if has_attribute("bm", &f.attrs, "compact") {
+			quote_spanned! {
+				f.span() =>
+					<<ssz::Compact<#ty> as ssz::Codec>::Size as ssz::Size>::size()
+			}
+		} else {
+			quote_spanned! {
+				f.span() =>
+					<<#ty as ssz::Codec>::Size as ssz::Size>::size()
+			}
+		}
+	});
+
+	let container = name.to_string();
+	let accessors = fields.iter().enumerate().map(|(i, f)| {
+		let field = f.ident.as_ref().expect("View can only be derived for named structs");
+		let field_bytes = Ident::new(&format!("{}_bytes", field), field.span());
+		let ty = &f.ty;
+
+		let field_name = field.to_string();
+		let decode = if has_attribute("bm", &f.attrs, "compact") {
+			quote_spanned! { f.span() =>
+				<ssz::Compact<#ty> as ssz::Decode>::decode(self.#field_bytes()).map(|v| v.0)
+			}
+		} else {
+			quote_spanned! { f.span() =>
+				<#ty as ssz::Decode>::decode(self.#field_bytes())
+			}
+		};
+
+		quote_spanned! { f.span() =>
+			/// Decode only this field.
+			pub fn #field(&self) -> Result<#ty, ssz::Error> {
+				#decode.map_err(|e| e.in_field(&#container, &#field_name, self.ranges[#i].0))
+			}
+
+			/// Encoded bytes of this field.
+			pub fn #field_bytes(&self) -> &'ssz [u8] {
+				let (start, end) = self.ranges[#i];
+				&self.bytes[start..end]
+			}
+		}
+	});
+
+	let doc = format!("Zero-copy view of an encoded `{}`.", name);
+
+	let expanded = quote! {
+		#[doc = #doc]
+		#vis struct #view_name #view_impl_generics #where_clause {
+			bytes: &'ssz [u8],
+			ranges: [(usize, usize); #count],
+			_marker: core::marker::PhantomData<#name #ty_generics>,
+		}
+
+		impl #view_impl_generics #view_name #view_ty_generics #impl_where_clause {
+			/// Create a view over encoded bytes, validating all offsets.
+			pub fn new(bytes: &'ssz [u8]) -> Result<Self, ssz::Error> {
+				let types = [#(#size_fields),*];
+				let mut ranges = [(0, 0); #count];
+				ssz::Series::vector_ranges(bytes, &types, &mut ranges)?;
+
+				Ok(Self { bytes, ranges, _marker: core::marker::PhantomData })
+			}
+
+			/// Encoded bytes of the whole value.
+			pub fn as_bytes(&self) -> &'ssz [u8] {
+				self.bytes
+			}
+
+			/// Decode the whole value.
+			pub fn decode(&self) -> Result<#name #ty_generics, ssz::Error> {
+				<#name #ty_generics as ssz::Decode>::decode(self.bytes)
+			}
+
+			#(#accessors)*
+		}
+	};
+
+	proc_macro::TokenStream::from(expanded)
+}
+
+/// Generics with the given predicates added to their where clause.
+fn with_predicates<I: IntoIterator<Item=WherePredicate>>(generics: &Generics, predicates: I) -> Generics {
+	let mut generics = generics.clone();
+	generics.make_where_clause().predicates.extend(predicates);
+	generics
+}
+
+/// Variants of an enum deriving a union, with their value types. Returns
+/// `None` if the input is not an enum.
+fn union_variants(data: &Data) -> Option<Vec<(Ident, Option<Type>)>> {
+	let data = match data {
+		Data::Enum(data) => data,
+		_ => return None,
+	};
+
+	if data.variants.is_empty() || data.variants.len() > 128 {
+		panic!("Union must have between 1 and 128 variants");
+	}
+
+	Some(data.variants.iter().enumerate().map(|(i, variant)| {
+		let ty = match &variant.fields {
+			Fields::Unit if i == 0 => None,
+			Fields::Unit => panic!("Only the first union variant can be empty"),
+			Fields::Unnamed(fields) if fields.unnamed.len() == 1 =>
+				Some(fields.unnamed[0].ty.clone()),
+			_ => panic!("Union variants must be empty or have a single unnamed field"),
+		};
+
+		(variant.ident.clone(), ty)
+	}).collect())
+}
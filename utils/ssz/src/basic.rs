@@ -17,7 +17,7 @@ macro_rules! impl_builtin_uint {
 			fn decode(value: &[u8]) -> Result<Self, Error> {
 				let mut bytes = <$t>::default().to_le_bytes();
 				if value.len() != bytes.len() {
-					return Err(Error::IncorrectSize)
+					return Err(Error::SizeMismatch { expected: bytes.len(), actual: value.len() })
 				}
 				bytes.copy_from_slice(value);
 				Ok(<$t>::from_le_bytes(bytes))
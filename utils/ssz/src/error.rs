@@ -0,0 +1,238 @@
+use core::fmt;
+
+/// Maximum depth of fields and list elements recorded in a field path.
+pub const MAX_FIELD_PATH_DEPTH: usize = 4;
+
+/// Name of a container or a field. It is a reference to a static string
+/// slice so that it only takes a single pointer in `FieldPath`.
+pub type Name = &'static &'static str;
+
+const NO_INDEX: u32 = u32::max_value();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error type for encoding and decoding.
+pub enum Error {
+	/// Incorrect size.
+	IncorrectSize,
+	/// Invalid type.
+	InvalidType,
+	/// Vector length is incorrect.
+	InvalidLength,
+	/// List length is too large.
+	ListTooLarge,
+	/// Size of the value is not the expected one.
+	SizeMismatch {
+		/// Expected size in bytes.
+		expected: usize,
+		/// Actual size in bytes.
+		actual: usize,
+	},
+	/// Offset of a variable-sized item is out of order or out of bounds.
+	InvalidOffset {
+		/// The decoded offset.
+		offset: usize,
+	},
+	/// Other errors.
+	Other(&'static str),
+	/// Decoding a container field failed.
+	Field {
+		/// Path of the field, from the outermost container.
+		path: FieldPath,
+		/// Byte offset of the field in the outermost container.
+		offset: usize,
+		/// The underlying error.
+		cause: ErrorCode,
+	},
+}
+
+impl Error {
+	/// Compact error code of the underlying error.
+	pub fn code(&self) -> ErrorCode {
+		match *self {
+			Error::IncorrectSize => ErrorCode::IncorrectSize,
+			Error::InvalidType => ErrorCode::InvalidType,
+			Error::InvalidLength => ErrorCode::InvalidLength,
+			Error::ListTooLarge => ErrorCode::ListTooLarge,
+			Error::SizeMismatch { expected, actual } => ErrorCode::SizeMismatch { expected, actual },
+			Error::InvalidOffset { offset } => ErrorCode::InvalidOffset { offset },
+			Error::Other(s) => ErrorCode::Other(s),
+			Error::Field { cause, .. } => cause,
+		}
+	}
+
+	/// Record that this error happened in the given field of a container,
+	/// where the field starts at `offset` of the container encoding.
+	pub fn in_field(self, container: Name, field: Name, offset: usize) -> Error {
+		self.in_path(offset, |path| path.push_field(container, field))
+	}
+
+	/// Record that this error happened in the given element of a list,
+	/// where the element starts at `offset` of the list encoding.
+	pub fn in_element(self, index: usize, offset: usize) -> Error {
+		self.in_path(offset, |path| path.push_index(index))
+	}
+
+	fn in_path<F: FnOnce(&mut FieldPath)>(self, offset: usize, f: F) -> Error {
+		match self {
+			Error::Field { mut path, offset: inner_offset, cause } => {
+				f(&mut path);
+				Error::Field { path, offset: offset + inner_offset, cause }
+			},
+			err => {
+				let mut path = FieldPath::default();
+				f(&mut path);
+				Error::Field { path, offset, cause: err.code() }
+			},
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Field { path, offset, cause } =>
+				write!(f, "{} at byte {}: {}", path, offset, cause),
+			err => write!(f, "{}", err.code()),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error { }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Compact error code, without field information.
+pub enum ErrorCode {
+	/// Incorrect size.
+	IncorrectSize,
+	/// Invalid type.
+	InvalidType,
+	/// Vector length is incorrect.
+	InvalidLength,
+	/// List length is too large.
+	ListTooLarge,
+	/// Size of the value is not the expected one.
+	SizeMismatch {
+		/// Expected size in bytes.
+		expected: usize,
+		/// Actual size in bytes.
+		actual: usize,
+	},
+	/// Offset of a variable-sized item is out of order or out of bounds.
+	InvalidOffset {
+		/// The decoded offset.
+		offset: usize,
+	},
+	/// Other errors.
+	Other(&'static str),
+}
+
+impl fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ErrorCode::IncorrectSize => write!(f, "incorrect size"),
+			ErrorCode::InvalidType => write!(f, "invalid type"),
+			ErrorCode::InvalidLength => write!(f, "invalid vector length"),
+			ErrorCode::ListTooLarge => write!(f, "list too large"),
+			ErrorCode::SizeMismatch { expected, actual } =>
+				write!(f, "expected {} bytes, got {}", expected, actual),
+			ErrorCode::InvalidOffset { offset } => write!(f, "invalid offset {}", offset),
+			ErrorCode::Other(s) => write!(f, "{}", s),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Path of container fields and list elements, from the outermost value.
+/// Only the outermost `MAX_FIELD_PATH_DEPTH` segments are kept.
+pub struct FieldPath {
+	container: Option<Name>,
+	fields: [Option<Name>; MAX_FIELD_PATH_DEPTH],
+	indices: [u32; MAX_FIELD_PATH_DEPTH],
+	len: u8,
+	truncated: bool,
+}
+
+impl Default for FieldPath {
+	fn default() -> Self {
+		Self {
+			container: None,
+			fields: [None; MAX_FIELD_PATH_DEPTH],
+			indices: [NO_INDEX; MAX_FIELD_PATH_DEPTH],
+			len: 0,
+			truncated: false,
+		}
+	}
+}
+
+impl FieldPath {
+	/// Outermost container of the path, if the path starts with a field.
+	pub fn container(&self) -> Option<&'static str> {
+		self.container.map(|name| *name)
+	}
+
+	/// Segments of the path, each with an optional field name and an
+	/// optional list element index.
+	pub fn segments<'a>(&'a self) -> impl Iterator<Item=(Option<&'static str>, Option<usize>)> + 'a {
+		(0..(self.len as usize)).map(move |i| {
+			let index = self.indices[i];
+			(
+				self.fields[i].map(|name| *name),
+				if index == NO_INDEX { None } else { Some(index as usize) },
+			)
+		})
+	}
+
+	/// Whether inner segments were dropped from the path.
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	fn push_field(&mut self, container: Name, field: Name) {
+		self.container = Some(container);
+		if self.len > 0 && self.fields[0].is_none() {
+			self.fields[0] = Some(field);
+		} else {
+			self.push_front(Some(field), NO_INDEX);
+		}
+	}
+
+	fn push_index(&mut self, index: usize) {
+		self.container = None;
+		self.push_front(None, index.min(NO_INDEX as usize - 1) as u32);
+	}
+
+	fn push_front(&mut self, field: Option<Name>, index: u32) {
+		if self.len as usize == MAX_FIELD_PATH_DEPTH {
+			self.len -= 1;
+			self.truncated = true;
+		}
+		for i in (0..(self.len as usize)).rev() {
+			self.fields[i + 1] = self.fields[i];
+			self.indices[i + 1] = self.indices[i];
+		}
+		self.fields[0] = field;
+		self.indices[0] = index;
+		self.len += 1;
+	}
+}
+
+impl fmt::Display for FieldPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some(container) = self.container() {
+			write!(f, "{}", container)?;
+		}
+		for (field, index) in self.segments() {
+			if let Some(field) = field {
+				write!(f, ".{}", field)?;
+			}
+			if let Some(index) = index {
+				write!(f, "[{}]", index)?;
+			}
+		}
+		if self.truncated {
+			write!(f, "...")?;
+		}
+		Ok(())
+	}
+}
@@ -27,30 +27,22 @@ mod series;
 mod size;
 mod fixed;
 mod variable;
+mod union;
+mod error;
 
 pub use bm_le::{Compact, CompactRef, MaxVec};
 pub use series::{Series, SeriesItem};
-pub use ssz_derive::{Codec, Encode, Decode};
+pub use ssz_derive::{Codec, Encode, Decode, UnionTree, View};
 
+pub use crate::error::{Error, ErrorCode, FieldPath, Name, MAX_FIELD_PATH_DEPTH};
 pub use crate::size::{Size, VariableSize, Add, Mul, Div};
+pub use crate::union::{
+	encode_union, decode_union, union_into_tree, empty_union_into_tree, union_from_tree,
+	OptionRef, OptionValue,
+};
 
 use alloc::vec::Vec;
 
-#[derive(Debug)]
-/// Error type for encoding and decoding.
-pub enum Error {
-	/// Incorrect size.
-	IncorrectSize,
-	/// Invalid type.
-	InvalidType,
-	/// Vector length is incorrect.
-	InvalidLength,
-	/// List length is too large.
-	ListTooLarge,
-	/// Other errors.
-	Other(&'static str),
-}
-
 #[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
 	fn from(_: std::io::Error) -> Error {
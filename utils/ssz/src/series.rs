@@ -97,6 +97,63 @@ impl Series {
 		Ok(Self(ret))
 	}
 
+	/// Find the byte ranges of values in a ssz vector, with given types,
+	/// without copying them. Offsets are validated before returning. The
+	/// length of types and ranges must equal to the length of values in the
+	/// vector.
+	pub fn vector_ranges(
+		value: &[u8],
+		typs: &[Option<usize>],
+		ranges: &mut [(usize, usize)],
+	) -> Result<(), Error> {
+		if typs.len() != ranges.len() {
+			return Err(Error::InvalidLength)
+		}
+
+		let offset_len = LengthOffset::default().using_encoded(|buf| buf.len());
+		let fixed_parts_size = typs.iter().fold(0, |acc, typ| {
+			acc + typ.unwrap_or(offset_len)
+		});
+		if value.len() < fixed_parts_size {
+			return Err(Error::SizeMismatch { expected: fixed_parts_size, actual: value.len() })
+		}
+
+		let mut pos = 0;
+		let mut last_variable: Option<usize> = None;
+		for i in 0..typs.len() {
+			match typs[i] {
+				Some(fixed_len) => {
+					ranges[i] = (pos, pos + fixed_len);
+					pos += fixed_len;
+				},
+				None => {
+					let offset = LengthOffset::decode(&value[pos..(pos + offset_len)])? as usize;
+					match last_variable {
+						None if offset != fixed_parts_size => return Err(Error::InvalidOffset { offset }),
+						Some(last) if offset < ranges[last].0 => return Err(Error::InvalidOffset { offset }),
+						_ => (),
+					}
+					if offset > value.len() {
+						return Err(Error::InvalidOffset { offset })
+					}
+
+					if let Some(last) = last_variable {
+						ranges[last].1 = offset;
+					}
+					ranges[i] = (offset, value.len());
+					last_variable = Some(i);
+					pos += offset_len;
+				},
+			}
+		}
+
+		if last_variable.is_none() && value.len() != fixed_parts_size {
+			return Err(Error::SizeMismatch { expected: fixed_parts_size, actual: value.len() })
+		}
+
+		Ok(())
+	}
+
 	/// Decode raw bytes as a ssz list, with the given type.
 	pub fn decode_list(value: &[u8], typ: Option<usize>) -> Result<Self, Error> {
 		let mut ret = Vec::new();
@@ -0,0 +1,136 @@
+use crate::{Encode, Decode, Error, Codec, VariableSize};
+use alloc::vec::Vec;
+
+/// Encode a union value, given its selector and the encoding of the selected
+/// value. `None` is only valid for selector `0`.
+pub fn encode_union(selector: u8, value: Option<Vec<u8>>) -> Vec<u8> {
+	let mut ret = Vec::new();
+	ret.push(selector);
+	if let Some(value) = value {
+		ret.extend(value);
+	}
+	ret
+}
+
+/// Split an encoded union value into its selector and the encoding of the
+/// selected value.
+pub fn decode_union(value: &[u8]) -> Result<(u8, &[u8]), Error> {
+	if value.is_empty() {
+		return Err(Error::IncorrectSize)
+	}
+	Ok((value[0], &value[1..]))
+}
+
+/// Merkleize a union value with the given selector.
+pub fn union_into_tree<T: bm_le::IntoTree, DB: bm_le::WriteBackend>(
+	selector: u8,
+	value: &T,
+	db: &mut DB,
+) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+	DB::Construct: bm_le::CompatibleConstruct,
+{
+	bm_le::utils::mix_in_type(value, db, selector as usize)
+}
+
+/// Merkleize an empty union value, which always has selector `0`.
+pub fn empty_union_into_tree<DB: bm_le::WriteBackend>(
+	db: &mut DB,
+) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+	DB::Construct: bm_le::CompatibleConstruct,
+{
+	// An empty value is merkleized as a zero chunk, which is the same as
+	// that of a zero integer.
+	union_into_tree(0, &0u64, db)
+}
+
+/// Split a merkleized union value into its selector and the root of the
+/// selected value.
+pub fn union_from_tree<DB: bm_le::ReadBackend>(
+	root: &<DB::Construct as bm_le::Construct>::Value,
+	db: &mut DB,
+) -> Result<(u8, <DB::Construct as bm_le::Construct>::Value), bm_le::Error<DB::Error>> where
+	DB::Construct: bm_le::CompatibleConstruct,
+{
+	bm_le::utils::decode_with_type(root, db, |inner, _db, selector| {
+		if selector > u8::max_value() as usize {
+			return Err(bm_le::Error::CorruptedDatabase)
+		}
+		Ok((selector as u8, inner.clone()))
+	})
+}
+
+impl<T> Codec for Option<T> {
+	type Size = VariableSize;
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode(&self) -> Vec<u8> {
+		match self {
+			None => encode_union(0, None),
+			Some(value) => encode_union(1, Some(value.encode())),
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(value: &[u8]) -> Result<Self, Error> {
+		match decode_union(value)? {
+			(0, value) => if value.is_empty() {
+				Ok(None)
+			} else {
+				Err(Error::IncorrectSize)
+			},
+			(1, value) => Ok(Some(T::decode(value)?)),
+			_ => Err(Error::InvalidType),
+		}
+	}
+}
+
+/// Reference to an `Option` merkleized as a union with selector `0` for
+/// `None` and `1` for `Some`. Tree traits cannot be implemented on `Option`
+/// itself outside of `bm_le`, so this wrapper is used instead.
+pub struct OptionRef<'a, T>(pub &'a Option<T>);
+
+impl<'a, T: bm_le::IntoTree> bm_le::IntoTree for OptionRef<'a, T> {
+	fn into_tree<DB: bm_le::WriteBackend>(
+		&self,
+		db: &mut DB,
+	) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+		DB::Construct: bm_le::CompatibleConstruct,
+	{
+		match self.0 {
+			None => empty_union_into_tree(db),
+			Some(value) => union_into_tree(1, value, db),
+		}
+	}
+}
+
+/// Owned `Option` merkleized as a union, as in `OptionRef`.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct OptionValue<T>(pub Option<T>);
+
+impl<T: bm_le::IntoTree> bm_le::IntoTree for OptionValue<T> {
+	fn into_tree<DB: bm_le::WriteBackend>(
+		&self,
+		db: &mut DB,
+	) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+		DB::Construct: bm_le::CompatibleConstruct,
+	{
+		OptionRef(&self.0).into_tree(db)
+	}
+}
+
+impl<T: bm_le::FromTree> bm_le::FromTree for OptionValue<T> {
+	fn from_tree<DB: bm_le::ReadBackend>(
+		root: &<DB::Construct as bm_le::Construct>::Value,
+		db: &mut DB,
+	) -> Result<Self, bm_le::Error<DB::Error>> where
+		DB::Construct: bm_le::CompatibleConstruct,
+	{
+		match union_from_tree(root, db)? {
+			(0, _) => Ok(OptionValue(None)),
+			(1, value) => Ok(OptionValue(Some(T::from_tree(&value, db)?))),
+			_ => Err(bm_le::Error::CorruptedDatabase),
+		}
+	}
+}
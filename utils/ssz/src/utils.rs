@@ -22,23 +22,34 @@ pub fn decode_list<T: Decode>(
 	let series = Series::decode_list(value, value_typ)?;
 	let mut ret = Vec::new();
 
-	for part in series.0 {
-		match part {
+	// Variable parts are laid out in order at the end of the encoding.
+	let mut offset = match value_typ {
+		Some(_) => 0,
+		None => value.len().saturating_sub(series.0.iter().map(|part| match part {
+			SeriesItem::Fixed(part) | SeriesItem::Variable(part) => part.len(),
+		}).sum::<usize>()),
+	};
+
+	for (index, part) in series.0.into_iter().enumerate() {
+		let part = match part {
 			SeriesItem::Fixed(fixed) => {
 				if <T as Codec>::Size::is_fixed() {
-					ret.push(T::decode(&fixed)?);
+					fixed
 				} else {
 					return Err(Error::InvalidType)
 				}
 			},
 			SeriesItem::Variable(variable) => {
 				if <T as Codec>::Size::is_variable() {
-					ret.push(T::decode(&variable)?);
+					variable
 				} else {
 					return Err(Error::InvalidType)
 				}
 			},
-		}
+		};
+
+		ret.push(T::decode(&part).map_err(|e| e.in_element(index, offset))?);
+		offset += part.len();
 	}
 
 	Ok(ret)
@@ -1,4 +1,6 @@
-use ssz::{Encode, Decode, MaxVec, Compact};
+use ssz::{Codec, Encode, Decode, View, UnionTree, OptionRef, Error, ErrorCode, MaxVec, Compact};
+use bm_le::tree_root;
+use sha2::{Digest, Sha256};
 use generic_array::GenericArray;
 use core::fmt::Debug;
 use typenum::*;
@@ -10,6 +12,39 @@ fn t<T: Debug + Encode + Decode + PartialEq>(value: T, expected: &[u8]) {
 	assert_eq!(value, decoded);
 }
 
+#[derive(Codec, Encode, Decode, UnionTree, PartialEq, Debug)]
+enum NoneOrUints {
+	None,
+	Short(u16),
+	Long(MaxVec<u32, U4>),
+}
+
+#[derive(Codec, Encode, Decode, View, PartialEq, Debug)]
+struct ViewTest {
+	a: u16,
+	b: MaxVec<u16, U8>,
+	c: u8,
+	d: MaxVec<u8, U8>,
+}
+
+#[derive(Codec, Encode, Decode, PartialEq, Debug)]
+struct FieldErrorTest {
+	a: u8,
+	b: bool,
+}
+
+#[derive(Codec, Encode, Decode, PartialEq, Debug)]
+struct NestedFieldErrorTest {
+	x: u16,
+	inner: FieldErrorTest,
+}
+
+#[derive(Codec, Encode, Decode, PartialEq, Debug)]
+struct ListFieldErrorTest {
+	x: u8,
+	items: MaxVec<FieldErrorTest, U4>,
+}
+
 #[test]
 fn spec() {
 	t(false, &[0x00]); // boolean F
@@ -92,3 +127,100 @@ fn spec() {
 			0x03]);
 	}
 }
+
+#[test]
+fn union() {
+	t(None::<u16>, &[0x00]);
+	t(Some(0xabcdu16), &[0x01, 0xcd, 0xab]);
+	t(Some(None::<u8>), &[0x01, 0x00]);
+
+	t(NoneOrUints::None, &[0x00]);
+	t(NoneOrUints::Short(0xabcd), &[0x01, 0xcd, 0xab]);
+	t(NoneOrUints::Long(MaxVec::from(vec![1, 2])), &[0x02, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00]);
+
+	assert!(<Option<u16>>::decode(&[]).is_err());
+	assert!(<Option<u16>>::decode(&[0x00, 0x00]).is_err());
+	assert!(<Option<u16>>::decode(&[0x02, 0xcd, 0xab]).is_err());
+	assert!(NoneOrUints::decode(&[0x03]).is_err());
+}
+
+fn hex_root(value: &str) -> Vec<u8> {
+	(0..32).map(|i| u8::from_str_radix(&value[(i * 2)..(i * 2 + 2)], 16).unwrap()).collect()
+}
+
+fn mix_in_selector(root: &[u8], selector: u8) -> Vec<u8> {
+	let mut selector_chunk = [0u8; 32];
+	selector_chunk[0] = selector;
+	Sha256::digest(&[root, &selector_chunk[..]].concat()).to_vec()
+}
+
+#[test]
+fn union_tree() {
+	let none = hex_root("f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b");
+	assert_eq!(mix_in_selector(&[0u8; 32], 0), none);
+	assert_eq!(&tree_root::<Sha256, _>(&OptionRef(&None::<u16>))[..], &none[..]);
+	assert_eq!(&tree_root::<Sha256, _>(&NoneOrUints::None)[..], &none[..]);
+
+	let mut short_chunk = [0u8; 32];
+	short_chunk[0] = 0xcd;
+	short_chunk[1] = 0xab;
+	let short = hex_root("33b66770925322794e3eda3516a66ec4c8e01b05c3605d1755772e79de68f5e1");
+	assert_eq!(mix_in_selector(&short_chunk, 1), short);
+	assert_eq!(&tree_root::<Sha256, _>(&OptionRef(&Some(0xabcdu16)))[..], &short[..]);
+	assert_eq!(&tree_root::<Sha256, _>(&NoneOrUints::Short(0xabcd))[..], &short[..]);
+
+	let long = hex_root("1eaa5d05ec860281a966beae4214c3e7d221e80d622cd2a040b0114279312a9c");
+	let list = MaxVec::<u32, U4>::from(vec![1, 2]);
+	assert_eq!(mix_in_selector(&tree_root::<Sha256, _>(&list)[..], 2), long);
+	assert_eq!(&tree_root::<Sha256, _>(&NoneOrUints::Long(list))[..], &long[..]);
+}
+
+#[test]
+fn view() {
+	let value = ViewTest {
+		a: 0xabcd,
+		b: MaxVec::from(vec![1, 2]),
+		c: 0xff,
+		d: MaxVec::from(vec![3]),
+	};
+	let encoded = value.encode();
+	assert_eq!(&encoded[..], &[
+		0xcd, 0xab, 0x0b, 0x00, 0x00, 0x00, 0xff, 0x0f, 0x00, 0x00, 0x00,
+		0x01, 0x00, 0x02, 0x00, 0x03,
+	][..]);
+
+	let view = ViewTestView::new(&encoded).unwrap();
+	assert_eq!(view.a().unwrap(), 0xabcd);
+	assert_eq!(view.b_bytes(), &[0x01, 0x00, 0x02, 0x00]);
+	assert_eq!(view.c().unwrap(), 0xff);
+	assert_eq!(view.d().unwrap(), MaxVec::from(vec![3]));
+	assert_eq!(view.decode().unwrap(), value);
+
+	let mut invalid = encoded.clone();
+	invalid[2] = 0x0c;
+	assert!(ViewTestView::new(&invalid).is_err());
+	let mut invalid = encoded.clone();
+	invalid[7] = 0x0a;
+	assert!(ViewTestView::new(&invalid).is_err());
+	assert!(ViewTestView::new(&encoded[..8]).is_err());
+}
+
+#[test]
+fn field_errors() {
+	let err = FieldErrorTest::decode(&[0x01, 0x02]).unwrap_err();
+	assert_eq!(err.code(), ErrorCode::InvalidType);
+	assert_eq!(format!("{}", err), "FieldErrorTest.b at byte 1: invalid type");
+
+	let err = NestedFieldErrorTest::decode(&[0x00, 0x00, 0x01, 0x02]).unwrap_err();
+	assert_eq!(format!("{}", err), "NestedFieldErrorTest.inner.b at byte 3: invalid type");
+
+	let err = ListFieldErrorTest::decode(&[0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x02]).unwrap_err();
+	assert_eq!(format!("{}", err), "ListFieldErrorTest.items[1].b at byte 8: invalid type");
+	assert!(core::mem::size_of::<Error>() <= 128);
+
+	let err = NestedFieldErrorTest::decode(&[0x00, 0x00, 0x01]).unwrap_err();
+	assert_eq!(err, Error::SizeMismatch { expected: 4, actual: 3 });
+
+	let err = ViewTest::decode(&[0xcd, 0xab, 0x0c, 0x00, 0x00, 0x00, 0xff, 0x0f, 0x00, 0x00, 0x00]).unwrap_err();
+	assert_eq!(err, Error::InvalidOffset { offset: 12 });
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+use clap::{App, Arg};
+use serde::de::DeserializeOwned;
+use ssz::Decode;
+use beacon::{Config, BeaconState, MainnetConfig, MinimalConfig};
+use yamltests::{read_raw_unwrap, read_value_unwrap};
+
+/// Read a state from an SSZ file, or a YAML file otherwise.
+fn read_state<C: Config + DeserializeOwned>(path: &Path) -> BeaconState<C> {
+	match path.extension().and_then(|extension| extension.to_str()) {
+		Some("ssz") => BeaconState::<C>::decode(&read_raw_unwrap(path))
+			.unwrap_or_else(|e| panic!("Decoding {} failed: {:?}", path.display(), e)),
+		_ => read_value_unwrap::<_, BeaconState<C>>(path),
+	}
+}
+
+fn diff<C: Config + DeserializeOwned>(left: &Path, right: &Path) -> bool {
+	let diff = beacon::diff::diff_states(
+		&read_state::<C>(left),
+		&read_state::<C>(right),
+	);
+	print!("{}", diff);
+	diff.is_empty()
+}
+
+fn main() {
+	let matches = App::new("statediff")
+        .version("0.1.0")
+        .author("Parity Technologies <admin@parity.io>")
+        .about("Compare two beacon states field by field")
+        .arg(Arg::with_name("LEFT")
+             .help("Left state, as .ssz or .yaml file")
+             .required(true))
+        .arg(Arg::with_name("RIGHT")
+             .help("Right state, as .ssz or .yaml file")
+             .required(true))
+        .arg(Arg::with_name("config")
+             .long("config")
+             .takes_value(true)
+             .possible_values(&["minimal", "mainnet"])
+             .default_value("minimal")
+             .help("Beacon chain config of the states"))
+        .get_matches();
+
+	let left = Path::new(matches.value_of("LEFT").expect("LEFT is required; qed"));
+	let right = Path::new(matches.value_of("RIGHT").expect("RIGHT is required; qed"));
+
+	let equal = match matches.value_of("config") {
+		Some("mainnet") => diff::<MainnetConfig>(left, right),
+		_ => diff::<MinimalConfig>(left, right),
+	};
+
+	if !equal {
+		std::process::exit(1);
+	}
+}
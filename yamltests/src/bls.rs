@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+use beacon::BLSConfig;
+use beacon::primitives::{H256, Signature, ValidatorId};
+use crypto::bls::{self, BLSVerification};
+use crypto::keystore::secret_from_bytes;
+use crate::read_value_unwrap;
+use crate::description::{BLSType, TestDescription, TestPhase};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Case<I, O> {
+	pub input: I,
+	pub output: O,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SignInput {
+	pub privkey: H256,
+	pub message: H256,
+	pub domain: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MsgHashInput {
+	pub message: H256,
+	pub domain: String,
+}
+
+pub fn test(typ: BLSType, desc: TestDescription) {
+	assert_eq!(desc.phase, TestPhase::Phase0);
+	let path = desc.path.clone().unwrap();
+
+	match typ {
+		BLSType::AggregatePubkeys => test_aggregate_pubkeys(path),
+		BLSType::AggregateSigs => test_aggregate_sigs(path),
+		BLSType::MsgHashCompressed => test_msg_hash_compressed(path),
+		BLSType::MsgHashUncompressed => test_msg_hash_uncompressed(path),
+		BLSType::PrivToPub => test_priv_to_pub(path),
+		BLSType::SignMsg => test_sign_msg(path),
+	}
+}
+
+fn read_case<I, O>(path: PathBuf) -> Case<I, O> where
+	Case<I, O>: serde::de::DeserializeOwned,
+{
+	let mut path = path;
+	path.push("data.yaml");
+
+	read_value_unwrap::<_, Case<I, O>>(path)
+}
+
+fn domain(value: &str) -> u64 {
+	let bytes = decode_hex(value);
+	let mut domain = [0u8; 8];
+	domain.copy_from_slice(&bytes);
+	u64::from_le_bytes(domain)
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+	hex::decode(value.trim_start_matches("0x")).expect("Invalid hex in test case")
+}
+
+pub fn test_aggregate_pubkeys(path: PathBuf) {
+	let case = read_case::<Vec<ValidatorId>, ValidatorId>(path);
+	assert_eq!(BLSVerification::aggregate_pubkeys(&case.input), case.output);
+}
+
+pub fn test_aggregate_sigs(path: PathBuf) {
+	let case = read_case::<Vec<Signature>, Signature>(path);
+	assert_eq!(BLSVerification::aggregate_signatures(&case.input), case.output);
+}
+
+pub fn test_priv_to_pub(path: PathBuf) {
+	let case = read_case::<H256, ValidatorId>(path);
+	let secret = secret_from_bytes(&case.input[..]).expect("Invalid secret key");
+	let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&secret).as_bytes()[..]);
+	assert_eq!(pubkey, case.output);
+}
+
+pub fn test_sign_msg(path: PathBuf) {
+	let case = read_case::<SignInput, Signature>(path);
+	let secret = secret_from_bytes(&case.input.privkey[..]).expect("Invalid secret key");
+	let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&secret).as_bytes()[..]);
+	let domain = domain(&case.input.domain);
+
+	let signature = Signature::from_slice(
+		&bls::Signature::new(&case.input.message[..], domain, &secret).as_bytes()[..]
+	);
+	assert_eq!(signature, case.output);
+	assert!(BLSVerification::verify(&pubkey, &case.input.message, &signature, domain));
+}
+
+pub fn test_msg_hash_compressed(path: PathBuf) {
+	let case = read_case::<MsgHashInput, Vec<String>>(path);
+	let point = bls::hash_on_g2(&case.input.message[..], domain(&case.input.domain));
+
+	let expected = case.output.iter()
+		.flat_map(|coordinate| decode_hex(coordinate))
+		.collect::<Vec<_>>();
+	assert_eq!(bls::compress_g2(&point), expected);
+}
+
+pub fn test_msg_hash_uncompressed(path: PathBuf) {
+	let case = read_case::<MsgHashInput, Vec<Vec<String>>>(path);
+	let mut point = bls::hash_on_g2(&case.input.message[..], domain(&case.input.domain));
+	point.affine();
+
+	let mut encoded = Vec::new();
+	for coordinate in &[point.getpx(), point.getpy(), point.getpz()] {
+		for component in &[coordinate.geta(), coordinate.getb()] {
+			let mut bytes = [0u8; 48];
+			component.tobytes(&mut bytes);
+			encoded.extend_from_slice(&bytes[..]);
+		}
+	}
+
+	let expected = case.output.iter()
+		.flat_map(|coordinate| coordinate.iter().flat_map(|component| decode_hex(component)))
+		.collect::<Vec<_>>();
+	assert_eq!(encoded, expected);
+}
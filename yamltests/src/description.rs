@@ -108,6 +108,7 @@ pub enum SszGenericType {
 	Boolean,
 	Containers,
 	Uints,
+	Union,
 }
 
 impl FromStr for SszGenericType {
@@ -121,6 +122,7 @@ impl FromStr for SszGenericType {
 			"boolean" => Ok(Self::Boolean),
 			"containers" => Ok(Self::Containers),
 			"uints" => Ok(Self::Uints),
+			"union" => Ok(Self::Union),
 			_ => Err(Error::InvalidType),
 		}
 	}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 use serde::{Serialize, de::DeserializeOwned};
-use beacon::{BeaconExecutive, Config, Error, MainnetConfig, MinimalConfig};
+use beacon::{BeaconExecutive, Config, Error, MainnetConfig, MinimalConfig, RuntimeConfig};
 use crate::{test_name, test_state_with, read_pre_post_unwrap};
 use crate::description::{EpochProcessingType, TestNetwork, TestDescription, TestPhase};
 
@@ -8,7 +8,7 @@ pub fn test(typ: EpochProcessingType, desc: TestDescription) {
 	match desc.network {
 		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
 		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
-		TestNetwork::General => unimplemented!("Not supported"),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
 	}
 }
 
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use ssz::Encode;
+use beacon::{Config, BeaconState, MinimalConfig, MainnetConfig, RuntimeConfig};
+use beacon::primitives::H256;
+use beacon::types::*;
+use crypto::bls::BLSVerification;
+use crate::{read_value_unwrap, read_raw_unwrap, assert_state_eq};
+use crate::description::{GenesisType, TestNetwork, TestDescription, TestPhase};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Meta {
+	pub deposits_count: usize,
+}
+
+pub fn test(typ: GenesisType, desc: TestDescription) {
+	match desc.network {
+		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
+		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
+	}
+}
+
+pub fn test_with_config<C: Config>(typ: GenesisType, desc: TestDescription) where
+	C: Serialize + DeserializeOwned,
+{
+	assert_eq!(desc.phase, TestPhase::Phase0);
+	let path = desc.path.clone().unwrap();
+
+	match typ {
+		GenesisType::Initialization => test_initialization::<C>(path),
+		GenesisType::Validity => test_validity::<C>(path),
+	}
+}
+
+pub fn test_initialization<C: Config>(path: PathBuf) where
+	C: DeserializeOwned,
+{
+	let eth1_block_hash = {
+		let mut path = path.clone();
+		path.push("eth1_block_hash.yaml");
+
+		read_value_unwrap::<_, H256>(path)
+	};
+
+	let eth1_timestamp = {
+		let mut path = path.clone();
+		path.push("eth1_timestamp.yaml");
+
+		read_value_unwrap::<_, u64>(path)
+	};
+
+	let meta = {
+		let mut path = path.clone();
+		path.push("meta.yaml");
+
+		read_value_unwrap::<_, Meta>(path)
+	};
+
+	let mut deposits = Vec::new();
+	for i in 0..meta.deposits_count {
+		let deposit = {
+			let mut path = path.clone();
+			path.push(&format!("deposits_{}.yaml", i));
+
+			read_value_unwrap::<_, Deposit>(path)
+		};
+
+		let deposit_ssz = {
+			let mut path = path.clone();
+			path.push(&format!("deposits_{}.ssz", i));
+
+			read_raw_unwrap(path)
+		};
+
+		assert_eq!(Encode::encode(&deposit), deposit_ssz);
+		deposits.push(deposit);
+	}
+
+	let expected = {
+		let mut path = path.clone();
+		path.push("state.yaml");
+
+		read_value_unwrap::<_, BeaconState<C>>(path)
+	};
+
+	let state = beacon::initialize_beacon_state_from_eth1::<C, BLSVerification>(
+		eth1_block_hash, eth1_timestamp, &deposits
+	).unwrap();
+	assert_state_eq(&state, &expected);
+}
+
+pub fn test_validity<C: Config>(path: PathBuf) where
+	C: DeserializeOwned,
+{
+	let genesis = {
+		let mut path = path.clone();
+		path.push("genesis.yaml");
+
+		read_value_unwrap::<_, BeaconState<C>>(path)
+	};
+
+	let is_valid = {
+		let mut path = path.clone();
+		path.push("is_valid.yaml");
+
+		read_value_unwrap::<_, bool>(path)
+	};
+
+	assert_eq!(beacon::is_valid_genesis_state(&genesis), is_valid);
+}
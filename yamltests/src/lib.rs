@@ -1,8 +1,13 @@
 pub mod description;
 pub mod ssz_static;
+pub mod ssz_generic;
 pub mod operations;
 pub mod sanity;
 pub mod epoch_processing;
+pub mod bls;
+pub mod genesis;
+pub mod shuffling;
+pub mod runner;
 
 use std::fs::File;
 use std::io::{self, BufReader, Read};
@@ -39,56 +44,64 @@ pub fn test_name<P: AsRef<Path>>(path: P) -> Result<String, Error> {
 	   + s0[4] + "/" + s0[5])
 }
 
-pub fn test(desc: TestDescription) {
+/// Run a test case, panicking on failure. Returns `false` if the case is
+/// not supported and was skipped.
+pub fn test(desc: TestDescription) -> bool {
 	match desc.typ {
 		TestType::SszStatic(typ) => ssz_static::test(typ, desc),
-		TestType::Operations(typ) => operations::test(typ, desc),
-		TestType::Sanity(typ) => sanity::test(typ, desc),
-		TestType::EpochProcessing(typ) => epoch_processing::test(typ, desc),
-		_ => println!("Skipped {}", test_name(desc.path.unwrap()).unwrap()),
+		TestType::SszGeneric(typ) => ssz_generic::test(typ, desc),
+		TestType::Operations(typ) => { operations::test(typ, desc); true },
+		TestType::Sanity(typ) => { sanity::test(typ, desc); true },
+		TestType::EpochProcessing(typ) => { epoch_processing::test(typ, desc); true },
+		TestType::Bls(typ) => { bls::test(typ, desc); true },
+		TestType::Genesis(typ) => { genesis::test(typ, desc); true },
+		TestType::Shuffling(typ) => { shuffling::test(typ, desc); true },
+	}
+}
+
+/// Assert that the state equals the expected state, reporting the differing
+/// fields on failure.
+pub fn assert_state_eq<C: Config>(state: &BeaconState<C>, expected: &BeaconState<C>) {
+	if state != expected {
+		panic!("State mismatch:\n{}", beacon::diff::diff_states(state, expected));
 	}
 }
 
 pub fn test_state_with<C: Config, F: FnOnce(&mut BeaconExecutive<C>) -> Result<(), beacon::Error>>(
 	description: &str, pre: &BeaconState<C>, post: Option<&BeaconState<C>>, f: F
 ) {
-	print!("Running test: {} ...", description);
-
 	let mut state = pre.clone();
 	let mut executive = BeaconExecutive::new(&mut state);
 
 	match f(&mut executive) {
-		Ok(()) => {
-			print!(" accepted");
-
-			let post = post.unwrap().clone();
-			assert_eq!(state, post);
-			print!(" passed");
-		}
-		Err(e) => {
-			print!(" rejected({:?})", e);
-
-			assert!(post.is_none());
-			print!(" passed");
-		}
+		Ok(()) => match post {
+			Some(post) => assert_state_eq(&state, post),
+			None => panic!("{}: accepted, but expected to be rejected", description),
+		},
+		Err(e) => if post.is_some() {
+			panic!("{}: rejected({:?}), but expected to be accepted", description, e)
+		},
 	}
-
-	println!("");
 }
 
 pub fn read_raw_unwrap<P: AsRef<Path>>(path: P) -> Vec<u8> {
-	File::open(path).expect("Open serialized failed")
-		.bytes()
-		.map(|v| v.unwrap())
-		.collect::<Vec<_>>()
+	let path = path.as_ref();
+	let mut raw = Vec::new();
+	File::open(path)
+		.and_then(|mut file| file.read_to_end(&mut raw))
+		.unwrap_or_else(|e| panic!("Reading {} failed: {}", path.display(), e));
+	raw
 }
 
 pub fn read_value_unwrap<P: AsRef<Path>, T>(path: P) -> T where
 	T: DeserializeOwned
 {
-	let file = File::open(path).expect("Open roots failed");
+	let path = path.as_ref();
+	let file = File::open(path)
+		.unwrap_or_else(|e| panic!("Opening {} failed: {}", path.display(), e));
 	let reader = BufReader::new(file);
-	serde_yaml::from_reader::<_, T>(reader).expect("Parse roots failed")
+	serde_yaml::from_reader::<_, T>(reader)
+		.unwrap_or_else(|e| panic!("Parsing {} failed: {}", path.display(), e))
 }
 
 pub fn read_pre_post_unwrap<C: Config>(path: PathBuf) -> (BeaconState<C>, Option<BeaconState<C>>) where
@@ -148,8 +161,14 @@ mod tests {
 		};
 
 		let descs = crate::description::read_descriptions(dir).unwrap();
-		for desc in descs {
-			crate::test(desc);
-		}
+		let results = crate::runner::run_all(descs, &Default::default());
+		let failures = results.iter()
+			.filter_map(|result| match &result.outcome {
+				crate::runner::Outcome::Failed(message) => Some(format!("{}: {}", result.name, message)),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		assert!(failures.is_empty(), "Failed cases:\n{}", failures.join("\n"));
 	}
 }
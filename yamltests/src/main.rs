@@ -1,4 +1,9 @@
+use std::fs::File;
+use std::str::FromStr;
 use clap::{App, Arg};
+use beacon::{MinimalConfig, RuntimeConfig, RuntimeParameters};
+use yamltests::description::{TestNetwork, TestPhase};
+use yamltests::runner::{self, Filter, Outcome, Summary};
 
 fn main() {
 	let matches = App::new("yamltests")
@@ -8,12 +13,99 @@ fn main() {
         .arg(Arg::with_name("DIR")
              .help("Target yaml files to import")
              .required(true))
+        .arg(Arg::with_name("network")
+             .long("network")
+             .takes_value(true)
+             .possible_values(&["general", "mainnet", "minimal"])
+             .help("Only run tests of the given network"))
+        .arg(Arg::with_name("phase")
+             .long("phase")
+             .takes_value(true)
+             .possible_values(&["phase0"])
+             .help("Only run tests of the given phase"))
+        .arg(Arg::with_name("type")
+             .long("type")
+             .takes_value(true)
+             .help("Only run tests of the given type, such as `operations` or `operations/deposit`"))
+        .arg(Arg::with_name("name")
+             .long("name")
+             .takes_value(true)
+             .help("Only run tests whose full name matches the given glob"))
+        .arg(Arg::with_name("jobs")
+             .long("jobs")
+             .short("j")
+             .takes_value(true)
+             .help("Number of tests to run in parallel"))
+        .arg(Arg::with_name("junit")
+             .long("junit")
+             .takes_value(true)
+             .help("Write a JUnit XML report to the given path"))
+        .arg(Arg::with_name("json")
+             .long("json")
+             .takes_value(true)
+             .help("Write a JSON report to the given path"))
+        .arg(Arg::with_name("config-file")
+             .long("config-file")
+             .takes_value(true)
+             .help("Spec config file, on top of the minimal config, to run general tests with"))
         .get_matches();
 
+	let filter = Filter {
+		network: matches.value_of("network")
+			.map(|network| TestNetwork::from_str(network).expect("Network is validated by clap; qed")),
+		phase: matches.value_of("phase")
+			.map(|phase| TestPhase::from_str(phase).expect("Phase is validated by clap; qed")),
+		typ: matches.value_of("type").map(|typ| typ.to_string()),
+		name: matches.value_of("name")
+			.map(|name| glob::Pattern::new(name).expect("Invalid name glob")),
+	};
+
+	if let Some(path) = matches.value_of("config-file") {
+		let parameters: RuntimeParameters = serde_yaml::from_reader(
+			File::open(path).expect("Opening config file failed")
+		).expect("Parsing config file failed");
+		RuntimeConfig::<MinimalConfig>::install(parameters).expect("Invalid config file");
+	}
+
+	if let Some(jobs) = matches.value_of("jobs") {
+		rayon::ThreadPoolBuilder::new()
+			.num_threads(jobs.parse().expect("Invalid number of jobs"))
+			.build_global()
+			.expect("Global thread pool is only built once; qed");
+	}
+
+	// Failures are collected, with their location and backtrace, and
+	// reported in the summary.
+	runner::install_panic_hook();
+
 	let dir = matches.value_of("DIR").unwrap();
 	let descs = yamltests::description::read_descriptions(dir).unwrap();
+	let results = runner::run_all(descs, &filter);
+
+	for result in &results {
+		match &result.outcome {
+			Outcome::Passed => println!("passed  {}", result.name),
+			Outcome::Skipped => println!("skipped {}", result.name),
+			Outcome::Failed(message) => println!("FAILED  {}: {}", result.name, message),
+		}
+	}
+
+	let summary = Summary::new(&results);
+	println!(
+		"\n{} passed, {} failed, {} skipped",
+		summary.passed, summary.failed, summary.skipped,
+	);
+
+	if let Some(path) = matches.value_of("junit") {
+		runner::write_junit(&results, File::create(path).expect("Creating JUnit report failed"))
+			.expect("Writing JUnit report failed");
+	}
+	if let Some(path) = matches.value_of("json") {
+		runner::write_json(&results, File::create(path).expect("Creating JSON report failed"))
+			.expect("Writing JSON report failed");
+	}
 
-	for desc in descs {
-		yamltests::test(desc);
+	if summary.failed > 0 {
+		std::process::exit(1);
 	}
 }
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use serde::{Serialize, de::DeserializeOwned};
 use ssz::{Encode, Decode};
 use bm_le::{FromTree, IntoTree};
-use beacon::{BeaconExecutive, Config, Error, MainnetConfig, MinimalConfig};
+use beacon::{BeaconExecutive, Config, Error, MainnetConfig, MinimalConfig, RuntimeConfig};
 use beacon::types::*;
 use crypto::bls::BLSVerification;
 use crate::{test_name, read_raw_unwrap, read_value_unwrap, test_state_with, read_pre_post_unwrap};
@@ -13,7 +13,7 @@ pub fn test(typ: OperationsType, desc: TestDescription) {
 	match desc.network {
 		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
 		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
-		TestNetwork::General => unimplemented!("Not supported"),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
 	}
 }
 
@@ -0,0 +1,306 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use backtrace::Backtrace;
+use glob::Pattern;
+use rayon::prelude::*;
+use serde::Serialize;
+use crate::description::{TestDescription, TestNetwork, TestPhase};
+use crate::test_name;
+
+/// Outcome of a single test case.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", content = "message", rename_all = "lowercase")]
+pub enum Outcome {
+	Passed,
+	Skipped,
+	Failed(String),
+}
+
+/// Result of a single test case.
+#[derive(Serialize, Debug, Clone)]
+pub struct CaseResult {
+	pub name: String,
+	pub outcome: Outcome,
+	#[serde(serialize_with = "serialize_duration")]
+	pub duration: Duration,
+}
+
+fn serialize_duration<S: serde::Serializer>(
+	duration: &Duration,
+	serializer: S
+) -> Result<S::Ok, S::Error> {
+	serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Filter of test cases to run.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+	pub network: Option<TestNetwork>,
+	pub phase: Option<TestPhase>,
+	/// Test type prefix, such as `operations` or `operations/deposit`.
+	pub typ: Option<String>,
+	/// Glob matched against the full test name.
+	pub name: Option<Pattern>,
+}
+
+impl Filter {
+	/// Whether the test case matches the filter.
+	pub fn matches(&self, desc: &TestDescription) -> bool {
+		let name = match desc.path.as_ref().and_then(|path| test_name(path).ok()) {
+			Some(name) => name,
+			None => return false,
+		};
+		let typ = name.split('/').skip(2).take(2).collect::<Vec<_>>().join("/");
+
+		self.network.map(|network| network == desc.network).unwrap_or(true) &&
+			self.phase.map(|phase| phase == desc.phase).unwrap_or(true) &&
+			self.typ.as_ref().map(|prefix| {
+				typ == *prefix || typ.starts_with(&format!("{}/", prefix))
+			}).unwrap_or(true) &&
+			self.name.as_ref().map(|pattern| pattern.matches(&name)).unwrap_or(true)
+	}
+}
+
+/// Summary counts of test results.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+	pub passed: usize,
+	pub failed: usize,
+	pub skipped: usize,
+}
+
+impl Summary {
+	/// Count the given results.
+	pub fn new(results: &[CaseResult]) -> Self {
+		let mut summary = Self::default();
+		for result in results {
+			match result.outcome {
+				Outcome::Passed => summary.passed += 1,
+				Outcome::Failed(_) => summary.failed += 1,
+				Outcome::Skipped => summary.skipped += 1,
+			}
+		}
+		summary
+	}
+}
+
+thread_local! {
+	/// Location and backtrace of the last panic on this thread.
+	static LAST_PANIC: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Install a panic hook that records the location and backtrace of panics
+/// instead of printing them, so that they are reported with the failed case.
+pub fn install_panic_hook() {
+	panic::set_hook(Box::new(|info| {
+		let location = info.location()
+			.map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column()))
+			.unwrap_or_else(|| "unknown location".to_string());
+		let details = format!("at {}\n{:?}", location, Backtrace::new());
+		LAST_PANIC.with(|last| *last.borrow_mut() = Some(details));
+	}));
+}
+
+/// Run a single test case, catching failures.
+pub fn run(desc: TestDescription) -> CaseResult {
+	let name = desc.path.as_ref()
+		.and_then(|path| test_name(path).ok())
+		.unwrap_or_else(|| format!("{}/{}", desc.origin, desc.name));
+
+	LAST_PANIC.with(|last| *last.borrow_mut() = None);
+	let start = Instant::now();
+	let outcome = match panic::catch_unwind(AssertUnwindSafe(|| crate::test(desc))) {
+		Ok(true) => Outcome::Passed,
+		Ok(false) => Outcome::Skipped,
+		Err(payload) => Outcome::Failed(panic_message(
+			payload,
+			LAST_PANIC.with(|last| last.borrow_mut().take()),
+		)),
+	};
+
+	CaseResult { name, outcome, duration: start.elapsed() }
+}
+
+/// Run all matching test cases in parallel. Results are sorted by name.
+pub fn run_all(descs: Vec<TestDescription>, filter: &Filter) -> Vec<CaseResult> {
+	let mut results = descs.into_par_iter()
+		.filter(|desc| filter.matches(desc))
+		.map(run)
+		.collect::<Vec<_>>();
+	results.sort_by(|a, b| a.name.cmp(&b.name));
+	results
+}
+
+fn panic_message(payload: Box<dyn Any + Send>, details: Option<String>) -> String {
+	let message = if let Some(message) = payload.downcast_ref::<&str>() {
+		message.to_string()
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		"Unknown panic".to_string()
+	};
+
+	match details {
+		Some(details) => format!("{} {}", message, details),
+		None => message,
+	}
+}
+
+/// Write results as JSON.
+pub fn write_json<W: Write>(results: &[CaseResult], writer: W) -> io::Result<()> {
+	#[derive(Serialize)]
+	struct Report<'a> {
+		summary: Summary,
+		cases: &'a [CaseResult],
+	}
+
+	serde_json::to_writer_pretty(writer, &Report { summary: Summary::new(results), cases: results })
+		.map_err(Into::into)
+}
+
+/// Write results as JUnit XML.
+pub fn write_junit<W: Write>(results: &[CaseResult], mut writer: W) -> io::Result<()> {
+	let summary = Summary::new(results);
+	let time = results.iter().map(|result| result.duration.as_secs_f64()).sum::<f64>();
+
+	let mut xml = String::new();
+	writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).expect("Writing to string never fails; qed");
+	writeln!(
+		xml, r#"<testsuite name="ethtests" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+		results.len(), summary.failed, summary.skipped, time,
+	).expect("Writing to string never fails; qed");
+	for result in results {
+		let (classname, name) = match result.name.rfind('/') {
+			Some(index) => (&result.name[..index], &result.name[(index + 1)..]),
+			None => ("", &result.name[..]),
+		};
+		write!(
+			xml, r#"  <testcase classname="{}" name="{}" time="{:.3}""#,
+			escape(classname), escape(name), result.duration.as_secs_f64(),
+		).expect("Writing to string never fails; qed");
+		match &result.outcome {
+			Outcome::Passed => writeln!(xml, "/>"),
+			Outcome::Skipped => writeln!(xml, "><skipped/></testcase>"),
+			Outcome::Failed(message) => writeln!(
+				xml, r#"><failure message="{}">{}</failure></testcase>"#,
+				escape(message.lines().next().unwrap_or("")), escape(message),
+			),
+		}.expect("Writing to string never fails; qed");
+	}
+	writeln!(xml, "</testsuite>").expect("Writing to string never fails; qed");
+
+	writer.write_all(xml.as_bytes())
+}
+
+fn escape(value: &str) -> String {
+	value.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+		.replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+	use std::path::PathBuf;
+
+	fn desc(name: &str) -> TestDescription {
+		let mut desc = TestDescription::from_str(name).unwrap();
+		desc.path = Some(PathBuf::from(format!("/res/ethtests/tests/{}", name)));
+		desc
+	}
+
+	fn results() -> Vec<CaseResult> {
+		vec![
+			CaseResult {
+				name: "minimal/phase0/operations/deposit/pyspec_tests/new_deposit".to_string(),
+				outcome: Outcome::Passed,
+				duration: Duration::from_millis(1500),
+			},
+			CaseResult {
+				name: "minimal/phase0/sanity/blocks/pyspec_tests/<empty>".to_string(),
+				outcome: Outcome::Failed("assertion failed: \"a\" & b\nbacktrace".to_string()),
+				duration: Duration::from_millis(500),
+			},
+			CaseResult {
+				name: "mainnet/phase0/shuffling/core/shuffle/shuffle_0".to_string(),
+				outcome: Outcome::Skipped,
+				duration: Duration::from_millis(0),
+			},
+		]
+	}
+
+	#[test]
+	fn filter_matches() {
+		let deposit = desc("minimal/phase0/operations/deposit/pyspec_tests/new_deposit");
+		let exit = desc("mainnet/phase0/operations/voluntary_exit/pyspec_tests/success");
+
+		assert!(Filter::default().matches(&deposit));
+
+		let filter = Filter { network: Some(TestNetwork::Minimal), ..Default::default() };
+		assert!(filter.matches(&deposit));
+		assert!(!filter.matches(&exit));
+
+		let filter = Filter { typ: Some("operations".to_string()), ..Default::default() };
+		assert!(filter.matches(&deposit));
+		assert!(filter.matches(&exit));
+
+		let filter = Filter { typ: Some("operations/deposit".to_string()), ..Default::default() };
+		assert!(filter.matches(&deposit));
+		assert!(!filter.matches(&exit));
+
+		let filter = Filter { typ: Some("operations/dep".to_string()), ..Default::default() };
+		assert!(!filter.matches(&deposit));
+
+		let filter = Filter { name: Some(Pattern::new("*/success").unwrap()), ..Default::default() };
+		assert!(!filter.matches(&deposit));
+		assert!(filter.matches(&exit));
+
+		let mut unnamed = deposit.clone();
+		unnamed.path = None;
+		assert!(!Filter::default().matches(&unnamed));
+	}
+
+	#[test]
+	fn writes_junit() {
+		let mut out = Vec::new();
+		write_junit(&results(), &mut out).unwrap();
+
+		assert_eq!(String::from_utf8(out).unwrap(), concat!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+			"<testsuite name=\"ethtests\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"2.000\">\n",
+			"  <testcase classname=\"minimal/phase0/operations/deposit/pyspec_tests\" name=\"new_deposit\" time=\"1.500\"/>\n",
+			"  <testcase classname=\"minimal/phase0/sanity/blocks/pyspec_tests\" name=\"&lt;empty&gt;\" time=\"0.500\">",
+			"<failure message=\"assertion failed: &quot;a&quot; &amp; b\">assertion failed: &quot;a&quot; &amp; b\nbacktrace</failure></testcase>\n",
+			"  <testcase classname=\"mainnet/phase0/shuffling/core/shuffle\" name=\"shuffle_0\" time=\"0.000\"><skipped/></testcase>\n",
+			"</testsuite>\n",
+		));
+	}
+
+	#[test]
+	fn writes_json() {
+		let mut out = Vec::new();
+		write_json(&results(), &mut out).unwrap();
+
+		let report: serde_json::Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(report["summary"], serde_json::json!({ "passed": 1, "failed": 1, "skipped": 1 }));
+		assert_eq!(report["cases"][0]["outcome"]["status"], "passed");
+		assert_eq!(report["cases"][0]["duration"], 1.5);
+		assert_eq!(report["cases"][1]["outcome"]["status"], "failed");
+		assert_eq!(report["cases"][1]["outcome"]["message"], "assertion failed: \"a\" & b\nbacktrace");
+		assert_eq!(report["cases"][2]["name"], "mainnet/phase0/shuffling/core/shuffle/shuffle_0");
+	}
+
+	#[test]
+	fn reports_panic_location() {
+		let message = panic_message(Box::new("failed"), Some("at src/lib.rs:1:1".to_string()));
+		assert_eq!(message, "failed at src/lib.rs:1:1");
+		assert_eq!(panic_message(Box::new(1u8), None), "Unknown panic");
+	}
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize, de::DeserializeOwned};
 use ssz::Encode;
-use beacon::{Config, BeaconState, MinimalConfig, MainnetConfig};
+use beacon::{Config, BeaconState, MinimalConfig, MainnetConfig, RuntimeConfig};
 use beacon::types::*;
 use crypto::bls::BLSVerification;
 use crate::{test_state_with, test_name, read_value_unwrap, read_raw_unwrap, read_pre_post_unwrap};
@@ -17,7 +17,7 @@ pub fn test(typ: SanityType, desc: TestDescription) {
 	match desc.network {
 		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
 		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
-		TestNetwork::General => unimplemented!("Not supported"),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
 	}
 }
 
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use beacon::{Config, MainnetConfig, MinimalConfig, RuntimeConfig, utils};
+use beacon::primitives::H256;
+use crate::read_value_unwrap;
+use crate::description::{ShufflingType, TestNetwork, TestDescription, TestPhase};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+	pub seed: H256,
+	pub count: u64,
+	pub mapping: Vec<u64>,
+}
+
+pub fn test(typ: ShufflingType, desc: TestDescription) {
+	match desc.network {
+		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
+		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
+	}
+}
+
+pub fn test_with_config<C: Config>(typ: ShufflingType, desc: TestDescription) where
+	C: Serialize + DeserializeOwned,
+{
+	assert_eq!(desc.phase, TestPhase::Phase0);
+	let path = desc.path.clone().unwrap();
+
+	match typ {
+		ShufflingType::Core => test_core::<C>(path),
+	}
+}
+
+pub fn test_core<C: Config>(path: PathBuf) {
+	let mapping = {
+		let mut path = path.clone();
+		path.push("mapping.yaml");
+
+		read_value_unwrap::<_, Mapping>(path)
+	};
+
+	for (index, expected) in mapping.mapping.iter().enumerate() {
+		assert_eq!(
+			utils::shuffled_index::<C>(index as u64, mapping.count, mapping.seed).unwrap(),
+			*expected,
+		);
+	}
+
+	let indices = (0..mapping.count).collect::<Vec<_>>();
+	assert_eq!(
+		utils::shuffle_list::<C>(indices.clone(), mapping.seed).unwrap(),
+		mapping.mapping,
+	);
+	assert_eq!(
+		utils::compute_committee::<C>(&indices, mapping.seed, 0, 1).unwrap(),
+		mapping.mapping,
+	);
+}
@@ -0,0 +1,270 @@
+use std::path::Path;
+use serde_yaml::Value;
+use beacon::primitives::H256;
+use bm_le::{IntoTree, FromTree, CompactRef, tree_root};
+use ssz::{Codec, Encode, Decode, UnionTree, Compact, MaxVec, OptionRef};
+use sha2::Sha256;
+use typenum::*;
+use vecarray::VecArray;
+use crate::{read_raw_unwrap, read_value_unwrap};
+use crate::ssz_static::Roots;
+use crate::description::{SszGenericType, TestDescription, TestNetwork, TestPhase};
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct SingleFieldTestStruct {
+	pub a: u8,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct SmallTestStruct {
+	pub a: u16,
+	pub b: u16,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct FixedTestStruct {
+	pub a: u8,
+	pub b: u64,
+	pub c: u32,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct VarTestStruct {
+	pub a: u16,
+	#[bm(compact)]
+	pub b: MaxVec<u16, U1024>,
+	pub c: u8,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct ComplexTestStruct {
+	pub a: u16,
+	#[bm(compact)]
+	pub b: MaxVec<u16, U128>,
+	pub c: u8,
+	#[bm(compact)]
+	pub d: MaxVec<u8, U256>,
+	pub e: VarTestStruct,
+	pub f: VecArray<FixedTestStruct, U4>,
+	pub g: VecArray<VarTestStruct, U2>,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+pub struct BitsStruct {
+	#[bm(compact)]
+	pub a: MaxVec<bool, U5>,
+	#[bm(compact)]
+	pub b: VecArray<bool, U2>,
+	#[bm(compact)]
+	pub c: VecArray<bool, U1>,
+	#[bm(compact)]
+	pub d: MaxVec<bool, U6>,
+	#[bm(compact)]
+	pub e: VecArray<bool, U8>,
+}
+
+#[derive(Codec, Encode, Decode, UnionTree, Clone, PartialEq, Eq, Debug)]
+pub enum UnionTestStruct {
+	None,
+	A(u16),
+	B(SmallTestStruct),
+	C(VarTestStruct),
+}
+
+/// Runner of a single generic ssz case, given the case path and whether the
+/// case is valid.
+type Runner = fn(&Path, bool);
+
+macro_rules! with_length {
+	( $length:expr, $f:ident $(, $t:ty )* ) => {
+		match $length {
+			"1" => Some($f::<$( $t, )* U1> as Runner),
+			"2" => Some($f::<$( $t, )* U2> as Runner),
+			"3" => Some($f::<$( $t, )* U3> as Runner),
+			"4" => Some($f::<$( $t, )* U4> as Runner),
+			"5" => Some($f::<$( $t, )* U5> as Runner),
+			"6" => Some($f::<$( $t, )* U6> as Runner),
+			"8" => Some($f::<$( $t, )* U8> as Runner),
+			"16" => Some($f::<$( $t, )* U16> as Runner),
+			"31" => Some($f::<$( $t, )* U31> as Runner),
+			"512" => Some($f::<$( $t, )* U512> as Runner),
+			"513" => Some($f::<$( $t, )* U513> as Runner),
+			_ => None,
+		}
+	}
+}
+
+pub fn test(typ: SszGenericType, desc: TestDescription) -> bool {
+	assert_eq!(desc.network, TestNetwork::General);
+	assert_eq!(desc.phase, TestPhase::Phase0);
+	let path = desc.path.clone().unwrap();
+	let valid = match desc.origin.as_str() {
+		"valid" => true,
+		"invalid" => false,
+		_ => panic!("Unknown ssz_generic origin {}", desc.origin),
+	};
+
+	let parts = desc.name.split('_').collect::<Vec<_>>();
+	let runner: Option<Runner> = match typ {
+		SszGenericType::Boolean => Some(test_basic::<bool> as Runner),
+		SszGenericType::Uints => match parts.get(1).cloned() {
+			Some("8") => Some(test_uint::<u8> as Runner),
+			Some("16") => Some(test_uint::<u16> as Runner),
+			Some("32") => Some(test_uint::<u32> as Runner),
+			Some("64") => Some(test_uint::<u64> as Runner),
+			Some("128") => Some(test_uint::<u128> as Runner),
+			Some("256") => Some(test_basic::<H256> as Runner),
+			_ => None,
+		},
+		SszGenericType::BasicVector => match (parts.get(1).cloned(), parts.get(2).cloned()) {
+			(Some("bool"), Some(length)) => with_length!(length, test_bool_vector),
+			(Some("uint8"), Some(length)) => with_length!(length, test_basic_vector, u8),
+			(Some("uint16"), Some(length)) => with_length!(length, test_basic_vector, u16),
+			(Some("uint32"), Some(length)) => with_length!(length, test_basic_vector, u32),
+			(Some("uint64"), Some(length)) => with_length!(length, test_basic_vector, u64),
+			(Some("uint128"), Some(length)) => with_length!(length, test_basic_vector, u128),
+			(Some("uint256"), Some(length)) => with_length!(length, test_hash_vector),
+			_ => None,
+		},
+		SszGenericType::Bitlist => match parts.get(1).cloned() {
+			Some(length) => with_length!(length, test_bitlist),
+			None => None,
+		},
+		SszGenericType::Bitvector => match parts.get(1).cloned() {
+			Some(length) => with_length!(length, test_bitvector),
+			None => None,
+		},
+		SszGenericType::Containers => match parts.get(0).cloned() {
+			Some("SingleFieldTestStruct") => Some(test_basic::<SingleFieldTestStruct> as Runner),
+			Some("SmallTestStruct") => Some(test_basic::<SmallTestStruct> as Runner),
+			Some("FixedTestStruct") => Some(test_basic::<FixedTestStruct> as Runner),
+			Some("VarTestStruct") => Some(test_basic::<VarTestStruct> as Runner),
+			Some("ComplexTestStruct") => Some(test_basic::<ComplexTestStruct> as Runner),
+			Some("BitsStruct") => Some(test_basic::<BitsStruct> as Runner),
+			_ => None,
+		},
+		SszGenericType::Union => match parts.get(0).cloned() {
+			Some("OptionUint16") => Some(test_option::<u16> as Runner),
+			Some("OptionSmallTestStruct") => Some(test_option::<SmallTestStruct> as Runner),
+			Some("UnionTestStruct") => Some(test_basic::<UnionTestStruct> as Runner),
+			_ => None,
+		},
+	};
+
+	match runner {
+		Some(runner) => {
+			runner(&path, valid);
+			true
+		},
+		None => false,
+	}
+}
+
+/// Check a case against the given type. Valid cases must round-trip and
+/// match the expected root, and invalid cases must fail to decode.
+fn check<T, F>(path: &Path, valid: bool, root: F) -> Option<T> where
+	T: Encode + Decode,
+	F: FnOnce(&T) -> H256,
+{
+	let serialized = read_raw_unwrap(path.join("serialized.ssz"));
+
+	if !valid {
+		assert!(T::decode(&serialized).is_err());
+		return None
+	}
+
+	let value = T::decode(&serialized).unwrap();
+	assert_eq!(Encode::encode(&value), serialized);
+
+	let roots = read_value_unwrap::<_, Roots>(path.join("meta.yaml"));
+	assert_eq!(root(&value), roots.root);
+
+	Some(value)
+}
+
+fn read_yaml(path: &Path) -> Value {
+	read_value_unwrap::<_, Value>(path.join("value.yaml"))
+}
+
+/// Read an unsigned integer, which may be quoted in the test files.
+fn yaml_uint(value: &Value) -> u128 {
+	match value {
+		Value::Number(number) => number.as_u64().expect("Invalid integer") as u128,
+		Value::String(string) => string.parse().expect("Invalid integer"),
+		_ => panic!("Invalid integer {:?}", value),
+	}
+}
+
+fn test_basic<T>(path: &Path, valid: bool) where
+	T: Encode + Decode + IntoTree,
+{
+	check::<T, _>(path, valid, |value| tree_root::<Sha256, _>(value));
+}
+
+fn test_option<T>(path: &Path, valid: bool) where
+	T: Encode + Decode + IntoTree,
+{
+	check::<Option<T>, _>(path, valid, |value| tree_root::<Sha256, _>(&OptionRef(value)));
+}
+
+fn test_uint<T>(path: &Path, valid: bool) where
+	T: Encode + Decode + IntoTree + Copy + Into<u128>,
+{
+	if let Some(value) = check::<T, _>(path, valid, |value| tree_root::<Sha256, _>(value)) {
+		assert_eq!(value.into(), yaml_uint(&read_yaml(path)));
+	}
+}
+
+fn test_basic_vector<T, L: Unsigned>(path: &Path, valid: bool) where
+	VecArray<T, L>: Encode + Decode,
+	for<'a> CompactRef<'a, VecArray<T, L>>: IntoTree,
+	T: Copy + Into<u128>,
+{
+	let value = check::<VecArray<T, L>, _>(path, valid, |value| {
+		tree_root::<Sha256, _>(&CompactRef(value))
+	});
+
+	if let Some(value) = value {
+		let expected = read_yaml(path).as_sequence().expect("Invalid vector")
+			.iter().map(yaml_uint).collect::<Vec<_>>();
+		assert_eq!(value.iter().map(|v| (*v).into()).collect::<Vec<u128>>(), expected);
+	}
+}
+
+fn test_bool_vector<L: Unsigned>(path: &Path, valid: bool) where
+	VecArray<bool, L>: Encode + Decode,
+	for<'a> CompactRef<'a, VecArray<u8, L>>: IntoTree,
+{
+	// Boolean vectors are packed one byte per item, the same as `uint8`.
+	let value = check::<VecArray<bool, L>, _>(path, valid, |value| {
+		let mut bytes = VecArray::<u8, L>::default();
+		for (i, v) in value.iter().enumerate() {
+			bytes[i] = *v as u8;
+		}
+		tree_root::<Sha256, _>(&CompactRef(&bytes))
+	});
+
+	if let Some(value) = value {
+		let expected = read_yaml(path).as_sequence().expect("Invalid vector")
+			.iter().map(|v| v.as_bool().expect("Invalid boolean")).collect::<Vec<_>>();
+		assert_eq!(&value[..], &expected[..]);
+	}
+}
+
+fn test_hash_vector<L: Unsigned>(path: &Path, valid: bool) where
+	VecArray<H256, L>: Encode + Decode + IntoTree,
+{
+	check::<VecArray<H256, L>, _>(path, valid, |value| tree_root::<Sha256, _>(value));
+}
+
+fn test_bitlist<L: Unsigned>(path: &Path, valid: bool) where
+	Compact<MaxVec<bool, L>>: Encode + Decode + IntoTree,
+{
+	check::<Compact<MaxVec<bool, L>>, _>(path, valid, |value| tree_root::<Sha256, _>(value));
+}
+
+fn test_bitvector<L: Unsigned>(path: &Path, valid: bool) where
+	Compact<VecArray<bool, L>>: Encode + Decode + IntoTree,
+{
+	check::<Compact<VecArray<bool, L>>, _>(path, valid, |value| tree_root::<Sha256, _>(value));
+}
@@ -1,14 +1,14 @@
 use std::fmt::Debug;
-use std::io::Write;
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize, de::DeserializeOwned};
-use beacon::{Config, MinimalConfig, MainnetConfig, BeaconState};
+use beacon::{Config, MinimalConfig, MainnetConfig, BeaconState, RuntimeConfig};
+use beacon::cache::TreeHashCache;
 use beacon::primitives::*;
 use beacon::types::*;
-use bm_le::{FromTree, IntoTree, DigestConstruct, InMemoryBackend};
+use bm_le::{FromTree, IntoTree, DigestConstruct, InMemoryBackend, tree_root};
 use ssz::{Encode, Decode};
 use sha2::Sha256;
-use crate::{test_name, read_raw_unwrap, read_value_unwrap};
+use crate::{read_raw_unwrap, read_value_unwrap};
 use crate::description::{TestNetwork, TestPhase, TestDescription, SszStaticType};
 
 #[derive(Deserialize, Debug)]
@@ -18,22 +18,22 @@ pub struct Roots {
 	pub signing_root: Option<H256>,
 }
 
-pub fn test(typ: SszStaticType, desc: TestDescription) {
+pub fn test(typ: SszStaticType, desc: TestDescription) -> bool {
 	match desc.network {
 		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
 		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
-		TestNetwork::General => unimplemented!("Not supported"),
+		TestNetwork::General => test_with_config::<RuntimeConfig<MinimalConfig>>(typ, desc),
 	}
 }
 
-pub fn test_with_config<C: Config>(typ: SszStaticType, desc: TestDescription) where
+pub fn test_with_config<C: Config>(typ: SszStaticType, desc: TestDescription) -> bool where
 	C: Serialize + DeserializeOwned,
 {
 	assert_eq!(desc.phase, TestPhase::Phase0);
 	let path = desc.path.clone().unwrap();
 
 	match typ {
-		SszStaticType::AggregateAndProof => println!("Skipped {}", test_name(path).unwrap()),
+		SszStaticType::AggregateAndProof => return false,
 		SszStaticType::Attestation => test_ssz::<C, Attestation<C>>(path),
 		SszStaticType::AttestationData => test_ssz::<C, AttestationData>(path),
 		SszStaticType::AttestationDataAndCustodyBit => test_ssz::<C, AttestationDataAndCustodyBit>(path),
@@ -41,7 +41,10 @@ pub fn test_with_config<C: Config>(typ: SszStaticType, desc: TestDescription) wh
 		SszStaticType::BeaconBlock => test_ssz::<C, BeaconBlock<C>>(path),
 		SszStaticType::BeaconBlockBody => test_ssz::<C, BeaconBlockBody<C>>(path),
 		SszStaticType::BeaconBlockHeader => test_ssz::<C, BeaconBlockHeader>(path),
-		SszStaticType::BeaconState => test_ssz::<C, BeaconState<C>>(path),
+		SszStaticType::BeaconState => {
+			test_ssz::<C, BeaconState<C>>(path.clone());
+			test_tree_hash_cache::<C>(path);
+		},
 		SszStaticType::Checkpoint => test_ssz::<C, Checkpoint>(path),
 		SszStaticType::Deposit => test_ssz::<C, Deposit>(path),
 		SszStaticType::DepositData => test_ssz::<C, DepositData>(path),
@@ -54,14 +57,13 @@ pub fn test_with_config<C: Config>(typ: SszStaticType, desc: TestDescription) wh
 		SszStaticType::Validator => test_ssz::<C, Validator>(path),
 		SszStaticType::VoluntaryExit => test_ssz::<C, VoluntaryExit>(path),
 	}
+
+	true
 }
 
 pub fn test_ssz<C: Config, T>(path: PathBuf) where
 	T: FromTree + IntoTree + Debug + Encode + Decode + Eq + DeserializeOwned,
 {
-	print!("Testing {} ...", test_name(&path).unwrap());
-	std::io::stdout().flush().ok().expect("Could not flush stdout");
-
 	let path = PathBuf::from(path);
 
 	let roots = {
@@ -94,6 +96,30 @@ pub fn test_ssz<C: Config, T>(path: PathBuf) where
 	assert_eq!(H256::from_slice(encoded_root.as_ref()), roots.root);
 	let decoded_root = T::from_tree(&encoded_root, &mut db).unwrap();
 	assert_eq!(decoded_root, value);
+}
+
+pub fn test_tree_hash_cache<C: Config>(path: PathBuf) where
+	C: Serialize + DeserializeOwned,
+{
+	let roots = {
+		let mut path = path.clone();
+		path.push("roots.yaml");
+
+		read_value_unwrap::<_, Roots>(path)
+	};
+
+	let value = {
+		let mut path = path.clone();
+		path.push("value.yaml");
+
+		read_value_unwrap::<_, BeaconState<C>>(path)
+	};
 
-	println!(" passed");
+	// Warm the cache with a different state, so that the test value is
+	// hashed incrementally.
+	let mut cache = TreeHashCache::new();
+	let default = BeaconState::<C>::default();
+	assert_eq!(cache.state_root(&default), tree_root::<C::Digest, _>(&default));
+	assert_eq!(cache.state_root(&value), roots.root);
+	assert_eq!(cache.state_root(&value), roots.root);
 }